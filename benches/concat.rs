@@ -0,0 +1,40 @@
+use grim::interpreter::interpreter::Interpreter;
+use grim::interpreter::io::{SharedBuffer, WriteOutput};
+use grim::parsing::grammar::ProgramParser;
+use grim::parsing::lexer::Lexer;
+use std::time::Instant;
+
+/// Program joining a line of parts in a loop, `JOIN` is replaced by the expression joining them.
+const PROGRAM: &str = r#"
+let part = "a part of the line, ";
+let mut i = 0;
+while i < 20000 {
+    let line = JOIN;
+    i = i + 1;
+}
+"#;
+
+/// Time per run of the program joining 16 parts with the given expression.
+fn time(join: &str) -> f64 {
+    let mut tree = ProgramParser::new()
+        .parse(Lexer::new(&PROGRAM.replace("JOIN", join)))
+        .unwrap();
+    let runs = 5;
+    let start = Instant::now();
+    for _ in 0..runs {
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(SharedBuffer::default()))
+            .build();
+        interpreter.run(&mut tree).unwrap();
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / runs as f64
+}
+
+fn main() {
+    let parts = vec!["part"; 16];
+    println!("concat +: {:.1} ms per run", time(&parts.join(" + ")));
+    println!(
+        "concat(): {:.1} ms per run",
+        time(&format!("concat({})", parts.join(", ")))
+    );
+}
@@ -0,0 +1,37 @@
+use grim::interpreter::interpreter::Interpreter;
+use grim::interpreter::io::{SharedBuffer, WriteOutput};
+use grim::parsing::grammar::ProgramParser;
+use grim::parsing::lexer::Lexer;
+use std::time::Instant;
+
+/// Program passing, comparing and printing strings in a loop.
+const PROGRAM: &str = r#"
+fn same (a, b) -> { return a == b; }
+let mut i = 0;
+let mut s = "grim";
+let mut equal = 0;
+while i < 100000 {
+    s = "grim is an interpreted language";
+    if same(s, "grim is an interpreted language") {
+        equal = equal + 1;
+    }
+    print(s);
+    i = i + 1;
+}
+"#;
+
+fn main() {
+    let mut tree = ProgramParser::new().parse(Lexer::new(PROGRAM)).unwrap();
+    let runs = 5;
+    let start = Instant::now();
+    for _ in 0..runs {
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(SharedBuffer::default()))
+            .build();
+        interpreter.run(&mut tree).unwrap();
+    }
+    println!(
+        "strings: {:.1} ms per run",
+        start.elapsed().as_secs_f64() * 1000.0 / runs as f64
+    );
+}
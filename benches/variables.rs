@@ -0,0 +1,39 @@
+use grim::interpreter::interpreter::Interpreter;
+use grim::interpreter::io::{SharedBuffer, WriteOutput};
+use grim::parsing::grammar::ProgramParser;
+use grim::parsing::lexer::Lexer;
+use std::time::Instant;
+
+/// Program reading variables of the enclosing blocks and of a function in a loop.
+const PROGRAM: &str = r#"
+fn mix (a, b, c) -> { return a + b * c - a + b * c - a + b * c - a + b * c - a; }
+let first = 1;
+let second = 2;
+let third = 3;
+let mut total = 0;
+let mut i = 0;
+while i < 100000 {
+    let local = first + second + third - first - second - third + first + second;
+    if local > third {
+        total = total + local - first + second - third + local - first + second - third;
+    }
+    total = total + mix(first, second, third) - local + first - second + third - local;
+    i = i + 1;
+}
+"#;
+
+fn main() {
+    let mut tree = ProgramParser::new().parse(Lexer::new(PROGRAM)).unwrap();
+    let runs = 5;
+    let start = Instant::now();
+    for _ in 0..runs {
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(SharedBuffer::default()))
+            .build();
+        interpreter.run(&mut tree).unwrap();
+    }
+    println!(
+        "variables: {:.1} ms per run",
+        start.elapsed().as_secs_f64() * 1000.0 / runs as f64
+    );
+}
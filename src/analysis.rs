@@ -0,0 +1,5 @@
+pub mod optimizer;
+pub mod resolver;
+pub mod slots;
+pub mod type_checker;
+pub mod warnings;
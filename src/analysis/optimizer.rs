@@ -0,0 +1,423 @@
+use crate::interpreter::interpreter::TypeVal;
+use crate::parsing::ast::{BinaryOperator, Expression, Statement, StatementKind, UnaryOperator};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::Arc;
+
+/// Work left to simplify an expression, as in the evaluator.
+enum Task<'a> {
+    Simplify(&'a Expression),
+    Binary(&'a BinaryOperator),
+    Unary(&'a UnaryOperator),
+    Index,
+    Call(&'a str, usize),
+}
+
+/// State of the optimization of a program.
+///
+/// assigned: variables that are assigned or read from the input somewhere in the program.
+///
+/// constants: literal value of the variables that are never reassigned, for each block seen by the current one.
+struct Optimizer {
+    assigned: HashSet<String>,
+    constants: Vec<HashMap<String, Expression>>,
+}
+
+/// Simplify a program before running it.
+///
+/// Variables declared with a literal and never reassigned are replaced by their value,
+/// operations between literals are computed and branches that can never run are dropped,
+/// as well as the statements following a return.
+pub fn optimize(tree: &[Statement]) -> Vec<Statement> {
+    let mut assigned = HashSet::new();
+    collect_assigned(tree, &mut assigned);
+    let mut optimizer = Optimizer {
+        assigned,
+        constants: vec![HashMap::new()],
+    };
+    optimizer.block(tree)
+}
+
+fn collect_assigned(tree: &[Statement], assigned: &mut HashSet<String>) {
+    for stmt in tree {
+        match &stmt.kind {
+            StatementKind::AssignmentStatement { name, .. }
+            | StatementKind::InputStatement { name } => {
+                assigned.insert(name.clone());
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                declaration: false,
+                ..
+            } => assigned.extend(names.iter().cloned()),
+            StatementKind::IfStatement { then_part, .. } => collect_assigned(then_part, assigned),
+            StatementKind::IfElseStatement {
+                then_part,
+                else_part,
+                ..
+            } => {
+                collect_assigned(then_part, assigned);
+                collect_assigned(else_part, assigned);
+            }
+            StatementKind::WhileStatement { body, .. }
+            | StatementKind::ForStatement { body, .. }
+            | StatementKind::MeasureStatement { body, .. } => collect_assigned(body, assigned),
+            StatementKind::FunctionDeclaration { body, .. } => collect_assigned(body, assigned),
+            _ => {}
+        }
+    }
+}
+
+impl Optimizer {
+    fn block(&mut self, tree: &[Statement]) -> Vec<Statement> {
+        let mut optimized = vec![];
+        let mut reachable = true;
+        for stmt in tree {
+            // Statements after a return, a break or a continue are never executed, the functions
+            // they declare are still declared when the block starts
+            if reachable || matches!(stmt.kind, StatementKind::FunctionDeclaration { .. }) {
+                optimized.extend(self.statement(stmt));
+            }
+            if let StatementKind::ReturnStatement { .. }
+            | StatementKind::BreakStatement { .. }
+            | StatementKind::ContinueStatement { .. } = stmt.kind
+            {
+                reachable = false;
+            }
+        }
+        optimized
+    }
+
+    fn nested_block(&mut self, tree: &[Statement]) -> Vec<Statement> {
+        self.constants.push(HashMap::new());
+        let optimized = self.block(tree);
+        self.constants.pop();
+        optimized
+    }
+
+    /// Simplify a statement, None if it can be dropped.
+    fn statement(&mut self, stmt: &Statement) -> Option<Statement> {
+        let kind = match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                type_annotation,
+                value,
+                mutable,
+            } => {
+                let value = self.expression(value);
+                if type_annotation.is_none()
+                    && !self.assigned.contains(name)
+                    && value_of(&value).is_some()
+                {
+                    if let Some(constants) = self.constants.last_mut() {
+                        constants.insert(name.clone(), (*value).clone());
+                    }
+                }
+                StatementKind::VariableDeclarationStatement {
+                    name: name.clone(),
+                    type_annotation: *type_annotation,
+                    value,
+                    mutable: *mutable,
+                }
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                StatementKind::AssignmentStatement {
+                    name: name.clone(),
+                    value: self.expression(value),
+                }
+            }
+            // The names are not propagated as constants, they are rarely read as such
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                mutable,
+            } => StatementKind::DestructuringStatement {
+                names: names.clone(),
+                values: values.iter().map(|value| self.expression(value)).collect(),
+                declaration: *declaration,
+                mutable: *mutable,
+            },
+            StatementKind::IfStatement { cond, then_part } => match *self.expression(cond) {
+                Expression::Bool(false) => return None,
+                cond => StatementKind::IfStatement {
+                    cond: Box::new(cond),
+                    then_part: self.nested_block(then_part),
+                },
+            },
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => match *self.expression(cond) {
+                // The branch taken still runs in its own block
+                Expression::Bool(true) => StatementKind::IfStatement {
+                    cond: Box::new(Expression::Bool(true)),
+                    then_part: self.nested_block(then_part),
+                },
+                Expression::Bool(false) => StatementKind::IfStatement {
+                    cond: Box::new(Expression::Bool(true)),
+                    then_part: self.nested_block(else_part),
+                },
+                cond => StatementKind::IfElseStatement {
+                    cond: Box::new(cond),
+                    then_part: self.nested_block(then_part),
+                    else_part: self.nested_block(else_part),
+                },
+            },
+            StatementKind::WhileStatement { cond, body, label } => match *self.expression(cond) {
+                Expression::Bool(false) => return None,
+                cond => StatementKind::WhileStatement {
+                    cond: Box::new(cond),
+                    body: self.nested_block(body),
+                    label: label.clone(),
+                },
+            },
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                label,
+            } => StatementKind::ForStatement {
+                variable: variable.clone(),
+                start: self.expression(start),
+                end: self.expression(end),
+                step: step.as_ref().map(|step| self.expression(step)),
+                body: self.nested_block(body),
+                label: label.clone(),
+            },
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                return_type,
+                body,
+                doc,
+            } => {
+                // Function bodies do not see the variables of the caller
+                let outer = mem::replace(&mut self.constants, vec![HashMap::new()]);
+                let body = self.block(body);
+                self.constants = outer;
+                StatementKind::FunctionDeclaration {
+                    name: name.clone(),
+                    arguments: arguments.clone(),
+                    return_type: *return_type,
+                    body: Arc::new(body),
+                    doc: doc.clone(),
+                }
+            }
+            StatementKind::FunctionCallStatement { name, arguments } => {
+                StatementKind::FunctionCallStatement {
+                    name: name.clone(),
+                    arguments: arguments.iter().map(|arg| self.expression(arg)).collect(),
+                }
+            }
+            StatementKind::SpawnStatement { name, arguments } => StatementKind::SpawnStatement {
+                name: name.clone(),
+                arguments: arguments.iter().map(|arg| self.expression(arg)).collect(),
+            },
+            // Kept even when empty, the label is still printed
+            StatementKind::MeasureStatement { label, body } => StatementKind::MeasureStatement {
+                label: label.clone(),
+                body: self.nested_block(body),
+            },
+            StatementKind::ReturnStatement { value } => StatementKind::ReturnStatement {
+                value: self.expression(value),
+            },
+            StatementKind::PrintStatement { content } => StatementKind::PrintStatement {
+                content: self.expression(content),
+            },
+            StatementKind::PrintLineStatement { content } => StatementKind::PrintLineStatement {
+                content: self.expression(content),
+            },
+            StatementKind::InputStatement { .. }
+            | StatementKind::BreakStatement { .. }
+            | StatementKind::ContinueStatement { .. }
+            | StatementKind::BreakpointStatement
+            | StatementKind::IncludeStatement { .. } => stmt.kind.clone(),
+        };
+        Some(Statement {
+            kind,
+            span: stmt.span,
+        })
+    }
+
+    /// Replace the constants read by an expression and compute the operations between literals.
+    fn expression(&self, expr: &Expression) -> Box<Expression> {
+        let mut tasks = vec![Task::Simplify(expr)];
+        let mut simplified: Vec<Expression> = vec![];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Simplify(Expression::BinaryOperation { lhs, operator, rhs }) => {
+                    tasks.push(Task::Binary(operator));
+                    tasks.push(Task::Simplify(rhs));
+                    tasks.push(Task::Simplify(lhs));
+                }
+                Task::Simplify(Expression::UnaryOperation { operator, rhs }) => {
+                    tasks.push(Task::Unary(operator));
+                    tasks.push(Task::Simplify(rhs));
+                }
+                Task::Simplify(Expression::Index { target, index }) => {
+                    tasks.push(Task::Index);
+                    tasks.push(Task::Simplify(index));
+                    tasks.push(Task::Simplify(target));
+                }
+                Task::Simplify(Expression::FunctionCall { name, arguments }) => {
+                    tasks.push(Task::Call(name, arguments.len()));
+                    tasks.extend(arguments.iter().rev().map(|arg| Task::Simplify(arg)));
+                }
+                Task::Simplify(Expression::Identifier(name)) => simplified.push(
+                    self.constants
+                        .iter()
+                        .rev()
+                        .find_map(|constants| constants.get(name))
+                        .cloned()
+                        .unwrap_or_else(|| Expression::Identifier(name.clone())),
+                ),
+                Task::Simplify(expr) => simplified.push(expr.clone()),
+                Task::Binary(operator) => {
+                    let rhs = simplified.pop().unwrap_or(Expression::Bool(false));
+                    let lhs = simplified.pop().unwrap_or(Expression::Bool(false));
+                    simplified.push(fold_binary(lhs, operator, rhs));
+                }
+                Task::Unary(operator) => {
+                    let rhs = simplified.pop().unwrap_or(Expression::Bool(false));
+                    simplified.push(fold_unary(operator, rhs));
+                }
+                Task::Index => {
+                    let index = simplified.pop().unwrap_or(Expression::Bool(false));
+                    let target = simplified.pop().unwrap_or(Expression::Bool(false));
+                    simplified.push(fold_index(target, index));
+                }
+                Task::Call(name, count) => {
+                    let arguments = simplified
+                        .split_off(simplified.len() - count)
+                        .into_iter()
+                        .map(Box::new)
+                        .collect();
+                    simplified.push(Expression::FunctionCall {
+                        name: name.to_string(),
+                        arguments,
+                    });
+                }
+            }
+        }
+        Box::new(simplified.pop().unwrap_or(Expression::Bool(false)))
+    }
+}
+
+fn value_of(expr: &Expression) -> Option<TypeVal> {
+    match expr {
+        Expression::Int(x) => Some(TypeVal::Int(*x)),
+        Expression::Float(x) => Some(TypeVal::Float(*x)),
+        Expression::Bool(x) => Some(TypeVal::Boolean(*x)),
+        Expression::Str(x) => Some(TypeVal::Str(x.as_str().into())),
+        Expression::Char(x) => Some(TypeVal::Char(*x)),
+        _ => None,
+    }
+}
+
+fn literal_of(value: TypeVal) -> Option<Expression> {
+    match value {
+        TypeVal::Int(x) => Some(Expression::Int(x)),
+        TypeVal::Float(x) => Some(Expression::Float(x)),
+        TypeVal::Boolean(x) => Some(Expression::Bool(x)),
+        TypeVal::Str(x) => Some(Expression::Str(x.to_string())),
+        TypeVal::Char(x) => Some(Expression::Char(x)),
+        TypeVal::Channel(_) | TypeVal::Decimal(_) | TypeVal::Set(_) => None,
+    }
+}
+
+/// Integer operations that would overflow or divide by zero are left to the runtime.
+fn is_foldable(left: &TypeVal, operator: &BinaryOperator, right: &TypeVal) -> bool {
+    match (left, operator, right) {
+        (TypeVal::Int(x), BinaryOperator::Add, TypeVal::Int(y)) => x.checked_add(*y).is_some(),
+        (TypeVal::Int(x), BinaryOperator::Sub, TypeVal::Int(y)) => x.checked_sub(*y).is_some(),
+        (TypeVal::Int(x), BinaryOperator::Mul, TypeVal::Int(y)) => x.checked_mul(*y).is_some(),
+        (TypeVal::Int(x), BinaryOperator::Div | BinaryOperator::Mod, TypeVal::Int(y)) => {
+            x.checked_rem(*y).is_some()
+        }
+        _ => true,
+    }
+}
+
+fn fold_binary(lhs: Expression, operator: &BinaryOperator, rhs: Expression) -> Expression {
+    if let (Some(left), Some(right)) = (value_of(&lhs), value_of(&rhs)) {
+        if is_foldable(&left, operator, &right) {
+            // Operations failing between literals keep failing at runtime
+            if let Some(literal) = left.binary(operator, &right).ok().and_then(literal_of) {
+                return literal;
+            }
+        }
+    }
+    Expression::BinaryOperation {
+        lhs: Box::new(lhs),
+        operator: operator.clone(),
+        rhs: Box::new(rhs),
+    }
+}
+
+/// Indexing out of the string is left to the runtime.
+fn fold_index(target: Expression, index: Expression) -> Expression {
+    if let (Some(target), Some(index)) = (value_of(&target), value_of(&index)) {
+        if let Some(literal) = target.index(&index).ok().and_then(literal_of) {
+            return literal;
+        }
+    }
+    Expression::Index {
+        target: Box::new(target),
+        index: Box::new(index),
+    }
+}
+
+fn fold_unary(operator: &UnaryOperator, rhs: Expression) -> Expression {
+    match (operator, value_of(&rhs)) {
+        (UnaryOperator::Minus, Some(TypeVal::Int(x))) if x.checked_neg().is_none() => {}
+        (_, Some(right)) => {
+            if let Some(literal) = right.unary(operator).ok().and_then(literal_of) {
+                return literal;
+            }
+        }
+        (_, None) => {}
+    }
+    Expression::UnaryOperation {
+        operator: operator.clone(),
+        rhs: Box::new(rhs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+    use crate::parsing::printer::print_program;
+
+    fn printed(src: &str, optimized: bool) -> String {
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        match optimized {
+            true => print_program(&optimize(&ast)),
+            false => print_program(&ast),
+        }
+    }
+
+    #[test]
+    fn propagates_constants_and_drops_dead_code() {
+        assert_eq!(
+            printed(
+                "let x = 3; let mut y = 1; y = 2; let z: float = 1; \
+                 if x < 5 { printl(x + 5); } else { printl(x); } if false { printl(x); } \
+                 while x == 4 { printl(x); } printl(y + z); printl(1 / 0); \
+                 fn f (x) -> { return x; printl(x); }",
+                true
+            ),
+            printed(
+                "let x = 3; let mut y = 1; y = 2; let z: float = 1; \
+                 if true { printl(8); } printl(y + z); printl(1 / 0); \
+                 fn f (x) -> { return x; }",
+                false
+            )
+        );
+    }
+}
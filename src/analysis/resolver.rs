@@ -0,0 +1,518 @@
+use crate::interpreter::builtins::{constant, is_builtin};
+use crate::interpreter::error_reporting::did_you_mean;
+use crate::parsing::ast::{declared_functions, Expression, Span, Statement, StatementKind};
+use crate::parsing::include::not_included;
+use std::collections::{HashMap, HashSet};
+
+/// Names declared in a block.
+///
+/// variables: the variables declared so far, with the statement declaring them.
+///
+/// immutable: the variables declared without mut.
+///
+/// later: the variables declared further down the block.
+#[derive(Default)]
+struct Block {
+    variables: HashMap<String, Span>,
+    immutable: HashSet<String>,
+    later: HashMap<String, Span>,
+    functions: HashSet<String>,
+}
+
+/// globals: functions declared at the top level of the program or outside of it, the function
+/// bodies can call them wherever they are declared.
+///
+/// loops: labels of the loops around the statement, from the outermost.
+///
+/// ended: variables of the blocks that ended in the current function, with their declaration.
+struct Resolver<'src> {
+    src: &'src str,
+    blocks: Vec<Block>,
+    globals: HashSet<String>,
+    loops: Vec<Option<String>>,
+    ended: HashMap<String, Span>,
+    span: Span,
+    errors: Vec<String>,
+}
+
+/// Walk the program before execution and report every reference to an undefined variable or function.
+///
+/// Visibility follows the interpreter: a block sees the variables declared before it in the father
+/// blocks and all their functions, a function body only sees its arguments, itself, what it
+/// declares and the global functions.
+pub fn resolve_names(tree: &Vec<Statement>, src: &str) -> Vec<String> {
+    resolve_names_with(tree, src, HashSet::new())
+}
+
+/// Same as resolve_names, functions are declared outside of the program, as the native ones.
+pub fn resolve_names_with(
+    tree: &Vec<Statement>,
+    src: &str,
+    functions: HashSet<String>,
+) -> Vec<String> {
+    resolve_names_after(tree, src, functions, HashMap::new())
+}
+
+/// Same as resolve_names_with, the program continues a session whose global variables are
+/// declared, with whether they can be assigned.
+pub fn resolve_names_after(
+    tree: &Vec<Statement>,
+    src: &str,
+    functions: HashSet<String>,
+    variables: HashMap<String, bool>,
+) -> Vec<String> {
+    let declared = declared_functions(tree).cloned();
+    let mut resolver = Resolver {
+        src,
+        blocks: vec![],
+        globals: functions.iter().cloned().chain(declared).collect(),
+        loops: vec![],
+        ended: HashMap::new(),
+        span: Span::default(),
+        errors: vec![],
+    };
+    resolver.resolve_block(
+        tree,
+        Block {
+            immutable: variables
+                .iter()
+                .filter(|(_, &mutable)| !mutable)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            variables: variables
+                .into_keys()
+                .map(|name| (name, Span::default()))
+                .collect(),
+            functions,
+            ..Default::default()
+        },
+    );
+    resolver.errors
+}
+
+impl<'src> Resolver<'src> {
+    fn resolve_block(&mut self, tree: &Vec<Statement>, mut block: Block) {
+        // Functions can be called before their declaration
+        block.functions.extend(declared_functions(tree).cloned());
+        block.later = declared_variables(tree);
+        self.blocks.push(block);
+        for stmt in tree {
+            self.span = stmt.span;
+            self.resolve_statement(stmt);
+        }
+        if let Some(block) = self.blocks.pop() {
+            self.ended.extend(block.variables);
+        }
+    }
+
+    fn resolve_statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                value,
+                mutable,
+                ..
+            } => {
+                self.resolve_expression(value);
+                self.declare_variable(name, *mutable);
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                self.resolve_expression(value);
+                self.resolve_assignment(name);
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                mutable,
+            } => {
+                for value in values {
+                    self.resolve_expression(value);
+                }
+                if names.len() != values.len() {
+                    self.report(format!(
+                        "{} names are bound to {} values",
+                        names.len(),
+                        values.len()
+                    ));
+                }
+                for (i, name) in names.iter().enumerate() {
+                    if names[..i].contains(name) {
+                        self.report(format!("Variable `{}` is bound twice", name));
+                    } else if *declaration {
+                        self.declare_variable(name, *mutable);
+                    } else {
+                        self.resolve_assignment(name);
+                    }
+                }
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                self.resolve_expression(cond);
+                self.resolve_block(then_part, Block::default());
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                self.resolve_expression(cond);
+                self.resolve_block(then_part, Block::default());
+                self.resolve_block(else_part, Block::default());
+            }
+            StatementKind::WhileStatement { cond, body, label } => {
+                self.resolve_expression(cond);
+                self.resolve_loop(body, Block::default(), label);
+            }
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                label,
+            } => {
+                for bound in [Some(start), Some(end), step.as_ref()]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.resolve_expression(bound);
+                }
+                self.reserve_name(variable);
+                let loop_block = Block {
+                    variables: HashMap::from([(variable.clone(), stmt.span)]),
+                    ..Default::default()
+                };
+                self.resolve_loop(body, loop_block, label);
+            }
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                body,
+                ..
+            } => {
+                if let Some(block) = self.blocks.last_mut() {
+                    block.functions.insert(name.clone());
+                }
+                for arg in arguments {
+                    self.reserve_name(&arg.name);
+                }
+                let function_block = Block {
+                    variables: arguments
+                        .iter()
+                        .map(|arg| (arg.name.clone(), stmt.span))
+                        .collect(),
+                    functions: HashSet::from([name.clone()]),
+                    ..Default::default()
+                };
+                // The loops around the declaration cannot be left from the body
+                let globals = Block {
+                    functions: self.globals.clone(),
+                    ..Default::default()
+                };
+                let outer_blocks = std::mem::replace(&mut self.blocks, vec![globals]);
+                let outer_loops = std::mem::take(&mut self.loops);
+                let outer_ended = std::mem::take(&mut self.ended);
+                self.resolve_block(body, function_block);
+                self.blocks = outer_blocks;
+                self.loops = outer_loops;
+                self.ended = outer_ended;
+                self.span = stmt.span;
+            }
+            StatementKind::FunctionCallStatement { name, arguments }
+            | StatementKind::SpawnStatement { name, arguments } => {
+                self.resolve_function(name);
+                for arg in arguments {
+                    self.resolve_expression(arg);
+                }
+            }
+            StatementKind::ReturnStatement { value } => self.resolve_expression(value),
+            StatementKind::PrintStatement { content }
+            | StatementKind::PrintLineStatement { content } => self.resolve_expression(content),
+            StatementKind::InputStatement { name } => self.resolve_assignment(name),
+            StatementKind::BreakStatement { label } => self.resolve_loop_exit("break", label),
+            StatementKind::ContinueStatement { label } => self.resolve_loop_exit("continue", label),
+            StatementKind::BreakpointStatement => {}
+            StatementKind::IncludeStatement { path } => self.report(not_included(path)),
+            StatementKind::MeasureStatement { body, .. } => {
+                self.resolve_block(body, Block::default())
+            }
+        }
+    }
+
+    fn resolve_loop(&mut self, body: &Vec<Statement>, block: Block, label: &Option<String>) {
+        if label.is_some() && self.loops.contains(label) {
+            self.report(format!(
+                "Label `{}` is already used by an enclosing loop",
+                label.as_deref().unwrap_or_default()
+            ));
+        }
+        self.loops.push(label.clone());
+        self.resolve_block(body, block);
+        self.loops.pop();
+    }
+
+    fn resolve_loop_exit(&mut self, keyword: &str, label: &Option<String>) {
+        match label {
+            _ if self.loops.is_empty() => self.report(format!("`{}` is not in a loop", keyword)),
+            Some(name) if !self.loops.contains(label) => {
+                let suggestion = did_you_mean(name, self.loops.iter().flatten());
+                self.report(format!("Loop `{}` does not exist{}", name, suggestion));
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                self.resolve_variable(name)
+            }
+            Expression::FunctionCall { name, arguments } => {
+                self.resolve_function(name);
+                for arg in arguments {
+                    self.resolve_expression(arg);
+                }
+            }
+            Expression::BinaryOperation { lhs, rhs, .. } => {
+                self.resolve_expression(lhs);
+                self.resolve_expression(rhs);
+            }
+            Expression::UnaryOperation { rhs, .. } => self.resolve_expression(rhs),
+            Expression::Index { target, index } => {
+                self.resolve_expression(target);
+                self.resolve_expression(index);
+            }
+            Expression::Float(_)
+            | Expression::Int(_)
+            | Expression::Str(_)
+            | Expression::Char(_)
+            | Expression::Bool(_) => {}
+        }
+    }
+
+    /// Report a variable declared with the name of a constant.
+    fn reserve_name(&mut self, name: &str) {
+        if constant(name).is_some() {
+            self.report(format!(
+                "`{}` is a constant, a variable cannot have its name",
+                name
+            ));
+        }
+    }
+
+    fn declare_variable(&mut self, name: &str, mutable: bool) {
+        self.reserve_name(name);
+        if let Some(block) = self.blocks.last_mut() {
+            block.later.remove(name);
+            block.variables.insert(name.to_string(), self.span);
+            if !mutable {
+                block.immutable.insert(name.to_string());
+            }
+        }
+    }
+
+    /// Report an assignment to a variable that does not exist or is declared without mut.
+    fn resolve_assignment(&mut self, name: &str) {
+        self.resolve_variable(name);
+        let declared = self
+            .blocks
+            .iter()
+            .rev()
+            .find(|block| block.variables.contains_key(name));
+        if declared.is_none() && constant(name).is_some() {
+            self.report(format!("`{}` is a constant, it cannot be assigned", name));
+        } else if declared.is_some_and(|block| block.immutable.contains(name)) {
+            self.report(format!(
+                "Variable `{}` is immutable, declare it with `let mut {}` to assign it",
+                name, name
+            ));
+        }
+    }
+
+    /// Report a variable that is not declared before the statement in the blocks around it.
+    ///
+    /// A variable declared further down or in a block that ended is reported with its declaration.
+    fn resolve_variable(&mut self, name: &str) {
+        if constant(name).is_some()
+            || self
+                .blocks
+                .iter()
+                .any(|block| block.variables.contains_key(name))
+        {
+            return;
+        }
+        let later = self
+            .blocks
+            .iter()
+            .rev()
+            .find_map(|block| block.later.get(name));
+        let message = if let Some(declaration) = later {
+            format!(
+                "Variable `{}` is used before its declaration at line {}",
+                name,
+                declaration.line_and_column(self.src).0
+            )
+        } else if let Some(declaration) = self.ended.get(name) {
+            format!(
+                "Variable `{}` is only declared at line {}, in a block that ended before this statement",
+                name,
+                declaration.line_and_column(self.src).0
+            )
+        } else {
+            let suggestion = did_you_mean(
+                name,
+                self.blocks.iter().flat_map(|block| block.variables.keys()),
+            );
+            format!("Variable `{}` does not exist{}", name, suggestion)
+        };
+        self.report(message);
+    }
+
+    fn resolve_function(&mut self, name: &str) {
+        if !is_builtin(name)
+            && !self
+                .blocks
+                .iter()
+                .any(|block| block.functions.contains(name))
+        {
+            let suggestion = did_you_mean(
+                name,
+                self.blocks.iter().flat_map(|block| block.functions.iter()),
+            );
+            self.report(format!("Function `{}` does not exist{}", name, suggestion));
+        }
+    }
+
+    fn report(&mut self, message: String) {
+        let (line, column) = self.span.line_and_column(self.src);
+        self.errors
+            .push(format!("Line {}, column {}: {}", line, column, message));
+    }
+}
+
+/// Variables declared by the statements of a block, with the first statement declaring them.
+fn declared_variables(tree: &[Statement]) -> HashMap<String, Span> {
+    let mut variables = HashMap::new();
+    for stmt in tree {
+        let names = match &stmt.kind {
+            StatementKind::VariableDeclarationStatement { name, .. } => vec![name],
+            StatementKind::DestructuringStatement {
+                names,
+                declaration: true,
+                ..
+            } => names.iter().collect(),
+            _ => vec![],
+        };
+        for name in names {
+            variables.entry(name.clone()).or_insert(stmt.span);
+        }
+    }
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    fn errors_of(src: &str) -> Vec<String> {
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        resolve_names(&ast, src)
+    }
+
+    #[test]
+    fn reports_undefined_names_on_any_path() {
+        let errors =
+            errors_of("let counter = 0;\nif false {\n    printl(contuer);\n}\nf(counter);");
+        assert_eq!(
+            errors,
+            vec![
+                "Line 3, column 5: Variable `contuer` does not exist; did you mean `counter`?"
+                    .to_string(),
+                "Line 5, column 1: Function `f` does not exist".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_interpreter_visibility() {
+        let errors = errors_of(
+            "let a = 1; fn f (x) -> { if x > 0 { return f(x - 1); } return a; } \
+             if true { let b = 2; } printl(b); printl(f(a));",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Line 1, column 56: Variable `a` does not exist".to_string(),
+                "Line 1, column 91: Variable `b` is only declared at line 1, in a block that ended \
+                 before this statement"
+                    .to_string(),
+            ]
+        );
+        // Function bodies call the global functions, declared before or after them
+        let errors = errors_of(
+            "fn f () -> { return g() + h(); } \
+             if true { fn h () -> { return 1; } } fn g () -> { return 2; }",
+        );
+        assert_eq!(
+            errors,
+            vec!["Line 1, column 14: Function `h` does not exist".to_string()]
+        );
+    }
+
+    #[test]
+    fn reports_assignments_to_immutable_variables() {
+        let errors = errors_of(
+            "let a = 1; let mut b = 2; b = a;\nif true { a = b; }\nlet (c, d) = (1, 2); input(c);",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Line 2, column 11: Variable `a` is immutable, declare it with `let mut a` to assign it"
+                    .to_string(),
+                "Line 3, column 22: Variable `c` is immutable, declare it with `let mut c` to assign it"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reserves_the_names_of_the_constants() {
+        let errors =
+            errors_of("let area = PI * 2;\nlet mut E = 1;\nPI = 3;\nfn f (INF) -> { return NAN; }");
+        assert_eq!(
+            errors,
+            vec![
+                "Line 2, column 1: `E` is a constant, a variable cannot have its name".to_string(),
+                "Line 3, column 1: `PI` is a constant, it cannot be assigned".to_string(),
+                "Line 4, column 1: `INF` is a constant, a variable cannot have its name"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_reads_of_uninitialized_variables() {
+        let errors = errors_of(
+            "if true {\n    let found = 1;\n} else {\n    printl(0);\n}\nprintl(found);\n\
+             let total = total + limit;\nwhile true { printl(limit); }\nlet limit = 3;\n\
+             fn f () -> { let local = 1; return local; }\nprintl(local);",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Line 6, column 1: Variable `found` is only declared at line 2, in a block that \
+                 ended before this statement"
+                    .to_string(),
+                "Line 7, column 1: Variable `total` is used before its declaration at line 7"
+                    .to_string(),
+                "Line 7, column 1: Variable `limit` is used before its declaration at line 9"
+                    .to_string(),
+                "Line 8, column 14: Variable `limit` is used before its declaration at line 9"
+                    .to_string(),
+                "Line 11, column 1: Variable `local` does not exist".to_string(),
+            ]
+        );
+    }
+}
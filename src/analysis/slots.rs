@@ -0,0 +1,220 @@
+use crate::parsing::ast::{Expression, Statement, StatementKind};
+use std::sync::Arc;
+
+/// Replace the variables read by the program with the slot holding their value.
+///
+/// Each block stores its variables in declaration order, so a variable is found `depth` blocks
+/// above the reading one at the index of its declaration. A function body starts from its arguments.
+/// The tree is resolved in place, the identifiers resolved by an earlier run are resolved again.
+/// globals: variables already bound in the scope running the program.
+pub fn resolve_slots(tree: &mut [Statement], globals: Vec<String>) {
+    let mut blocks = vec![globals];
+    resolve_block(tree, &mut blocks);
+}
+
+fn resolve_block(tree: &mut [Statement], blocks: &mut Vec<Vec<String>>) {
+    for stmt in tree {
+        resolve_statement(stmt, blocks);
+    }
+}
+
+fn resolve_nested_block(tree: &mut [Statement], blocks: &mut Vec<Vec<String>>) {
+    blocks.push(vec![]);
+    resolve_block(tree, blocks);
+    blocks.pop();
+}
+
+fn resolve_statement(stmt: &mut Statement, blocks: &mut Vec<Vec<String>>) {
+    match &mut stmt.kind {
+        StatementKind::VariableDeclarationStatement { name, value, .. } => {
+            resolve_expression(value, blocks);
+            if let Some(block) = blocks.last_mut() {
+                if !block.contains(name) {
+                    block.push(name.clone());
+                }
+            }
+        }
+        StatementKind::DestructuringStatement {
+            names,
+            values,
+            declaration,
+            ..
+        } => {
+            for value in values {
+                resolve_expression(value, blocks);
+            }
+            if let (true, Some(block)) = (*declaration, blocks.last_mut()) {
+                for name in names {
+                    if !block.contains(name) {
+                        block.push(name.clone());
+                    }
+                }
+            }
+        }
+        StatementKind::AssignmentStatement { value, .. }
+        | StatementKind::ReturnStatement { value }
+        | StatementKind::PrintStatement { content: value }
+        | StatementKind::PrintLineStatement { content: value } => resolve_expression(value, blocks),
+        StatementKind::IfStatement { cond, then_part } => {
+            resolve_expression(cond, blocks);
+            resolve_nested_block(then_part, blocks);
+        }
+        StatementKind::IfElseStatement {
+            cond,
+            then_part,
+            else_part,
+        } => {
+            resolve_expression(cond, blocks);
+            resolve_nested_block(then_part, blocks);
+            resolve_nested_block(else_part, blocks);
+        }
+        StatementKind::WhileStatement { cond, body, .. } => {
+            resolve_expression(cond, blocks);
+            resolve_nested_block(body, blocks);
+        }
+        StatementKind::MeasureStatement { body, .. } => resolve_nested_block(body, blocks),
+        StatementKind::ForStatement {
+            variable,
+            start,
+            end,
+            step,
+            body,
+            ..
+        } => {
+            for bound in [Some(start), Some(end), step.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                resolve_expression(bound, blocks);
+            }
+            // Each iteration has its own block, starting with the variable
+            blocks.push(vec![variable.clone()]);
+            resolve_block(body, blocks);
+            blocks.pop();
+        }
+        StatementKind::FunctionDeclaration {
+            arguments, body, ..
+        } => {
+            // Function bodies only see their arguments
+            let mut parameters: Vec<String> = vec![];
+            for arg in arguments.iter() {
+                if !parameters.contains(&arg.name) {
+                    parameters.push(arg.name.clone());
+                }
+            }
+            let mut function_blocks = vec![parameters];
+            resolve_block(Arc::make_mut(body).as_mut_slice(), &mut function_blocks);
+        }
+        StatementKind::FunctionCallStatement { arguments, .. }
+        | StatementKind::SpawnStatement { arguments, .. } => {
+            for arg in arguments {
+                resolve_expression(arg, blocks);
+            }
+        }
+        StatementKind::InputStatement { .. }
+        | StatementKind::BreakStatement { .. }
+        | StatementKind::ContinueStatement { .. }
+        | StatementKind::BreakpointStatement
+        | StatementKind::IncludeStatement { .. } => {}
+    }
+}
+
+fn resolve_expression(expr: &mut Expression, blocks: &[Vec<String>]) {
+    // Operands are visited from a work list so that nested expressions do not grow the stack
+    let mut pending = vec![expr];
+    while let Some(expr) = pending.pop() {
+        match expr {
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                let found = blocks.iter().rev().enumerate().find_map(|(depth, block)| {
+                    let slot = block.iter().position(|variable| variable == name)?;
+                    Some((depth, slot))
+                });
+                let name = std::mem::take(name);
+                *expr = match found {
+                    Some((depth, slot)) => Expression::ResolvedIdentifier { name, depth, slot },
+                    None => Expression::Identifier(name),
+                };
+            }
+            Expression::FunctionCall { arguments, .. } => {
+                pending.extend(arguments.iter_mut().map(|arg| arg.as_mut()))
+            }
+            Expression::BinaryOperation { lhs, rhs, .. } => {
+                pending.push(lhs);
+                pending.push(rhs);
+            }
+            Expression::UnaryOperation { rhs, .. } => pending.push(rhs),
+            Expression::Index { target, index } => {
+                pending.push(target);
+                pending.push(index);
+            }
+            Expression::Float(_)
+            | Expression::Int(_)
+            | Expression::Str(_)
+            | Expression::Char(_)
+            | Expression::Bool(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+    use crate::parsing::printer::print_expression;
+
+    /// Print the resolved identifiers of the printed values as name@depth:slot.
+    fn printed_slots(src: &str, globals: &[&str]) -> Vec<String> {
+        let mut ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        let globals = globals.iter().map(|name| name.to_string()).collect();
+        resolve_slots(&mut ast, globals);
+        let mut printed = vec![];
+        collect_printed(&ast, &mut printed);
+        printed
+    }
+
+    fn collect_printed(tree: &[Statement], printed: &mut Vec<String>) {
+        for stmt in tree {
+            match &stmt.kind {
+                StatementKind::PrintLineStatement { content } => printed.push(match &**content {
+                    Expression::ResolvedIdentifier { name, depth, slot } => {
+                        format!("{}@{}:{}", name, depth, slot)
+                    }
+                    other => print_expression(other),
+                }),
+                StatementKind::IfStatement {
+                    then_part: body, ..
+                }
+                | StatementKind::WhileStatement { body, .. }
+                | StatementKind::MeasureStatement { body, .. } => collect_printed(body, printed),
+                StatementKind::FunctionDeclaration { body, .. } => collect_printed(body, printed),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_depth_and_slot() {
+        let printed = printed_slots(
+            "let a = 1; let b = 2; if a > 0 { let c = 3; printl(c); printl(b); } \
+             fn f (x, y) -> { printl(y); printl(g); return x; } printl(z); printl(b);",
+            &["z"],
+        );
+        assert_eq!(
+            printed,
+            vec!["c@0:0", "b@1:2", "y@0:1", "g", "z@0:0", "b@0:2"]
+        );
+    }
+
+    #[test]
+    fn resolves_a_resolved_tree_again() {
+        let mut ast = ProgramParser::new()
+            .parse(Lexer::new("printl(a); printl(b);"))
+            .unwrap();
+        resolve_slots(&mut ast, vec!["a".to_string()]);
+        resolve_slots(&mut ast, vec!["b".to_string()]);
+        let mut printed = vec![];
+        collect_printed(&ast, &mut printed);
+        assert_eq!(printed, vec!["a", "b@0:0"]);
+    }
+}
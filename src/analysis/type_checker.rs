@@ -0,0 +1,435 @@
+use crate::interpreter::builtins::constant;
+use crate::interpreter::coercion::{coerces, common_type, is_number};
+use crate::parsing::ast::{
+    BinaryOperator, Expression, Parameter, Statement, StatementKind, Type, UnaryOperator,
+};
+use std::collections::HashMap;
+
+/// Inferred type of an expression, None when it can only be known at runtime.
+type Inferred = Option<Type>;
+
+#[derive(Default)]
+struct TypeChecker {
+    blocks: Vec<HashMap<String, Inferred>>,
+    functions: HashMap<String, (Vec<Parameter>, Option<Type>)>,
+    return_type: Option<Type>,
+    errors: Vec<String>,
+}
+
+/// Infer the types of the program and report every mismatch found.
+pub fn check_types(tree: &Vec<Statement>) -> Vec<String> {
+    let mut checker = TypeChecker::default();
+    checker.check_block(tree, HashMap::new());
+    checker.errors
+}
+
+/// Infer the type of an expression reading the given variables and calling the given functions,
+/// None when it is only known at runtime.
+pub fn infer_type(
+    expr: &Expression,
+    variables: HashMap<String, Type>,
+    functions: HashMap<String, (Vec<Parameter>, Option<Type>)>,
+) -> Result<Option<Type>, Vec<String>> {
+    let mut checker = TypeChecker {
+        blocks: vec![variables
+            .into_iter()
+            .map(|(name, variable_type)| (name, Some(variable_type)))
+            .collect()],
+        functions,
+        ..Default::default()
+    };
+    let inferred = checker.infer(expr);
+    match checker.errors.is_empty() {
+        true => Ok(inferred),
+        false => Err(checker.errors),
+    }
+}
+
+impl TypeChecker {
+    fn check_block(&mut self, tree: &Vec<Statement>, variables: HashMap<String, Inferred>) {
+        // Functions can be called before their declaration
+        for stmt in tree {
+            if let StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                return_type,
+                ..
+            } = &stmt.kind
+            {
+                self.functions
+                    .insert(name.clone(), (arguments.clone(), *return_type));
+            }
+        }
+        self.blocks.push(variables);
+        for stmt in tree {
+            self.check_statement(stmt);
+        }
+        self.blocks.pop();
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => {
+                let inferred = self.infer(value);
+                let inferred = match type_annotation {
+                    Some(annotation) => {
+                        self.check_annotation(name, *annotation, inferred);
+                        Some(*annotation)
+                    }
+                    None => inferred,
+                };
+                if let Some(variables) = self.blocks.last_mut() {
+                    variables.insert(name.clone(), inferred);
+                }
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                let inferred = self.infer(value);
+                self.check_assignment(name, inferred);
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                ..
+            } => {
+                let inferred: Vec<Inferred> =
+                    values.iter().map(|value| self.infer(value)).collect();
+                for (name, inferred) in names.iter().zip(inferred) {
+                    match (declaration, self.blocks.last_mut()) {
+                        (true, Some(variables)) => {
+                            variables.insert(name.clone(), inferred);
+                        }
+                        (true, None) => {}
+                        (false, _) => self.check_assignment(name, inferred),
+                    }
+                }
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                self.check_condition(cond, "if");
+                self.check_block(then_part, HashMap::new());
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                self.check_condition(cond, "if");
+                self.check_block(then_part, HashMap::new());
+                self.check_block(else_part, HashMap::new());
+            }
+            StatementKind::WhileStatement { cond, body, .. } => {
+                self.check_condition(cond, "while");
+                self.check_block(body, HashMap::new());
+            }
+            StatementKind::MeasureStatement { body, .. } => self.check_block(body, HashMap::new()),
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                for bound in [Some(start), Some(end), step.as_ref()]
+                    .into_iter()
+                    .flatten()
+                {
+                    match self.infer(bound) {
+                        Some(Type::Int) | None => (),
+                        Some(other) => self.errors.push(format!(
+                            "The bounds and the step of a for loop must be ints, a {} is given",
+                            other
+                        )),
+                    }
+                }
+                self.check_block(body, HashMap::from([(variable.clone(), Some(Type::Int))]));
+            }
+            StatementKind::FunctionDeclaration {
+                arguments,
+                return_type,
+                body,
+                ..
+            } => {
+                // Function bodies only see their arguments, unannotated ones are known at call time
+                let outer_blocks = std::mem::take(&mut self.blocks);
+                let outer_return_type = std::mem::replace(&mut self.return_type, *return_type);
+                let arguments = arguments
+                    .iter()
+                    .map(|arg| (arg.name.clone(), arg.type_annotation))
+                    .collect();
+                self.check_block(body, arguments);
+                self.blocks = outer_blocks;
+                self.return_type = outer_return_type;
+            }
+            StatementKind::FunctionCallStatement { name, arguments }
+            | StatementKind::SpawnStatement { name, arguments } => {
+                self.check_call(name, arguments);
+            }
+            StatementKind::ReturnStatement { value } => {
+                let inferred = self.infer(value);
+                if let Some(return_type) = self.return_type {
+                    self.check_annotation("return value", return_type, inferred);
+                }
+            }
+            StatementKind::PrintStatement { content }
+            | StatementKind::PrintLineStatement { content } => {
+                self.infer(content);
+            }
+            StatementKind::InputStatement { .. }
+            | StatementKind::BreakStatement { .. }
+            | StatementKind::ContinueStatement { .. }
+            | StatementKind::BreakpointStatement
+            | StatementKind::IncludeStatement { .. } => {}
+        }
+    }
+
+    fn check_annotation(&mut self, name: &str, annotation: Type, inferred: Inferred) {
+        match (annotation, inferred) {
+            (_, None) => (),
+            (annotation, Some(inferred)) if coerces(inferred, annotation) => (),
+            (annotation, Some(inferred)) => self.errors.push(format!(
+                "({}) is declared as {} but a {} is given",
+                name, annotation, inferred
+            )),
+        }
+    }
+
+    /// Check the arguments of a call and return the declared return type of the function.
+    fn check_call(&mut self, name: &str, arguments: &[Box<Expression>]) -> Inferred {
+        let inferred: Vec<Inferred> = arguments.iter().map(|arg| self.infer(arg)).collect();
+        let (parameters, return_type) = self.functions.get(name).cloned()?;
+        for (parameter, inferred) in parameters.iter().zip(inferred) {
+            if let Some(annotation) = parameter.type_annotation {
+                self.check_annotation(&parameter.name, annotation, inferred);
+            }
+        }
+        return_type
+    }
+
+    fn check_condition(&mut self, cond: &Expression, construct: &str) {
+        match self.infer(cond) {
+            None => (),
+            Some(inferred) if coerces(inferred, Type::Bool) => (),
+            Some(other) => self.errors.push(format!(
+                "{} cannot be used as {} condition",
+                other, construct
+            )),
+        }
+    }
+
+    fn check_assignment(&mut self, name: &str, inferred: Inferred) {
+        if let (Some(declared), Some(assigned)) = (self.lookup(name), inferred) {
+            if !coerces(assigned, declared) {
+                self.errors.push(format!(
+                    "Variable ({}) is a {} but a {} is assigned to it",
+                    name, declared, assigned
+                ));
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Inferred {
+        self.blocks
+            .iter()
+            .rev()
+            .find_map(|variables| variables.get(name).copied())
+            .flatten()
+    }
+
+    fn infer(&mut self, expr: &Expression) -> Inferred {
+        match expr {
+            Expression::Int(_) => Some(Type::Int),
+            Expression::Float(_) => Some(Type::Float),
+            Expression::Bool(_) => Some(Type::Bool),
+            Expression::Str(_) => Some(Type::Str),
+            Expression::Char(_) => Some(Type::Char),
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                match constant(name) {
+                    Some(_) => Some(Type::Float),
+                    None => self.lookup(name),
+                }
+            }
+            Expression::FunctionCall { name, arguments } => self.check_call(name, arguments),
+            Expression::UnaryOperation { operator, rhs } => {
+                let rhs = self.infer(rhs)?;
+                match (operator, rhs) {
+                    (UnaryOperator::Minus, rhs) if is_number(rhs) => Some(rhs),
+                    (UnaryOperator::Not, Type::Bool) => Some(Type::Bool),
+                    (UnaryOperator::Minus, _) => {
+                        self.mismatch(format!("Minus {} is not supported", rhs))
+                    }
+                    (UnaryOperator::Not, _) => {
+                        self.mismatch(format!("Not {} is not supported", rhs))
+                    }
+                }
+            }
+            Expression::BinaryOperation { lhs, operator, rhs } => {
+                let lhs = self.infer(lhs);
+                let rhs = self.infer(rhs);
+                self.infer_binary(lhs, operator, rhs)
+            }
+            Expression::Index { target, index } => {
+                let target = self.infer(target);
+                let index = self.infer(index);
+                match (target, index) {
+                    (None | Some(Type::Str), None | Some(Type::Int)) => Some(Type::Char),
+                    (Some(Type::Str), Some(index)) => {
+                        self.mismatch(format!("A string is indexed by an int, not by a {}", index))
+                    }
+                    (Some(target), _) => {
+                        self.mismatch(format!("Values of type {} cannot be indexed", target))
+                    }
+                    (None, Some(index)) => {
+                        self.mismatch(format!("A string is indexed by an int, not by a {}", index))
+                    }
+                }
+            }
+        }
+    }
+
+    fn infer_binary(
+        &mut self,
+        lhs: Inferred,
+        operator: &BinaryOperator,
+        rhs: Inferred,
+    ) -> Inferred {
+        let (lhs, rhs) = match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            // The result type of logic operators does not depend on the operands
+            _ => {
+                return match operator {
+                    BinaryOperator::Add
+                    | BinaryOperator::Sub
+                    | BinaryOperator::Mul
+                    | BinaryOperator::Div
+                    | BinaryOperator::Mod => None,
+                    _ => Some(Type::Bool),
+                }
+            }
+        };
+        // The operands of numbers are converted to their common type
+        let common = common_type(lhs, rhs);
+        let number = common.filter(|t| is_number(*t));
+        match operator {
+            BinaryOperator::Add if (lhs, rhs) == (Type::Str, Type::Str) => Some(Type::Str),
+            BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul => match number {
+                Some(number) => Some(number),
+                None => self.binary_mismatch(operator, lhs, rhs),
+            },
+            BinaryOperator::Div => match number {
+                // Int division is exact only when the remainder is zero
+                Some(Type::Int) => None,
+                Some(Type::Float) => Some(Type::Float),
+                _ => self.binary_mismatch(operator, lhs, rhs),
+            },
+            BinaryOperator::Mod => match (lhs, rhs) {
+                (Type::Int, Type::Int) => Some(Type::Int),
+                _ => self.binary_mismatch(operator, lhs, rhs),
+            },
+            BinaryOperator::And | BinaryOperator::Or => match (lhs, rhs) {
+                (Type::Bool, Type::Bool) => Some(Type::Bool),
+                _ => self.binary_mismatch(operator, lhs, rhs),
+            },
+            BinaryOperator::Less
+            | BinaryOperator::Greater
+            | BinaryOperator::LessEq
+            | BinaryOperator::GreaterEq => {
+                if number.is_some()
+                    || (lhs, rhs) == (Type::Str, Type::Str)
+                    || (lhs, rhs) == (Type::Char, Type::Char)
+                {
+                    Some(Type::Bool)
+                } else {
+                    self.binary_mismatch(operator, lhs, rhs)
+                }
+            }
+            BinaryOperator::CompareEq | BinaryOperator::CompareNeq => {
+                if common.is_some() {
+                    Some(Type::Bool)
+                } else {
+                    self.binary_mismatch(operator, lhs, rhs)
+                }
+            }
+        }
+    }
+
+    fn binary_mismatch(&mut self, operator: &BinaryOperator, lhs: Type, rhs: Type) -> Inferred {
+        self.mismatch(format!(
+            "`{}` between incompatible types {} and {}",
+            operator, lhs, rhs
+        ))
+    }
+
+    fn mismatch(&mut self, message: String) -> Inferred {
+        self.errors.push(message);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    fn errors_of(src: &str) -> Vec<String> {
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        check_types(&ast)
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let errors = errors_of("let a = true + 1; if 1 { printl(a); } let mut b = 1.0; b = \"s\";");
+        assert_eq!(
+            errors,
+            vec![
+                "`+` between incompatible types bool and int".to_string(),
+                "int cannot be used as if condition".to_string(),
+                "Variable (b) is a float but a str is assigned to it".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_promotions_and_unknowns() {
+        let errors = errors_of(
+            "fn f (x) -> { return x * 2; } let mut a = 1 + 2.5; let b = f(a) + 1; \
+             while (a < 10) && (b != 0) { a = a + 1.0; }",
+        );
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn checks_annotations() {
+        let errors = errors_of(
+            "fn f (a: int, b: float) -> float { return a + b; } \
+             let x: float = 1; let y: str = 2; f(1.5, 2);",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "(y) is declared as str but a int is given".to_string(),
+                "(a) is declared as int but a float is given".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_return_types() {
+        let errors = errors_of(
+            "fn f (a) -> int { if a { return 1; } return \"no\"; } let b = f(true) && true;",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "(return value) is declared as int but a str is given".to_string(),
+                "`&&` between incompatible types int and bool".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use crate::interpreter::builtins::format::{parse_template, Field, Piece};
+use crate::parsing::ast::{Expression, Parameter, Span, Statement, StatementKind};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Non-fatal diagnostics found before execution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    UnusedVariable(String),
+    UnusedFunction(String),
+    UnreadAssignment { name: String, line: usize },
+    UnreachableCode { line: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::UnusedVariable(name) => write!(f, "Variable ({}) is never read", name),
+            Warning::UnusedFunction(name) => write!(f, "Function ({}) is never called", name),
+            Warning::UnreadAssignment { name, line } => write!(
+                f,
+                "Value assigned to ({}) at line {} is never read",
+                name, line
+            ),
+            Warning::UnreachableCode { line } => {
+                write!(f, "Code starting at line {} is never executed", line)
+            }
+        }
+    }
+}
+
+/// A declared variable and what is known about its usage.
+///
+/// pending_write: id of the block holding the last write that has not been read yet, with the
+/// offset of the write in the source code.
+struct Declaration {
+    name: String,
+    is_argument: bool,
+    read: bool,
+    pending_write: Option<(usize, usize)>,
+}
+
+#[derive(Default)]
+struct WarningCollector<'src> {
+    src: &'src str,
+    blocks: Vec<(usize, Vec<Declaration>)>,
+    next_block_id: usize,
+    declared_functions: Vec<String>,
+    called_functions: HashSet<String>,
+    current_function: Option<String>,
+    warnings: Vec<Warning>,
+}
+
+/// Collect warnings about unused variables, unused functions, assignments never read and unreachable code.
+pub fn collect_warnings(tree: &Vec<Statement>, src: &str) -> Vec<Warning> {
+    let mut collector = WarningCollector {
+        src,
+        ..Default::default()
+    };
+    collector.visit_block(tree, &[]);
+    for function in &collector.declared_functions {
+        if !collector.called_functions.contains(function) {
+            collector
+                .warnings
+                .push(Warning::UnusedFunction(function.clone()));
+        }
+    }
+    collector.warnings
+}
+
+/// Check if the execution never continues after the statement.
+///
+/// It happens after a return, a break or a continue, an if-else diverging in both branches and
+/// a while true loop without a break leaving it, or a measure block around any of them.
+fn diverges(stmt: &Statement) -> bool {
+    match &stmt.kind {
+        StatementKind::ReturnStatement { .. }
+        | StatementKind::BreakStatement { .. }
+        | StatementKind::ContinueStatement { .. } => true,
+        StatementKind::IfElseStatement {
+            then_part,
+            else_part,
+            ..
+        } => then_part.iter().any(diverges) && else_part.iter().any(diverges),
+        StatementKind::WhileStatement { cond, body, label } => {
+            **cond == Expression::Bool(true) && !breaks_out(body, label, false)
+        }
+        StatementKind::MeasureStatement { body, .. } => body.iter().any(diverges),
+        _ => false,
+    }
+}
+
+/// Check if a break of the statements leaves the loop with the given label, nested tells if they
+/// are in a loop inside it.
+fn breaks_out(tree: &[Statement], label: &Option<String>, nested: bool) -> bool {
+    tree.iter().any(|stmt| match &stmt.kind {
+        StatementKind::BreakStatement { label: None } => !nested,
+        StatementKind::BreakStatement { label: target } => target == label,
+        StatementKind::IfStatement { then_part, .. }
+        | StatementKind::MeasureStatement {
+            body: then_part, ..
+        } => breaks_out(then_part, label, nested),
+        StatementKind::IfElseStatement {
+            then_part,
+            else_part,
+            ..
+        } => breaks_out(then_part, label, nested) || breaks_out(else_part, label, nested),
+        StatementKind::WhileStatement { body, .. } | StatementKind::ForStatement { body, .. } => {
+            breaks_out(body, label, true)
+        }
+        _ => false,
+    })
+}
+
+impl<'src> WarningCollector<'src> {
+    fn visit_block(&mut self, tree: &Vec<Statement>, arguments: &[Parameter]) {
+        let block_id = self.next_block_id;
+        self.next_block_id += 1;
+        let declarations = arguments
+            .iter()
+            .map(|arg| Declaration {
+                name: arg.name.clone(),
+                is_argument: true,
+                read: false,
+                pending_write: None,
+            })
+            .collect();
+        self.blocks.push((block_id, declarations));
+
+        let mut diverged = false;
+        let mut reported = false;
+        for stmt in tree {
+            // Only the first unreachable statement of a block is reported, the functions are declared
+            // when the block starts
+            let is_function = matches!(stmt.kind, StatementKind::FunctionDeclaration { .. });
+            if diverged && !reported && !is_function {
+                let (line, _) = stmt.span.line_and_column(self.src);
+                self.warnings.push(Warning::UnreachableCode { line });
+                reported = true;
+            }
+            self.visit_statement(stmt, block_id);
+            diverged |= diverges(stmt);
+        }
+
+        let (_, declarations) = self.blocks.pop().unwrap();
+        for declaration in declarations {
+            match declaration.pending_write {
+                _ if !declaration.read && !declaration.is_argument => self
+                    .warnings
+                    .push(Warning::UnusedVariable(declaration.name.clone())),
+                Some((_, offset)) => self.unread_assignment(&declaration.name, offset),
+                None => {}
+            }
+        }
+        // A write left pending in a closed block may still be read by the enclosing code, such
+        // as the next iteration of a loop.
+        for (_, declarations) in self.blocks.iter_mut() {
+            for declaration in declarations.iter_mut() {
+                if declaration
+                    .pending_write
+                    .is_some_and(|(block, _)| block == block_id)
+                {
+                    declaration.pending_write = None;
+                }
+            }
+        }
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement, block_id: usize) {
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement { name, value, .. } => {
+                self.visit_expression(value);
+                if let Some((_, declarations)) = self.blocks.last_mut() {
+                    declarations.push(Declaration {
+                        name: name.clone(),
+                        is_argument: false,
+                        read: false,
+                        pending_write: Some((block_id, stmt.span.start)),
+                    });
+                }
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                self.visit_expression(value);
+                self.write(name, block_id, stmt.span.start);
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                ..
+            } => {
+                for value in values {
+                    self.visit_expression(value);
+                }
+                for name in names {
+                    match (declaration, self.blocks.last_mut()) {
+                        (true, Some((_, declarations))) => declarations.push(Declaration {
+                            name: name.clone(),
+                            is_argument: false,
+                            read: false,
+                            pending_write: Some((block_id, stmt.span.start)),
+                        }),
+                        (true, None) => {}
+                        (false, _) => self.write(name, block_id, stmt.span.start),
+                    }
+                }
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                self.visit_expression(cond);
+                self.visit_block(then_part, &[]);
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                self.visit_expression(cond);
+                self.visit_block(then_part, &[]);
+                self.visit_block(else_part, &[]);
+            }
+            StatementKind::WhileStatement { cond, body, .. } => {
+                self.visit_expression(cond);
+                self.visit_block(body, &[]);
+            }
+            StatementKind::MeasureStatement { body, .. } => self.visit_block(body, &[]),
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                for bound in [Some(start), Some(end), step.as_ref()]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.visit_expression(bound);
+                }
+                // The variable is not reported when unused, as the arguments
+                let variable = Parameter {
+                    name: variable.clone(),
+                    type_annotation: None,
+                };
+                self.visit_block(body, &[variable]);
+            }
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                body,
+                ..
+            } => {
+                self.declared_functions.push(name.clone());
+                // Function bodies cannot see the variables of the caller
+                let outer_blocks = std::mem::take(&mut self.blocks);
+                let outer_function = self.current_function.replace(name.clone());
+                self.visit_block(body, arguments);
+                self.current_function = outer_function;
+                self.blocks = outer_blocks;
+            }
+            StatementKind::FunctionCallStatement { name, arguments }
+            | StatementKind::SpawnStatement { name, arguments } => {
+                self.call(name, arguments);
+                for arg in arguments {
+                    self.visit_expression(arg);
+                }
+            }
+            StatementKind::ReturnStatement { value } => self.visit_expression(value),
+            StatementKind::PrintStatement { content }
+            | StatementKind::PrintLineStatement { content } => self.visit_expression(content),
+            StatementKind::InputStatement { name } => {
+                // Input checks the current type of the variable before overwriting it
+                self.read(name);
+                self.write(name, block_id, stmt.span.start);
+            }
+            StatementKind::BreakStatement { .. }
+            | StatementKind::ContinueStatement { .. }
+            | StatementKind::BreakpointStatement
+            | StatementKind::IncludeStatement { .. } => {}
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                self.read(name)
+            }
+            Expression::FunctionCall { name, arguments } => {
+                self.call(name, arguments);
+                for arg in arguments {
+                    self.visit_expression(arg);
+                }
+            }
+            Expression::BinaryOperation { lhs, rhs, .. } => {
+                self.visit_expression(lhs);
+                self.visit_expression(rhs);
+            }
+            Expression::UnaryOperation { rhs, .. } => self.visit_expression(rhs),
+            Expression::Index { target, index } => {
+                self.visit_expression(target);
+                self.visit_expression(index);
+            }
+            Expression::Float(_)
+            | Expression::Int(_)
+            | Expression::Str(_)
+            | Expression::Char(_)
+            | Expression::Bool(_) => {}
+        }
+    }
+
+    fn find(&mut self, name: &str) -> Option<&mut Declaration> {
+        self.blocks
+            .iter_mut()
+            .rev()
+            .flat_map(|(_, declarations)| declarations.iter_mut().rev())
+            .find(|declaration| declaration.name == name)
+    }
+
+    fn read(&mut self, name: &str) {
+        if let Some(declaration) = self.find(name) {
+            declaration.read = true;
+            declaration.pending_write = None;
+        }
+    }
+
+    /// Record a write at offset, the pending write of the same block it replaces is never read.
+    fn write(&mut self, name: &str, block_id: usize, offset: usize) {
+        let mut overwritten = None;
+        if let Some(declaration) = self.find(name) {
+            overwritten = declaration
+                .pending_write
+                .filter(|(block, _)| *block == block_id);
+            declaration.pending_write = Some((block_id, offset));
+        }
+        if let Some((_, offset)) = overwritten {
+            self.unread_assignment(name, offset);
+        }
+    }
+
+    fn unread_assignment(&mut self, name: &str, offset: usize) {
+        let span = Span {
+            start: offset,
+            end: offset,
+        };
+        self.warnings.push(Warning::UnreadAssignment {
+            name: name.to_string(),
+            line: span.line_and_column(self.src).0,
+        });
+    }
+
+    fn call(&mut self, name: &str, arguments: &[Box<Expression>]) {
+        // Recursive calls do not make a function used
+        if self.current_function.as_deref() != Some(name) {
+            self.called_functions.insert(name.to_string());
+        }
+        // The call builtin uses the function named by a literal
+        if let (true, Some(Expression::Str(target))) =
+            (name == "call", arguments.first().map(|arg| arg.as_ref()))
+        {
+            self.call(&target[1..target.len() - 1], &[]);
+        }
+        // The named fields of a literal format template read their variables
+        if let (true, Some(Expression::Str(template))) =
+            (name == "format", arguments.first().map(|arg| arg.as_ref()))
+        {
+            for piece in parse_template(&template[1..template.len() - 1]).unwrap_or_default() {
+                if let Piece::Field(Field::Name(name), _) = piece {
+                    self.read(name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    fn warnings_of(src: &str) -> Vec<Warning> {
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        collect_warnings(&ast, src)
+    }
+
+    #[test]
+    fn unused_variable_and_function() {
+        let warnings = warnings_of("let a = 1; let b = 2; printl(b); fn f (x) -> { return x; }");
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::UnusedVariable("a".to_string()),
+                Warning::UnusedFunction("f".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn overwritten_assignment() {
+        let warnings = warnings_of(
+            "let mut a = 1;\na = 2;\nprintl(a);\na = 3;\na = 4;\nprintl(a);\n\
+             let mut x = 1;\nprintl(x);\nx = 3;\nif true {\n  let mut y = 1;\n  printl(y);\n  y = 2;\n}",
+        );
+        let unread = |name: &str, line| Warning::UnreadAssignment {
+            name: name.to_string(),
+            line,
+        };
+        assert_eq!(
+            warnings,
+            vec![
+                unread("a", 1),
+                unread("a", 4),
+                unread("y", 13),
+                unread("x", 9)
+            ]
+        );
+        assert_eq!(
+            warnings[3].to_string(),
+            "Value assigned to (x) at line 9 is never read"
+        );
+    }
+
+    #[test]
+    fn no_warnings_for_used_code() {
+        let warnings = warnings_of(
+            "fn fact (n) -> { if n <= 1 { return 1; } return n * fact(n - 1); } \
+             let mut i = 0; input(i); if i > 0 { i = 1; } print(fact(i)); \
+             fn twice (x) -> { return x * 2; } print(call(\"twice\", i)); \
+             let name = \"n\"; print(format(\"{name}\"));",
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let warnings = warnings_of(
+            "fn f (x) -> {\n  if x { return 1; } else { return 2; }\n  printl(x);\n  printl(x);\n}\n\
+             printl(f(true));\nwhile true {\n  printl(1);\n}\nprintl(2);",
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::UnreachableCode { line: 3 },
+                Warning::UnreachableCode { line: 10 }
+            ]
+        );
+    }
+}
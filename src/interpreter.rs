@@ -1,3 +1,24 @@
-mod error_reporting;
+pub mod builtins;
+pub mod coercion;
+pub mod concurrency;
+pub mod coverage;
+pub mod debugger;
+pub mod decimal;
+pub mod environment;
+pub mod error_reporting;
 pub mod expression_evaluator;
+#[cfg(all(feature = "ffi", unix))]
+pub mod ffi;
+pub mod inspector;
 pub mod interpreter;
+pub mod io;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod jit;
+pub mod memory;
+pub mod observer;
+pub mod operators;
+pub mod plugins;
+pub mod profiler;
+pub mod runtime;
+pub mod set;
+pub mod tracer;
@@ -0,0 +1,621 @@
+pub mod encoding;
+pub mod format;
+pub mod json;
+pub mod math;
+pub mod numbers;
+pub mod process;
+pub mod random;
+
+use crate::interpreter::builtins::encoding::{base64_decode, base64_encode};
+use crate::interpreter::builtins::format::format_template;
+use crate::interpreter::builtins::json::{json_parse, json_string};
+use crate::interpreter::builtins::math::{abs, clamp, min_max, round_to_int};
+use crate::interpreter::builtins::numbers::{parse_float, parse_int, to_fixed, to_precision};
+use crate::interpreter::builtins::process::{exec, exec_status};
+use crate::interpreter::builtins::random::{uuid_v4, Rng};
+use crate::interpreter::concurrency::Channel;
+use crate::interpreter::decimal::Decimal;
+use crate::interpreter::environment::Environment;
+use crate::interpreter::expression_evaluator::call_function;
+#[cfg(all(feature = "ffi", unix))]
+use crate::interpreter::ffi::ffi_call;
+use crate::interpreter::interpreter::TypeVal;
+use crate::interpreter::operators::concat_strings;
+use crate::interpreter::runtime::Runtime;
+use crate::interpreter::set::Set;
+use crate::parsing::ast::BinaryOperator;
+
+/// Functions provided by the interpreter, a user function with the same name hides them.
+pub const BUILTINS: &[&str] = &[
+    "assert",
+    "assert_eq",
+    "channel",
+    "send",
+    "recv",
+    "set",
+    "add",
+    "contains",
+    "union",
+    "intersect",
+    "difference",
+    "copy",
+    "flush",
+    "eprint",
+    "eprintl",
+    "exit",
+    "panic",
+    "concat",
+    "json_parse",
+    "json_string",
+    "exec",
+    "exec_status",
+    "exec_stdout",
+    "exec_stderr",
+    "base64_encode",
+    "base64_decode",
+    "uuid",
+    "decimal",
+    "parse_int",
+    "parse_float",
+    "to_fixed",
+    "to_precision",
+    "floor",
+    "ceil",
+    "round",
+    "trunc",
+    "min",
+    "max",
+    "abs",
+    "clamp",
+    "random",
+    "seed_random",
+    "dump_scope",
+    "call",
+    "format",
+    "read_line",
+    "read_word",
+    "read_int",
+    "read_float",
+    #[cfg(all(feature = "ffi", unix))]
+    "ffi_call",
+];
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+/// Floats readable from every block, no variable can be declared with their names.
+pub const CONSTANTS: &[(&str, f64)] = &[
+    ("PI", std::f64::consts::PI),
+    ("E", std::f64::consts::E),
+    ("INF", f64::INFINITY),
+    ("NAN", f64::NAN),
+];
+
+pub fn constant(name: &str) -> Option<f64> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, value)| *value)
+}
+
+/// Call a builtin function with its evaluated arguments.
+pub fn call_builtin(name: &str, arguments: Vec<TypeVal>) -> Result<TypeVal, String> {
+    match (name, arguments.as_slice()) {
+        ("assert", [TypeVal::Boolean(cond)]) => assert(*cond, None),
+        ("assert", [TypeVal::Boolean(cond), message @ TypeVal::Str(_)]) => {
+            assert(*cond, Some(message))
+        }
+        ("assert", _) => Err("assert expects a bool and optionally a str message".to_string()),
+        // Compared as by ==, so an int equals the same float
+        ("assert_eq", [lhs, rhs]) => match lhs.binary(&BinaryOperator::CompareEq, rhs) {
+            Ok(TypeVal::Boolean(true)) => Ok(TypeVal::Boolean(true)),
+            _ => Err(format!(
+                "Assertion failed: {} ({}) is not equal to {} ({})",
+                lhs,
+                lhs.get_type(),
+                rhs,
+                rhs.get_type()
+            )),
+        },
+        ("assert_eq", _) => Err("assert_eq expects two arguments".to_string()),
+        ("channel", []) => Ok(TypeVal::Channel(Channel::default())),
+        ("channel", _) => Err("channel expects no arguments".to_string()),
+        ("send", [TypeVal::Channel(channel), value]) => {
+            channel.send(value.clone())?;
+            Ok(TypeVal::Boolean(true))
+        }
+        ("send", _) => Err("send expects a channel and a value".to_string()),
+        ("set", values) => {
+            let set = Set::default();
+            for value in values {
+                set.add(value)?;
+            }
+            Ok(TypeVal::Set(set))
+        }
+        ("add", [TypeVal::Set(set), value]) => set.add(value).map(TypeVal::Boolean),
+        ("add", _) => Err("add expects a set and a value".to_string()),
+        ("contains", [TypeVal::Set(set), value]) => set.contains(value).map(TypeVal::Boolean),
+        ("contains", _) => Err("contains expects a set and a value".to_string()),
+        ("union", [TypeVal::Set(lhs), TypeVal::Set(rhs)]) => Ok(TypeVal::Set(lhs.union(rhs))),
+        ("intersect", [TypeVal::Set(lhs), TypeVal::Set(rhs)]) => {
+            Ok(TypeVal::Set(lhs.intersect(rhs)))
+        }
+        ("difference", [TypeVal::Set(lhs), TypeVal::Set(rhs)]) => {
+            Ok(TypeVal::Set(lhs.difference(rhs)))
+        }
+        ("union" | "intersect" | "difference", _) => Err(format!("{} expects two sets", name)),
+        // The other values are copied on every assignment already
+        ("copy", [TypeVal::Set(set)]) => Ok(TypeVal::Set(set.copy())),
+        ("copy", [TypeVal::Channel(_)]) => Err("A channel cannot be copied".to_string()),
+        ("copy", [value]) => Ok(value.clone()),
+        ("copy", _) => Err("copy expects one argument".to_string()),
+        ("concat", parts) => concat(parts),
+        ("json_parse", [text @ TypeVal::Str(_)]) => json_parse(&text.to_string()),
+        ("json_parse", _) => Err("json_parse expects a str".to_string()),
+        ("json_string", [value]) => json_string(value),
+        ("json_string", _) => Err("json_string expects one argument".to_string()),
+        ("exec", arguments) => exec(arguments),
+        ("base64_encode", [text @ TypeVal::Str(_)]) => Ok(TypeVal::Str(
+            format!("\"{}\"", base64_encode(text.to_string().as_bytes())).into(),
+        )),
+        ("base64_encode", _) => Err("base64_encode expects a str".to_string()),
+        ("base64_decode", [text @ TypeVal::Str(_)]) => {
+            let bytes = base64_decode(&text.to_string())?;
+            match String::from_utf8(bytes) {
+                Ok(decoded) => Ok(TypeVal::Str(format!("\"{}\"", decoded).into())),
+                Err(_) => Err("The decoded base64 is not a valid UTF-8 str".to_string()),
+            }
+        }
+        ("base64_decode", _) => Err("base64_decode expects a str".to_string()),
+        ("uuid", []) => Ok(TypeVal::Str(format!("\"{}\"", uuid_v4()).into())),
+        ("uuid", _) => Err("uuid expects no arguments".to_string()),
+        ("decimal", [text @ TypeVal::Str(_)]) => {
+            Decimal::parse(&text.to_string()).map(TypeVal::Decimal)
+        }
+        ("decimal", [TypeVal::Int(x)]) => Ok(TypeVal::Decimal(Decimal::from(*x))),
+        ("decimal", _) => Err("decimal expects a str or an int".to_string()),
+        ("parse_int", [text @ TypeVal::Str(_)]) => {
+            parse_int(&text.to_string(), 10).map(TypeVal::Int)
+        }
+        ("parse_int", [text @ TypeVal::Str(_), TypeVal::Int(base)]) => {
+            parse_int(&text.to_string(), *base).map(TypeVal::Int)
+        }
+        ("parse_int", _) => Err("parse_int expects a str and optionally an int base".to_string()),
+        ("parse_float", [text @ TypeVal::Str(_)]) => {
+            parse_float(&text.to_string()).map(TypeVal::Float)
+        }
+        ("parse_float", _) => Err("parse_float expects a str".to_string()),
+        ("to_fixed", [x @ (TypeVal::Int(_) | TypeVal::Float(_)), TypeVal::Int(digits)]) => {
+            to_fixed(as_float(x), *digits).map(quoted)
+        }
+        ("to_fixed", _) => Err("to_fixed expects a number and an int".to_string()),
+        ("to_precision", [x @ (TypeVal::Int(_) | TypeVal::Float(_)), TypeVal::Int(digits)]) => {
+            to_precision(as_float(x), *digits).map(quoted)
+        }
+        ("to_precision", _) => Err("to_precision expects a number and an int".to_string()),
+        ("floor" | "ceil" | "round" | "trunc", [TypeVal::Int(x)]) => Ok(TypeVal::Int(*x)),
+        ("floor" | "ceil" | "round" | "trunc", [TypeVal::Float(x)]) => {
+            round_to_int(name, *x).map(TypeVal::Int)
+        }
+        ("floor" | "ceil" | "round" | "trunc", _) => Err(format!("{} expects a number", name)),
+        ("min" | "max", arguments) => min_max(name, arguments),
+        ("abs", [x]) => abs(x),
+        ("abs", _) => Err("abs expects a number".to_string()),
+        ("clamp", arguments) => clamp(arguments),
+        #[cfg(all(feature = "ffi", unix))]
+        ("ffi_call", arguments) => ffi_call(arguments),
+        _ => Err(format!("Function ({}) is not a builtin", name)),
+    }
+}
+
+/// Call a builtin writing to the outputs of the runtime, None for the other builtins.
+///
+/// The standard output is flushed before printing a diagnostic, so both appear in order.
+pub fn call_output_builtin(
+    runtime: &mut Runtime,
+    name: &str,
+    arguments: &[TypeVal],
+) -> Option<Result<TypeVal, String>> {
+    let printed = match (name, arguments) {
+        ("flush", []) => runtime.output.flush(),
+        ("flush", _) => return Some(Err("flush expects no arguments".to_string())),
+        ("eprint", [value]) => runtime
+            .output
+            .flush()
+            .and_then(|_| runtime.error_output.print(&value.to_string())),
+        ("eprintl", [value]) => runtime
+            .output
+            .flush()
+            .and_then(|_| runtime.error_output.print(&format!("{}\n", value))),
+        ("eprint" | "eprintl", _) => return Some(Err(format!("{} expects one argument", name))),
+        _ => return None,
+    };
+    Some(
+        printed
+            .map(|_| TypeVal::Boolean(true))
+            .map_err(|err| format!("Error during {} {}", name, err)),
+    )
+}
+
+/// Call a builtin waiting for a value of a channel, None for the other builtins.
+///
+/// What the spawned functions print is written while waiting, and before the value is returned
+/// so the text printed before sending it comes first.
+pub fn call_channel_builtin(
+    runtime: &mut Runtime,
+    name: &str,
+    arguments: &[TypeVal],
+) -> Option<Result<TypeVal, String>> {
+    match (name, arguments) {
+        ("recv", [TypeVal::Channel(channel)]) => {
+            let threads = runtime.threads.clone();
+            let received = channel.recv(&threads, || runtime.print_spawned());
+            runtime.print_spawned();
+            Some(received)
+        }
+        ("recv", _) => Some(Err("recv expects a channel".to_string())),
+        _ => None,
+    }
+}
+
+/// Call a builtin reading the input of the runtime, None for the other builtins.
+///
+/// The standard output is flushed before reading, so a prompt is shown as before an input.
+pub fn call_input_builtin(
+    runtime: &mut Runtime,
+    name: &str,
+    arguments: &[TypeVal],
+) -> Option<Result<TypeVal, String>> {
+    Some(match (name, arguments) {
+        ("read_line", []) => read_line(runtime).map(quoted),
+        ("read_word", []) => read_word(runtime).map(quoted),
+        ("read_int", []) => read_word(runtime)
+            .and_then(|word| parse_int(&word, 10))
+            .map(TypeVal::Int),
+        ("read_float", []) => read_word(runtime)
+            .and_then(|word| parse_float(&word))
+            .map(TypeVal::Float),
+        ("read_line" | "read_word" | "read_int" | "read_float", _) => {
+            Err(format!("{} expects no arguments", name))
+        }
+        _ => return None,
+    })
+}
+
+/// Next line of the input without its line break.
+fn read_line(runtime: &mut Runtime) -> Result<String, String> {
+    let mut line = String::new();
+    let read = runtime
+        .output
+        .flush()
+        .and_then(|_| runtime.read_line(&mut line))
+        .map_err(|err| format!("Error during read {}", err))?;
+    match read {
+        0 => Err("End of the input, nothing left to read".to_string()),
+        _ => Ok(line.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+/// Next word of the input, separated by whitespace and line breaks, the rest of its line is
+/// kept for the next read unless only whitespace is left.
+fn read_word(runtime: &mut Runtime) -> Result<String, String> {
+    loop {
+        let line = read_line(runtime)?;
+        let line = line.trim_start();
+        let end = line.find(char::is_whitespace).unwrap_or(line.len());
+        if end > 0 {
+            let rest = line[end..].trim_start();
+            if !rest.is_empty() {
+                runtime.unread = format!("{}\n", rest);
+            }
+            return Ok(line[..end].to_string());
+        }
+    }
+}
+
+/// Call a builtin running a command and keeping what it printed, None for the other builtins.
+pub fn call_process_builtin(
+    runtime: &mut Runtime,
+    name: &str,
+    arguments: &[TypeVal],
+) -> Option<Result<TypeVal, String>> {
+    Some(match (name, arguments) {
+        ("exec_status", arguments) => exec_status(arguments, &mut runtime.executed),
+        ("exec_stdout", []) => Ok(TypeVal::from(runtime.executed.0.as_str())),
+        ("exec_stderr", []) => Ok(TypeVal::from(runtime.executed.1.as_str())),
+        ("exec_stdout" | "exec_stderr", _) => Err(format!("{} expects no arguments", name)),
+        _ => return None,
+    })
+}
+
+/// Call a builtin using the random generator of the runtime, None for the other builtins.
+pub fn call_random_builtin(
+    runtime: &mut Runtime,
+    name: &str,
+    arguments: &[TypeVal],
+) -> Option<Result<TypeVal, String>> {
+    Some(match (name, arguments) {
+        ("random", []) => Ok(TypeVal::Float(runtime.random.next_float())),
+        ("random", _) => Err("random expects no arguments".to_string()),
+        ("seed_random", [TypeVal::Int(seed)]) => {
+            runtime.random = Rng::seeded(*seed as u64);
+            Ok(TypeVal::Int(*seed))
+        }
+        ("seed_random", _) => Err("seed_random expects an int".to_string()),
+        _ => return None,
+    })
+}
+
+/// End the program with an exit code, once every block and function is left.
+pub fn exit(env: &mut Environment, arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    match arguments {
+        [TypeVal::Int(code)] => {
+            let code = i32::try_from(*code)
+                .map_err(|_| format!("The exit code {} does not fit in 32 bits", code))?;
+            env.exit_code = Some(code);
+            env.returning = true;
+            Ok(TypeVal::Int(code as i64))
+        }
+        _ => Err("exit expects an int".to_string()),
+    }
+}
+
+/// Print the variables seen by the running statement with their values on the error output.
+pub fn dump_scope(env: &mut Environment, arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    if !arguments.is_empty() {
+        return Err("dump_scope expects no arguments".to_string());
+    }
+    let mut runtime = env.runtime.borrow_mut();
+    let line = runtime.line(runtime.statement);
+    let mut dump = format!("Scope at line {}:\n", line);
+    for (name, value) in env.visible_variables() {
+        dump.push_str(&format!("  {} = {} ({})\n", name, value, value.get_type()));
+    }
+    runtime
+        .output
+        .flush()
+        .and_then(|_| runtime.error_output.print(&dump))
+        .map(|_| TypeVal::Boolean(true))
+        .map_err(|err| format!("Error during dump_scope {}", err))
+}
+
+/// Call the function named by the first argument with the other ones, looked up at runtime as
+/// if it was called from the running statement.
+pub fn call_by_name(env: &mut Environment, arguments: Vec<TypeVal>) -> Result<TypeVal, String> {
+    match arguments.split_first() {
+        Some((target @ TypeVal::Str(_), arguments)) => {
+            call_function(env, &target.to_string(), arguments.to_vec())
+        }
+        _ => Err("call expects the name of a function as a str and its arguments".to_string()),
+    }
+}
+
+/// Str made of the template with its fields replaced, a named field reads the variable or the
+/// constant seen by the running statement.
+pub fn format(env: &mut Environment, arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    match arguments.split_first() {
+        Some((template @ TypeVal::Str(_), arguments)) => {
+            format_template(&template.to_string(), arguments, |name| {
+                match constant(name) {
+                    Some(value) => Ok(TypeVal::Float(value)),
+                    None => env.get_variable_value(name),
+                }
+            })
+            .map(quoted)
+        }
+        _ => Err("format expects a str template and the values of its fields".to_string()),
+    }
+}
+
+/// Raise an error holding the message and the calls leading to it, no try can catch it.
+pub fn panic(env: &mut Environment, arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    match arguments {
+        [message] => {
+            let panic = format!("Panic: {}\n{}", message, env.runtime.borrow().stack_trace());
+            env.panic = Some(panic.clone());
+            Err(panic)
+        }
+        _ => Err("panic expects one argument".to_string()),
+    }
+}
+
+/// Value of an int or a float, ints are promoted.
+fn as_float(value: &TypeVal) -> f64 {
+    match value {
+        TypeVal::Int(x) => *x as f64,
+        TypeVal::Float(x) => *x,
+        _ => f64::NAN,
+    }
+}
+
+/// Str value holding the text.
+fn quoted(text: String) -> TypeVal {
+    TypeVal::Str(format!("\"{}\"", text).into())
+}
+
+/// Join strs with a single allocation, a chain of `+` copies the text joined so far at each one.
+fn concat(arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    let mut parts = vec![];
+    for argument in arguments {
+        match argument {
+            TypeVal::Str(part) => parts.push(part),
+            _ => return Err(format!("concat expects strs, not {}", argument.describe())),
+        }
+    }
+    Ok(concat_strings(&parts))
+}
+
+fn assert(cond: bool, message: Option<&TypeVal>) -> Result<TypeVal, String> {
+    match (cond, message) {
+        (true, _) => Ok(TypeVal::Boolean(true)),
+        (false, Some(message)) => Err(format!("Assertion failed: {}", message)),
+        (false, None) => Err("Assertion failed".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::io::{SharedBuffer, WriteOutput};
+    use crate::language_runner::run_language::run_source;
+
+    /// Run a program in a new interpreter and return what it printed.
+    fn run_capturing(src: &str) -> String {
+        let output = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .build();
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        output.contents()
+    }
+
+    #[test]
+    fn assertions() {
+        assert_eq!(
+            call_builtin("assert", vec![TypeVal::Boolean(true)]),
+            Ok(TypeVal::Boolean(true))
+        );
+        assert_eq!(
+            call_builtin(
+                "assert",
+                vec![TypeVal::Boolean(false), TypeVal::Str("\"no\"".into())]
+            ),
+            Err("Assertion failed: no".to_string())
+        );
+        assert_eq!(
+            call_builtin("assert_eq", vec![TypeVal::Int(1), TypeVal::Float(1.0)]),
+            Ok(TypeVal::Boolean(true))
+        );
+        assert_eq!(
+            call_builtin("assert_eq", vec![TypeVal::Int(1), TypeVal::Float(1.5)]),
+            Err("Assertion failed: 1 (int) is not equal to 1.5 (float)".to_string())
+        );
+        assert!(call_builtin("assert_eq", vec![TypeVal::Int(1), TypeVal::Boolean(true)]).is_err());
+        assert!(call_builtin("assert", vec![TypeVal::Int(1)]).is_err());
+    }
+
+    #[test]
+    fn exits_through_every_function() {
+        let output = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .build();
+        let src = "fn stop (n) -> int { while true { exit(n); } printl(0); return n; } \
+                   printl(1); let code = stop(7); printl(code);";
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        assert_eq!(interpreter.exit_code(), Some(7));
+        assert_eq!(output.contents(), "1\n");
+        assert!(run_source("exit(1.5);", &mut interpreter).is_err());
+        assert_eq!(run_source("printl(2);", &mut interpreter), Ok(()));
+        assert_eq!(interpreter.exit_code(), None);
+    }
+
+    #[test]
+    fn panics_with_the_calls() {
+        let src =
+            "fn inner () -> {\n    panic(\"broken\");\n}\nfn outer () -> {\n    inner();\n}\n\
+                   outer();";
+        let mut interpreter = Interpreter::builder().source(src).build();
+        assert_eq!(
+            run_source(src, &mut interpreter),
+            Err(
+                "Panic: broken\n  in (inner) at line 2\n  in (outer) at line 5\n  \
+                 in the program at line 7"
+                    .to_string()
+            )
+        );
+        assert_eq!(interpreter.runtime().calls, vec![]);
+    }
+
+    #[test]
+    fn calls_functions_by_name() {
+        let src = "fn add (a, b) -> { return a + b; } fn mul (a, b) -> { return a * b; } \
+                   let mut name = \"add\"; printl(call(name, 2, 3)); name = \"mul\"; \
+                   printl(call(name, 2, 3)); printl(call(\"abs\", -1));";
+        assert_eq!(run_capturing(src), "5\n6\n1\n");
+        let mut interpreter = Interpreter::default();
+        assert!(run_source("call(\"missing\");", &mut interpreter)
+            .unwrap_err()
+            .contains("missing"));
+        assert!(run_source("call(1);", &mut interpreter).is_err());
+    }
+
+    #[test]
+    fn formats_with_the_visible_variables() {
+        let src =
+            "fn report (name, score) -> { return format(\"{name} scored {score:.2} of {}\", 3); } \
+                   printl(report(\"Ada\", 2.5)); printl(format(\"{PI:.3}\"));";
+        assert_eq!(run_capturing(src), "Ada scored 2.50 of 3\n3.142\n");
+        assert!(run_source(
+            "printl(format(\"{missing}\"));",
+            &mut Interpreter::default()
+        )
+        .unwrap_err()
+        .contains("missing"));
+    }
+
+    #[test]
+    fn dumps_the_visible_variables() {
+        let src = "let a = 1;\nfn f (x) -> {\n    let y = \"two\";\n    dump_scope();\n}\nf(3.5);";
+        let errors = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .source(src)
+            .error_output(WriteOutput(errors.clone()))
+            .build();
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        assert_eq!(
+            errors.contents(),
+            "Scope at line 4:\n  x = 3.5 (float)\n  y = two (str)\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reads_the_status_and_the_outputs_of_a_command() {
+        assert_eq!(
+            run_capturing(
+                "let status = exec_status(\"sh\", \"-c\", \"echo done; echo oops >&2; exit 2\");\n\
+                 printl(status); print(exec_stdout()); print(exec_stderr());"
+            ),
+            "2\ndone\noops\n"
+        );
+    }
+
+    #[test]
+    fn shares_sets_between_copies() {
+        assert_eq!(
+            run_capturing(
+                "let seen = set(1, 2);\nlet alias = seen;\nadd(alias, 3);\n\
+                 printl(contains(seen, 3));\nprintl(difference(seen, set(2)));"
+            ),
+            "true\n{1, 3}\n"
+        );
+    }
+
+    #[test]
+    fn joins_strs() {
+        assert_eq!(
+            run_capturing("let mut s = \"a\";\ns = s + \"b\";\nprintl(concat(s, \"\", \"c\", s));"),
+            "abcab\n"
+        );
+        assert!(
+            call_builtin("concat", vec![TypeVal::from("a"), TypeVal::Int(1)])
+                .unwrap_err()
+                .contains("concat expects strs, not int 1")
+        );
+    }
+
+    #[test]
+    fn changes_sets_in_place_unless_copied() {
+        assert_eq!(
+            run_capturing(
+                "fn mark (seen, x) -> { add(seen, x); return 0; }\nlet a = set();\nmark(a, 1);\n\
+                 let b = copy(a);\nmark(b, 2);\nprintl(a);\nprintl(b);\nprintl(copy(3));"
+            ),
+            "{1}\n{1, 2}\n3\n"
+        );
+        assert!(call_builtin("copy", vec![TypeVal::Channel(Channel::default())]).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 encoding of the bytes, with padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+/// Bytes of a base64 text, the padding is optional and the whitespace is ignored.
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut group = 0u32;
+    let mut bits = 0;
+    let digits = text.trim_end_matches(|c: char| c == '=' || c.is_ascii_whitespace());
+    for c in digits.chars().filter(|c| !c.is_ascii_whitespace()) {
+        let Some(value) = ALPHABET.iter().position(|&digit| digit as char == c) else {
+            return Err(format!("Invalid base64 character `{}`", c));
+        };
+        group = group << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+        }
+    }
+    // A single digit left over cannot hold a byte
+    if bits >= 6 {
+        return Err("Invalid base64 length".to_string());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_base64() {
+        for (text, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("café", "Y2Fmw6k="),
+        ] {
+            assert_eq!(base64_encode(text.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded), Ok(text.as_bytes().to_vec()));
+        }
+        assert_eq!(base64_decode("Zm9v\nYg"), Ok(b"foob".to_vec()));
+        assert!(base64_decode("Zm9v!").is_err());
+        assert!(base64_decode("Z").is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::interpreter::builtins::numbers::to_fixed;
+use crate::interpreter::interpreter::TypeVal;
+use std::mem;
+
+/// Value replacing a field of a template.
+#[derive(Debug, PartialEq)]
+pub enum Field<'a> {
+    /// {} is the argument after the one of the previous {}
+    Next,
+    /// {0} is the first argument after the template
+    Position(usize),
+    /// {name} is the variable name
+    Name(&'a str),
+}
+
+/// Piece of a template, a field has the digits written after the point of a number.
+#[derive(Debug, PartialEq)]
+pub enum Piece<'a> {
+    Text(String),
+    Field(Field<'a>, Option<i64>),
+}
+
+/// Split a template in its text and its fields, {{ and }} are written as single braces.
+///
+/// A field is {}, {0} or {name}, optionally followed by :.N to write a number with N digits
+/// after the point.
+pub fn parse_template(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+            text.push(c);
+            rest = after;
+            continue;
+        }
+        match c {
+            '{' => {
+                let (field, after) = rest[1..]
+                    .split_once('}')
+                    .ok_or("The format template has a { without its }, write {{ for a brace")?;
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(mem::take(&mut text)));
+                }
+                pieces.push(parse_field(field)?);
+                rest = after;
+            }
+            '}' => {
+                return Err(
+                    "The format template has a } without its {, write }} for a brace".to_string(),
+                )
+            }
+            _ => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn parse_field(field: &str) -> Result<Piece<'_>, String> {
+    let (key, digits) = match field.split_once(':') {
+        Some((key, spec)) => match spec.strip_prefix('.').and_then(|d| d.parse().ok()) {
+            Some(digits) => (key, Some(digits)),
+            None => {
+                return Err(format!(
+                    "`{}` in {{{}}} is not a format, only .N writing N digits after the point is",
+                    spec, field
+                ))
+            }
+        },
+        None => (field, None),
+    };
+    let is_name = key.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let key = match key.parse() {
+        _ if key.is_empty() => Field::Next,
+        Ok(position) => Field::Position(position),
+        Err(_) if is_name => Field::Name(key),
+        Err(_) => {
+            return Err(format!(
+            "{{{}}} is not a field, write {{}}, a position or a variable name between the braces",
+            field
+        ))
+        }
+    };
+    Ok(Piece::Field(key, digits))
+}
+
+/// Template with its fields replaced by the arguments following it or by the variables
+/// returned by variable.
+pub fn format_template(
+    template: &str,
+    arguments: &[TypeVal],
+    mut variable: impl FnMut(&str) -> Result<TypeVal, String>,
+) -> Result<String, String> {
+    let mut formatted = String::new();
+    let mut next = 0;
+    for piece in parse_template(template)? {
+        let (field, digits) = match piece {
+            Piece::Text(text) => {
+                formatted.push_str(&text);
+                continue;
+            }
+            Piece::Field(field, digits) => (field, digits),
+        };
+        let value = match field {
+            Field::Name(name) => variable(name)?,
+            Field::Position(position) => argument(arguments, position)?,
+            Field::Next => {
+                next += 1;
+                argument(arguments, next - 1)?
+            }
+        };
+        match (digits, &value) {
+            (None, _) => formatted.push_str(&value.to_string()),
+            (Some(digits), TypeVal::Int(x)) => formatted.push_str(&to_fixed(*x as f64, digits)?),
+            (Some(digits), TypeVal::Float(x)) => formatted.push_str(&to_fixed(*x, digits)?),
+            (Some(_), _) => {
+                return Err(format!(
+                    "Only numbers have digits after the point, {} is a {}",
+                    value,
+                    value.get_type()
+                ))
+            }
+        }
+    }
+    Ok(formatted)
+}
+
+fn argument(arguments: &[TypeVal], position: usize) -> Result<TypeVal, String> {
+    arguments.get(position).cloned().ok_or_else(|| {
+        format!(
+            "The format template uses the argument {} but {} are given after it",
+            position,
+            arguments.len()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_the_fields() {
+        let score = |name: &str| match name {
+            "score" => Ok(TypeVal::Float(2.0 / 3.0)),
+            _ => Err(format!("Variable `{}` does not exist", name)),
+        };
+        let arguments = [TypeVal::Str("\"Ada\"".into()), TypeVal::Int(3)];
+        assert_eq!(
+            format_template(
+                "{} scored {score:.2} in {}, {0} {{{1:.1}}}",
+                &arguments,
+                score
+            ),
+            Ok("Ada scored 0.67 in 3, Ada {3.0}".to_string())
+        );
+        assert!(format_template("{2}", &arguments, score).is_err());
+        assert!(format_template("{level}", &arguments, score).is_err());
+        assert!(format_template("{0:.1}", &arguments, score).is_err());
+        assert!(format_template("{0:x}", &arguments, score).is_err());
+        assert!(format_template("{0", &arguments, score).is_err());
+        assert!(format_template("0}", &arguments, score).is_err());
+        assert!(format_template("{a b}", &arguments, score).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::interpreter::interpreter::TypeVal;
+use serde_json::{Number, Value};
+
+/// Value of a JSON document.
+///
+/// Grim has no list or map values yet, so only the documents holding a number, a bool or a
+/// string can be read.
+pub fn json_parse(text: &str) -> Result<TypeVal, String> {
+    let document: Value =
+        serde_json::from_str(text).map_err(|err| format!("Invalid JSON: {}", err))?;
+    match document {
+        Value::Bool(x) => Ok(TypeVal::Boolean(x)),
+        Value::Number(x) => match (x.as_i64(), x.as_f64()) {
+            (Some(x), _) => Ok(TypeVal::Int(x)),
+            (None, Some(x)) => Ok(TypeVal::Float(x)),
+            (None, None) => Err(format!("The JSON number {} is out of range", x)),
+        },
+        Value::String(x) => Ok(TypeVal::Str(format!("\"{}\"", x).into())),
+        Value::Null => Err("JSON null has no equivalent in Grim".to_string()),
+        Value::Array(_) | Value::Object(_) => {
+            Err("JSON arrays and objects need list and map values, Grim has none yet".to_string())
+        }
+    }
+}
+
+/// JSON text of a value.
+pub fn json_string(value: &TypeVal) -> Result<TypeVal, String> {
+    let document = match value {
+        TypeVal::Int(x) => Value::from(*x),
+        TypeVal::Float(x) => match Number::from_f64(*x) {
+            Some(x) => Value::Number(x),
+            None => return Err(format!("{} cannot be written in JSON", x)),
+        },
+        TypeVal::Boolean(x) => Value::Bool(*x),
+        TypeVal::Str(_) | TypeVal::Char(_) => Value::String(value.to_string()),
+        TypeVal::Channel(_) => return Err("A channel cannot be written in JSON".to_string()),
+        TypeVal::Set(_) => return Err("A set cannot be written in JSON".to_string()),
+        // The digits are written as they are, a JSON number has no precision limit
+        TypeVal::Decimal(x) => return Ok(TypeVal::Str(format!("\"{}\"", x).into())),
+    };
+    Ok(TypeVal::Str(format!("\"{}\"", document).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_scalars() {
+        assert_eq!(json_parse(" 42 "), Ok(TypeVal::Int(42)));
+        assert_eq!(json_parse("2.5e1"), Ok(TypeVal::Float(25.0)));
+        assert_eq!(
+            json_parse(r#""café \"x\"""#),
+            Ok(TypeVal::Str("\"café \"x\"\"".into()))
+        );
+        assert!(json_parse("[1, 2]").unwrap_err().contains("list and map"));
+        assert!(json_parse("{").unwrap_err().starts_with("Invalid JSON"));
+
+        let text = json_string(&TypeVal::Str("\"say \"hi\"\n\"".into())).unwrap();
+        assert_eq!(text.to_string(), r#""say \"hi\"\n""#);
+        assert_eq!(
+            json_parse(&text.to_string()),
+            Ok(TypeVal::Str("\"say \"hi\"\n\"".into()))
+        );
+        assert_eq!(
+            json_string(&TypeVal::Float(1.5)),
+            Ok(TypeVal::Str("\"1.5\"".into()))
+        );
+        assert!(json_string(&TypeVal::Float(f64::NAN)).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::interpreter::interpreter::TypeVal;
+
+/// Float rounded by floor, ceil, round or trunc, converted to an int.
+///
+/// round rounds half away from zero: round(2.5) is 3 and round(-2.5) is -3. NaN, the infinities
+/// and the floats beyond the range of ints raise an error.
+pub fn round_to_int(rounding: &str, x: f64) -> Result<i64, String> {
+    let rounded = match rounding {
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        "round" => x.round(),
+        "trunc" => x.trunc(),
+        _ => return Err(format!("{} is not a rounding", rounding)),
+    };
+    // i64::MAX is not a float, 2^63 is the first float out of range
+    match (i64::MIN as f64..-(i64::MIN as f64)).contains(&rounded) {
+        true => Ok(rounded as i64),
+        false => Err(format!("{}({}) does not fit in an int", rounding, x)),
+    }
+}
+
+/// Numbers of the arguments, ints when all of them are ints and floats otherwise, as the
+/// arithmetic operators promote them.
+enum Numbers {
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+}
+
+fn numbers(name: &str, arguments: &[TypeVal]) -> Result<Numbers, String> {
+    let ints: Option<Vec<i64>> = arguments
+        .iter()
+        .map(|argument| match argument {
+            TypeVal::Int(x) => Some(*x),
+            _ => None,
+        })
+        .collect();
+    if let Some(ints) = ints {
+        return Ok(Numbers::Ints(ints));
+    }
+    arguments
+        .iter()
+        .map(|argument| match argument {
+            TypeVal::Int(x) => Ok(*x as f64),
+            TypeVal::Float(x) => Ok(*x),
+            _ => Err(format!(
+                "{} expects numbers, a {} is given",
+                name,
+                argument.get_type()
+            )),
+        })
+        .collect::<Result<Vec<f64>, String>>()
+        .map(Numbers::Floats)
+}
+
+/// Smallest or largest of at least one number, a NaN argument gives NaN.
+pub fn min_max(name: &str, arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    let smallest = name == "min";
+    match numbers(name, arguments)? {
+        Numbers::Ints(ints) => match smallest {
+            true => ints.into_iter().min(),
+            false => ints.into_iter().max(),
+        }
+        .map(TypeVal::Int),
+        Numbers::Floats(floats) => floats
+            .into_iter()
+            .reduce(|x, y| match (x.is_nan() || y.is_nan(), smallest) {
+                (true, _) => f64::NAN,
+                (false, true) => x.min(y),
+                (false, false) => x.max(y),
+            })
+            .map(TypeVal::Float),
+    }
+    .ok_or_else(|| format!("{} expects at least one number", name))
+}
+
+/// Absolute value of a number.
+pub fn abs(x: &TypeVal) -> Result<TypeVal, String> {
+    match x {
+        TypeVal::Int(x) => x
+            .checked_abs()
+            .map(TypeVal::Int)
+            .ok_or_else(|| format!("abs({}) does not fit in an int", x)),
+        TypeVal::Float(x) => Ok(TypeVal::Float(x.abs())),
+        _ => Err(format!("abs expects a number, a {} is given", x.get_type())),
+    }
+}
+
+/// Number limited to the range between lo and hi, lo cannot be greater than hi.
+pub fn clamp(arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    let bounds_error = || "clamp expects lo not greater than hi".to_string();
+    match numbers("clamp", arguments)? {
+        Numbers::Ints(ints) => match ints[..] {
+            [x, lo, hi] if lo <= hi => Ok(TypeVal::Int(x.clamp(lo, hi))),
+            [_, _, _] => Err(bounds_error()),
+            _ => Err("clamp expects a number, lo and hi".to_string()),
+        },
+        Numbers::Floats(floats) => match floats[..] {
+            [x, lo, hi] if lo <= hi => Ok(TypeVal::Float(x.clamp(lo, hi))),
+            [_, _, _] => Err(bounds_error()),
+            _ => Err("clamp expects a number, lo and hi".to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_to_ints() {
+        assert_eq!(round_to_int("floor", -1.5), Ok(-2));
+        assert_eq!(round_to_int("ceil", -1.5), Ok(-1));
+        assert_eq!(round_to_int("trunc", -1.5), Ok(-1));
+        assert_eq!(round_to_int("round", -2.5), Ok(-3));
+        assert_eq!(round_to_int("round", 2.5), Ok(3));
+        assert!(round_to_int("floor", f64::NAN).is_err());
+        assert!(round_to_int("ceil", f64::INFINITY).is_err());
+        assert!(round_to_int("trunc", 1e19).is_err());
+    }
+
+    #[test]
+    fn compares_numbers() {
+        use TypeVal::{Float, Int};
+        assert_eq!(min_max("min", &[Int(3), Int(-1), Int(2)]), Ok(Int(-1)));
+        assert_eq!(min_max("max", &[Int(3), Float(3.5)]), Ok(Float(3.5)));
+        assert_eq!(min_max("min", &[Int(1), Float(2.0)]), Ok(Float(1.0)));
+        assert!(min_max("max", &[]).is_err());
+        assert!(min_max("max", &[Int(1), TypeVal::Boolean(true)]).is_err());
+        assert_eq!(abs(&Int(-4)), Ok(Int(4)));
+        assert!(abs(&Int(i64::MIN)).is_err());
+        assert_eq!(clamp(&[Int(12), Int(0), Int(10)]), Ok(Int(10)));
+        assert_eq!(clamp(&[Float(-0.5), Int(0), Int(1)]), Ok(Float(0.0)));
+        assert!(clamp(&[Int(1), Int(2), Int(0)]).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+/// Int written in the given base, between 2 and 36, with an optional sign.
+///
+/// The whitespace around the digits is ignored, anything else raises an error.
+pub fn parse_int(text: &str, base: i64) -> Result<i64, String> {
+    if !(2..=36).contains(&base) {
+        return Err(format!(
+            "The base must be between 2 and 36, {} is given",
+            base
+        ));
+    }
+    i64::from_str_radix(text.trim(), base as u32).map_err(|err| {
+        format!(
+            "\"{}\" is not an int in base {}: {}",
+            text.escape_debug(),
+            base,
+            err
+        )
+    })
+}
+
+/// Float written in decimal or scientific notation, inf and NaN are accepted.
+pub fn parse_float(text: &str) -> Result<f64, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a float", text.escape_debug()))
+}
+
+/// Digits a formatted number can ask for.
+pub const MAX_DIGITS: i64 = 100;
+
+fn check_digits(digits: i64, min: i64) -> Result<usize, String> {
+    match (min..=MAX_DIGITS).contains(&digits) {
+        true => Ok(digits as usize),
+        false => Err(format!(
+            "The number of digits must be between {} and {}, {} is given",
+            min, MAX_DIGITS, digits
+        )),
+    }
+}
+
+/// Number written with the given digits after the point, rounded half to even.
+pub fn to_fixed(x: f64, digits: i64) -> Result<String, String> {
+    Ok(format!("{:.*}", check_digits(digits, 0)?, x))
+}
+
+/// Number rounded to the given significant digits.
+///
+/// Very large or very small numbers, whose exponent is below -6 or not below the significant
+/// digits, are written in scientific notation.
+pub fn to_precision(x: f64, significant: i64) -> Result<String, String> {
+    let significant = check_digits(significant, 1)?;
+    if !x.is_finite() {
+        return Ok(x.to_string());
+    }
+    // The exponent after the rounding, 9.96 with 2 digits is 1.0e1
+    let scientific = format!("{:.*e}", significant - 1, x);
+    let exponent: i64 = scientific
+        .split_once('e')
+        .and_then(|(_, exponent)| exponent.parse().ok())
+        .unwrap_or(0);
+    match exponent < -6 || exponent >= significant as i64 {
+        true => Ok(scientific),
+        false => Ok(format!(
+            "{:.*}",
+            (significant as i64 - 1 - exponent) as usize,
+            x
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(parse_int(" -42\n", 10), Ok(-42));
+        assert_eq!(parse_int("ff", 16), Ok(255));
+        assert_eq!(parse_int("101", 2), Ok(5));
+        assert!(parse_int("12a", 10).is_err());
+        assert!(parse_int("", 10).is_err());
+        assert!(parse_int("1", 37).is_err());
+        assert_eq!(parse_float("2.5e3"), Ok(2500.0));
+        assert_eq!(parse_float("7"), Ok(7.0));
+        assert!(parse_float("seven").is_err());
+    }
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(to_fixed(2.0 / 3.0, 2), Ok("0.67".to_string()));
+        assert_eq!(to_fixed(-1.5, 0), Ok("-2".to_string()));
+        assert!(to_fixed(1.0, -1).is_err());
+        assert_eq!(to_precision(123.456, 4), Ok("123.5".to_string()));
+        assert_eq!(to_precision(9.96, 2), Ok("10".to_string()));
+        assert_eq!(to_precision(0.000123, 2), Ok("0.00012".to_string()));
+        assert_eq!(to_precision(123456.0, 2), Ok("1.2e5".to_string()));
+        assert_eq!(to_precision(0.0, 3), Ok("0.00".to_string()));
+        assert!(to_precision(1.0, 0).is_err());
+    }
+}
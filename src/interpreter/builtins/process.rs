@@ -0,0 +1,107 @@
+use crate::interpreter::interpreter::TypeVal;
+use std::process::{Command, Output, Stdio};
+
+/// Run a command with the given arguments, name: builtin reported in the errors.
+///
+/// Returns the name of the command and what it printed.
+fn run(name: &str, arguments: &[TypeVal]) -> Result<(String, Output), String> {
+    let mut words = vec![];
+    for argument in arguments {
+        match argument {
+            TypeVal::Str(_) => words.push(argument.to_string()),
+            _ => {
+                return Err(format!(
+                    "{} expects a command and its arguments as str",
+                    name
+                ))
+            }
+        }
+    }
+    let Some((command, arguments)) = words.split_first() else {
+        return Err(format!(
+            "{} expects a command and its arguments as str",
+            name
+        ));
+    };
+    let output = Command::new(command)
+        .args(arguments)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("Cannot run {}: {}", command, err))?;
+    Ok((command.clone(), output))
+}
+
+/// Run a command with the given arguments and return what it printed on stdout.
+///
+/// A command ending with a non zero status is an error reporting its status and what it printed
+/// on stderr, exec_status returns them instead.
+pub fn exec(arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    let (command, output) = run("exec", arguments)?;
+    match output.status.code() {
+        Some(0) => Ok(TypeVal::from(&*String::from_utf8_lossy(&output.stdout))),
+        code => Err(format!(
+            "{} failed with status {}\n{}",
+            command,
+            code.map_or("unknown".to_string(), |code| code.to_string()),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )),
+    }
+}
+
+/// Run a command with the given arguments and return its exit status, -1 if a signal ended it.
+///
+/// printed: replaced by what the command printed on stdout and stderr.
+pub fn exec_status(
+    arguments: &[TypeVal],
+    printed: &mut (String, String),
+) -> Result<TypeVal, String> {
+    let (_, output) = run("exec_status", arguments)?;
+    *printed = (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    );
+    Ok(TypeVal::Int(output.status.code().map_or(-1, i64::from)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn runs_commands() {
+        let string = |x: &str| TypeVal::Str(format!("\"{}\"", x).into());
+        assert_eq!(
+            exec(&[string("echo"), string("hello world")]),
+            Ok(string("hello world\n"))
+        );
+        assert_eq!(
+            exec(&[string("sh"), string("-c"), string("echo oops >&2; exit 3")]),
+            Err("sh failed with status 3\noops".to_string())
+        );
+        assert!(exec(&[string("echo"), TypeVal::Int(1)]).is_err());
+        assert!(exec(&[]).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn returns_the_status_of_failing_commands() {
+        let string = |x: &str| TypeVal::Str(format!("\"{}\"", x).into());
+        let mut printed = (String::new(), String::new());
+        assert_eq!(
+            exec_status(
+                &[
+                    string("sh"),
+                    string("-c"),
+                    string("echo out; echo oops >&2; exit 3")
+                ],
+                &mut printed
+            ),
+            Ok(TypeVal::Int(3))
+        );
+        assert_eq!(printed, ("out\n".to_string(), "oops\n".to_string()));
+        assert!(exec_status(&[TypeVal::Int(1)], &mut printed)
+            .unwrap_err()
+            .starts_with("exec_status expects"));
+    }
+}
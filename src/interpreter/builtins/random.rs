@@ -0,0 +1,102 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Calls to random_u64, hashed so that two calls never hash the same input.
+static CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Random number from the keys the standard library seeds its hash maps with.
+///
+/// Good enough for identifiers and test data, not for cryptography.
+pub fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(CALLS.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+/// Generator of the numbers returned by random, a splitmix64 so that a seed gives the same
+/// numbers on every platform.
+#[derive(Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::seeded(random_u64())
+    }
+}
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Float from 0 included to 1 excluded, made of the 53 high bits of the next number.
+    pub fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Random identifier in the RFC 4122 version 4 format, as 36 lowercase characters.
+pub fn uuid_v4() -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random_u64().to_le_bytes());
+    bytes[8..].copy_from_slice(&random_u64().to_le_bytes());
+    // Version 4 and the variant of the RFC
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_version_4_uuids() {
+        let uuid = uuid_v4();
+        assert_eq!(uuid.len(), 36);
+        let groups: Vec<&str> = uuid.split('-').collect();
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| group.len())
+                .collect::<Vec<usize>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('4'));
+        assert!(matches!(
+            groups[3].chars().next(),
+            Some('8' | '9' | 'a' | 'b')
+        ));
+        assert_ne!(uuid, uuid_v4());
+    }
+
+    #[test]
+    fn repeats_the_numbers_of_a_seed() {
+        let numbers = |seed| {
+            let mut rng = Rng::seeded(seed);
+            (0..3).map(|_| rng.next_float()).collect::<Vec<f64>>()
+        };
+        assert_eq!(numbers(42), numbers(42));
+        assert_ne!(numbers(42), numbers(43));
+        assert!(numbers(7).iter().all(|x| (0.0..1.0).contains(x)));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::interpreter::decimal::Decimal;
+use crate::interpreter::interpreter::TypeVal::{self, Boolean, Decimal as Dec, Float, Int};
+use crate::parsing::ast::Type;
+
+/// Implicit conversions, from the type of a value to the type it is used as.
+///
+/// They apply to the operands of the operators, the annotations, the conditions and the input
+/// statement, every other conversion needs a builtin such as `trunc` or `parse_int`.
+const COERCIONS: [(Type, Type); 2] = [(Type::Int, Type::Float), (Type::Int, Type::Decimal)];
+
+/// Whether a value of type from can be used where a to is expected.
+pub fn coerces(from: Type, to: Type) -> bool {
+    from == to || COERCIONS.contains(&(from, to))
+}
+
+/// Value converted to the type to, None if the conversion is not implicit.
+pub fn coerce(value: TypeVal, to: Type) -> Option<TypeVal> {
+    if !coerces(value.get_type(), to) {
+        return None;
+    }
+    match (value, to) {
+        (Int(x), Type::Float) => Some(Float(x as f64)),
+        (Int(x), Type::Decimal) => Some(Dec(Decimal::from(x))),
+        (value, _) => Some(value),
+    }
+}
+
+/// Type both operands of a binary operator are converted to, the one the other coerces to.
+///
+/// A float and a decimal have none, the result would not be exact.
+pub fn common_type(lhs: Type, rhs: Type) -> Option<Type> {
+    match (coerces(lhs, rhs), coerces(rhs, lhs)) {
+        (true, _) => Some(rhs),
+        (false, true) => Some(lhs),
+        (false, false) => None,
+    }
+}
+
+/// Types whose values are numbers, the arithmetic operators apply to them.
+pub fn is_number(t: Type) -> bool {
+    matches!(t, Type::Int | Type::Float | Type::Decimal)
+}
+
+/// Value of the condition of an if or a while, construct, no value converts to a bool.
+pub fn condition(value: &TypeVal, construct: &str) -> Result<bool, String> {
+    match coerce(value.clone(), Type::Bool) {
+        Some(Boolean(x)) => Ok(x),
+        _ => Err(format!(
+            "{} cannot be used as {} condition",
+            value.get_type(),
+            construct
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_only_ints_implicitly() {
+        assert_eq!(coerce(Int(2), Type::Float), Some(Float(2.0)));
+        assert_eq!(coerce(Int(2), Type::Decimal), Some(Dec(Decimal::from(2))));
+        assert_eq!(coerce(Float(2.0), Type::Int), None);
+        assert_eq!(coerce(Float(2.0), Type::Decimal), None);
+        assert_eq!(common_type(Type::Float, Type::Int), Some(Type::Float));
+        assert_eq!(common_type(Type::Int, Type::Decimal), Some(Type::Decimal));
+        assert_eq!(common_type(Type::Float, Type::Decimal), None);
+        assert_eq!(common_type(Type::Str, Type::Char), None);
+        assert_eq!(condition(&Boolean(true), "if"), Ok(true));
+        assert_eq!(
+            condition(&Int(1), "while"),
+            Err("int cannot be used as while condition".to_string())
+        );
+    }
+}
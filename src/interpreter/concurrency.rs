@@ -0,0 +1,218 @@
+use crate::interpreter::interpreter::{FunctionDef, Interpreter, TypeVal};
+use crate::interpreter::io::{Input, Output};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::interpreter::jit::Jit;
+use crate::interpreter::memory::Memory;
+use crate::interpreter::runtime::Runtime;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Time a waiting thread sleeps before checking whether another thread can still wake it.
+pub const WAIT: Duration = Duration::from_millis(10);
+
+/// Number of threads of a program that are not waiting, shared by the runtimes of its threads.
+///
+/// When every thread waits for a value, none of them can send it.
+#[derive(Debug, Clone)]
+pub struct Threads(Arc<AtomicUsize>);
+
+impl Default for Threads {
+    fn default() -> Self {
+        Threads(Arc::new(AtomicUsize::new(1)))
+    }
+}
+
+impl Threads {
+    /// Run f with the current thread counted as waiting.
+    pub fn wait<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+        let result = f();
+        self.0.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+
+    fn all_waiting(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// Text printed by a spawned function, and whether it goes to the error output.
+pub type Printed = (String, bool);
+
+/// Output of a spawned function, sent to the thread that spawned it to be printed there.
+#[derive(Debug)]
+struct SentOutput {
+    sender: Sender<Printed>,
+    error: bool,
+}
+
+impl Output for SentOutput {
+    fn print(&mut self, text: &str) -> io::Result<()> {
+        self.sender
+            .send((text.to_string(), self.error))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The program has ended"))
+    }
+}
+
+/// Input of a spawned function, the input of the program is only read by its main thread.
+#[derive(Debug)]
+struct NoInput;
+
+impl Input for NoInput {
+    fn read_line(&mut self, _: &mut String) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "a spawned function cannot read the input",
+        ))
+    }
+}
+
+/// Queue of values shared by the functions running on different threads.
+///
+/// Every copy of a channel sends to and receives from the same queue.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    sender: Sender<TypeVal>,
+    receiver: Arc<Mutex<Receiver<TypeVal>>>,
+}
+
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.receiver, &other.receiver)
+    }
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Channel {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+}
+
+impl Channel {
+    pub fn send(&self, value: TypeVal) -> Result<(), String> {
+        self.sender
+            .send(value)
+            .map_err(|_| "The channel is closed".to_string())
+    }
+
+    /// Wait for the next value sent to the channel, failing when every thread of the program waits.
+    ///
+    /// idle: called each time the wait lasted WAIT without a value.
+    pub fn recv(&self, threads: &Threads, mut idle: impl FnMut()) -> Result<TypeVal, String> {
+        threads.wait(|| {
+            let receiver = self
+                .receiver
+                .lock()
+                .map_err(|_| "The channel is closed".to_string())?;
+            // A thread that has just received a value is not counted yet, so every thread must
+            // still be waiting after a second wait
+            let mut stalled = false;
+            loop {
+                match receiver.recv_timeout(WAIT) {
+                    Ok(value) => return Ok(value),
+                    Err(_) if stalled && threads.all_waiting() => {
+                        return Err("recv waits forever, every thread of the program is waiting"
+                            .to_string())
+                    }
+                    Err(_) => stalled = threads.all_waiting(),
+                }
+                idle();
+            }
+        })
+    }
+}
+
+/// Call a function on a new thread, in a fresh interpreter configured as the one of the caller.
+///
+/// Scopes cannot be shared between threads: the function only gets a copy of its arguments.
+/// What it prints is sent to the caller, which writes it to its own outputs, and it cannot read
+/// the input.
+///
+/// functions: the spawned function and the global functions it can call.
+pub fn spawn(
+    runtime: &Runtime,
+    name: &str,
+    functions: HashMap<String, Arc<FunctionDef>>,
+    arguments: Vec<TypeVal>,
+) -> JoinHandle<Result<(), String>> {
+    let name = name.to_string();
+    let src = runtime.src.clone();
+    let sandbox = runtime.sandbox;
+    let prelude = runtime.prelude;
+    let plugins = runtime.plugins.clone();
+    let max_depth = runtime.max_depth;
+    let memory = Memory {
+        max_values: runtime.memory.max_values,
+        max_bytes: runtime.memory.max_bytes,
+        ..Default::default()
+    };
+    let cancelled = runtime.cancelled.clone();
+    let printed = runtime.printed.0.clone();
+    let threads = runtime.threads.clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let jit = runtime.jit.as_ref().map(|_| Jit::default());
+    threads.0.fetch_add(1, Ordering::SeqCst);
+    thread::spawn(move || {
+        let mut runtime = Runtime::new(&src);
+        runtime.output = Box::new(SentOutput {
+            sender: printed.clone(),
+            error: false,
+        });
+        runtime.error_output = Box::new(SentOutput {
+            sender: printed,
+            error: true,
+        });
+        runtime.input = Box::new(NoInput);
+        runtime.threads = threads.clone();
+        runtime.sandbox = sandbox;
+        runtime.prelude = prelude;
+        runtime.plugins = plugins;
+        runtime.max_depth = max_depth;
+        runtime.memory = memory;
+        runtime.cancelled = cancelled;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            runtime.jit = jit;
+        }
+        let mut interpreter = Interpreter::with_runtime(runtime);
+        let result = functions
+            .into_iter()
+            .try_for_each(|(function_name, function)| {
+                interpreter
+                    .env
+                    .insert_function(&function_name, function)
+                    .map(|_| ())
+            })
+            .and_then(|_| interpreter.call_function(&name, arguments));
+        let joined = interpreter.runtime.borrow_mut().join_spawned();
+        threads.0.fetch_sub(1, Ordering::SeqCst);
+        result.and(joined)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_share_the_queue() {
+        let channel = Channel::default();
+        let copy = channel.clone();
+        let handle = thread::spawn(move || copy.send(TypeVal::Int(1)));
+        assert_eq!(
+            channel.recv(&Threads::default(), || ()),
+            Ok(TypeVal::Int(1))
+        );
+        assert_eq!(handle.join().unwrap(), Ok(()));
+        assert_ne!(channel, Channel::default());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::parsing::ast::{Statement, StatementKind};
+use std::collections::{BTreeSet, HashSet};
+
+/// Statements executed during the run, identified by the start of their span.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    statements: Vec<usize>,
+    executed: HashSet<usize>,
+}
+
+impl Coverage {
+    pub fn new(tree: &[Statement]) -> Self {
+        let mut coverage = Coverage::default();
+        coverage.collect(tree);
+        coverage
+    }
+
+    fn collect(&mut self, tree: &[Statement]) {
+        for stmt in tree {
+            self.statements.push(stmt.span.start);
+            match &stmt.kind {
+                StatementKind::IfStatement { then_part, .. } => self.collect(then_part),
+                StatementKind::IfElseStatement {
+                    then_part,
+                    else_part,
+                    ..
+                } => {
+                    self.collect(then_part);
+                    self.collect(else_part);
+                }
+                StatementKind::WhileStatement { body, .. }
+                | StatementKind::MeasureStatement { body, .. } => self.collect(body),
+                StatementKind::FunctionDeclaration { body, .. } => self.collect(body),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn mark(&mut self, stmt: &Statement) {
+        self.executed.insert(stmt.span.start);
+    }
+
+    /// Summary of the statements executed and of the lines holding statements never executed.
+    pub fn report(&self, path: &str, src: &str) -> String {
+        let missed: Vec<usize> = self
+            .statements
+            .iter()
+            .copied()
+            .filter(|start| !self.executed.contains(start))
+            .collect();
+        let executed = self.statements.len() - missed.len();
+        let percentage = match self.statements.len() {
+            0 => 100.0,
+            total => executed as f64 * 100.0 / total as f64,
+        };
+        let mut out = format!(
+            "Coverage of {}: {}/{} statements executed ({:.1}%)\n",
+            path,
+            executed,
+            self.statements.len(),
+            percentage
+        );
+        if !missed.is_empty() {
+            let lines = missed
+                .iter()
+                .map(|start| src[..*start].matches('\n').count() + 1)
+                .collect();
+            out.push_str(&format!("Lines never executed: {}\n", line_ranges(&lines)));
+        }
+        out
+    }
+}
+
+/// Compact list of lines, consecutive lines are shown as ranges.
+fn line_ranges(lines: &BTreeSet<usize>) -> String {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    #[test]
+    fn reports_lines_never_executed() {
+        let src =
+            "let mut a = 1;\nif a > 1 {\n    a = 2;\n    a = 3;\n} else {\n    a = 4;\n}\nprintl(a);";
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        let mut coverage = Coverage::new(&ast);
+        coverage.mark(&ast[0]);
+        coverage.mark(&ast[1]);
+        coverage.mark(&ast[2]);
+        if let StatementKind::IfElseStatement { else_part, .. } = &ast[1].kind {
+            coverage.mark(&else_part[0]);
+        }
+        assert_eq!(
+            coverage.report("test.grim", src),
+            "Coverage of test.grim: 4/6 statements executed (66.7%)\nLines never executed: 3-4\n"
+        );
+    }
+}
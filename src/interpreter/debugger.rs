@@ -0,0 +1,155 @@
+use crate::interpreter::environment::Environment;
+use crate::parsing::ast::{Statement, StatementKind};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+
+const HELP: &str = "Commands:\n  \
+    n, next           execute the next statement\n  \
+    c, continue       run until the next breakpoint\n  \
+    v, vars           show the variables of the current scope\n  \
+    p, print NAME     show the value of a variable\n  \
+    b, break LINE     pause when the line is reached\n  \
+    w, watch NAME     report every change of a variable\n  \
+    q, quit           stop the program";
+
+/// Interactive debugger pausing the execution at `breakpoint;` statements and at the given lines.
+///
+/// stepping: the execution pauses before every statement.
+///
+/// watches: variables whose changes are printed, with their old and new values.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    pub breakpoints: HashSet<usize>,
+    pub watches: HashSet<String>,
+    stepping: bool,
+}
+
+/// What happens after a command of the user.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Show(String),
+    Step,
+    Continue,
+    Quit,
+}
+
+impl Debugger {
+    pub fn new(breakpoints: HashSet<usize>) -> Self {
+        Self {
+            breakpoints,
+            ..Default::default()
+        }
+    }
+
+    /// Pause before the statement if needed and read commands until the user resumes the execution.
+    pub fn before_statement(
+        &mut self,
+        stmt: &Statement,
+        line: usize,
+        src: &str,
+        env: &Environment,
+    ) -> Result<(), String> {
+        let at_breakpoint =
+            stmt.kind == StatementKind::BreakpointStatement || self.breakpoints.contains(&line);
+        if !self.stepping && !at_breakpoint {
+            return Ok(());
+        }
+
+        let source_line = src.lines().nth(line - 1).unwrap_or_default().trim();
+        eprintln!(
+            "{}",
+            format!("Paused at line {}: {}", line, source_line).cyan()
+        );
+        loop {
+            eprint!("(debug) ");
+            io::stderr().flush().unwrap();
+            let mut command = String::new();
+            // Once the input is over the program runs to the end
+            if io::stdin().read_line(&mut command).unwrap_or(0) == 0 {
+                self.stepping = false;
+                self.breakpoints.clear();
+                return Ok(());
+            }
+            match self.run_command(command.trim(), env) {
+                Action::Show(text) => eprintln!("{}", text),
+                Action::Step => {
+                    self.stepping = true;
+                    return Ok(());
+                }
+                Action::Continue => {
+                    self.stepping = false;
+                    return Ok(());
+                }
+                Action::Quit => return Err("Execution stopped by the debugger".to_string()),
+            }
+        }
+    }
+
+    fn run_command(&mut self, command: &str, env: &Environment) -> Action {
+        match command.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["n" | "next"] => Action::Step,
+            ["c" | "continue"] => Action::Continue,
+            ["q" | "quit"] => Action::Quit,
+            ["v" | "vars"] => Action::Show(
+                env.visible_variables()
+                    .iter()
+                    .map(|(name, value)| format!("{} = {} ({})", name, value, value.get_type()))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            ["p" | "print", name] => Action::Show(match env.get_variable_value(name) {
+                Ok(value) => format!("{} = {} ({})", name, value, value.get_type()),
+                Err(err) => err,
+            }),
+            ["b" | "break", line] => match line.parse::<usize>() {
+                Ok(line) => {
+                    self.breakpoints.insert(line);
+                    Action::Show(format!("Breakpoint set at line {}", line))
+                }
+                Err(_) => Action::Show(format!("{} is not a valid line", line)),
+            },
+            ["w" | "watch", name] => {
+                self.watches.insert(name.to_string());
+                Action::Show(format!("Watching `{}`", name))
+            }
+            _ => Action::Show(HELP.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::TypeVal;
+
+    #[test]
+    fn inspects_scope() {
+        let mut env = Environment::default();
+        env.insert_value("a", &TypeVal::Int(1)).unwrap();
+        env.push_block();
+        env.insert_value("b", &TypeVal::Boolean(true)).unwrap();
+
+        let mut debugger = Debugger::default();
+        assert_eq!(
+            debugger.run_command("vars", &env),
+            Action::Show("a = 1 (int)\nb = true (bool)".to_string())
+        );
+        assert_eq!(
+            debugger.run_command("p b", &env),
+            Action::Show("b = true (bool)".to_string())
+        );
+        assert_eq!(
+            debugger.run_command("break 4", &env),
+            Action::Show("Breakpoint set at line 4".to_string())
+        );
+        assert!(debugger.breakpoints.contains(&4));
+        assert_eq!(
+            debugger.run_command("watch a", &env),
+            Action::Show("Watching `a`".to_string())
+        );
+        assert!(debugger.watches.contains("a"));
+        assert_eq!(debugger.run_command("next", &env), Action::Step);
+    }
+}
@@ -0,0 +1,177 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Digits kept after the point, the scale of a product is capped to it.
+pub const MAX_SCALE: u32 = 28;
+
+/// Exact decimal number, mantissa / 10^scale.
+///
+/// The scale of the literal is kept: decimal("1.10") is printed as 1.10 and equals decimal("1.1").
+#[derive(Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Read a decimal written as an optional sign, digits and an optional fractional part.
+    pub fn parse(text: &str) -> Result<Decimal, String> {
+        let invalid = || format!("{} is not a decimal number", text);
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty()
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+            || (digits.contains('.') && fraction.is_empty())
+        {
+            return Err(invalid());
+        }
+        if fraction.len() > MAX_SCALE as usize {
+            return Err(format!(
+                "{} has more than {} digits after the point",
+                text, MAX_SCALE
+            ));
+        }
+        let mantissa: i128 = format!("{}{}", integer, fraction)
+            .parse()
+            .map_err(|_| format!("{} is too large for a decimal", text))?;
+        Ok(Decimal {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale: fraction.len() as u32,
+        })
+    }
+
+    /// Mantissa of the number written with the given scale, None on overflow.
+    fn rescaled(&self, scale: u32) -> Option<i128> {
+        10i128
+            .checked_pow(scale - self.scale)
+            .and_then(|factor| self.mantissa.checked_mul(factor))
+    }
+
+    /// Mantissas of both numbers written with the larger scale.
+    fn aligned(&self, other: &Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Some((self.rescaled(scale)?, other.rescaled(scale)?, scale))
+    }
+
+    pub fn add(&self, other: &Decimal) -> Result<Decimal, String> {
+        self.aligned(other)
+            .and_then(|(x, y, scale)| Some((x.checked_add(y)?, scale)))
+            .map(|(mantissa, scale)| Decimal { mantissa, scale })
+            .ok_or_else(|| "Decimal sum overflows".to_string())
+    }
+
+    pub fn sub(&self, other: &Decimal) -> Result<Decimal, String> {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Decimal) -> Result<Decimal, String> {
+        let overflow = || "Decimal product overflows".to_string();
+        let mut mantissa = self
+            .mantissa
+            .checked_mul(other.mantissa)
+            .ok_or_else(overflow)?;
+        let mut scale = self.scale + other.scale;
+        // Trailing zeros beyond the cap are dropped, the other digits would be lost
+        while scale > MAX_SCALE && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        match scale > MAX_SCALE {
+            true => Err(format!(
+                "Decimal product has more than {} digits after the point",
+                MAX_SCALE
+            )),
+            false => Ok(Decimal { mantissa, scale }),
+        }
+    }
+
+    pub fn neg(&self) -> Decimal {
+        Decimal {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(x: i64) -> Decimal {
+        Decimal {
+            mantissa: x as i128,
+            scale: 0,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match self.aligned(other) {
+            Some((x, y, _)) => x.cmp(&y),
+            // Only the larger scale overflows: the integer parts differ
+            None => (self.mantissa / 10i128.pow(self.scale))
+                .cmp(&(other.mantissa / 10i128.pow(other.scale))),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = format!(
+            "{:0>width$}",
+            self.mantissa.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        match fraction.is_empty() {
+            true => write!(f, "{}{}", sign, integer),
+            false => write!(f, "{}{}.{}", sign, integer, fraction),
+        }
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_exactly() {
+        let dec = |text| Decimal::parse(text).unwrap();
+        let tenth = dec("0.1");
+        assert_eq!(tenth.add(&dec("0.2")).unwrap(), dec("0.3"));
+        assert_eq!(dec("1.10").to_string(), "1.10");
+        assert_eq!(dec("1.10"), dec("1.1"));
+        assert_eq!(dec("1.10").mul(&dec("3")).unwrap().to_string(), "3.30");
+        assert_eq!(dec("0.05").sub(&dec("1")).unwrap().to_string(), "-0.95");
+        assert_eq!(dec("-0.5").to_string(), "-0.5");
+        assert!(dec("2.5") > Decimal::from(2));
+        assert!(Decimal::parse("1.").is_err());
+        assert!(Decimal::parse("1e3").is_err());
+        assert!(Decimal::parse("").is_err());
+    }
+}
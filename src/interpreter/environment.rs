@@ -0,0 +1,429 @@
+use crate::interpreter::error_reporting::did_you_mean;
+use crate::interpreter::interpreter::{apply_type_annotation, FunctionDef, TypeVal};
+use crate::interpreter::runtime::Runtime;
+use crate::parsing::ast::Type;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Variables and functions declared by a running block.
+///
+/// slots, values, names: the variables, each bound to the slot holding its value and its name.
+///
+/// immutable: the variables declared without mut, only their declaration binds them.
+///
+/// function: the frame holds the arguments of a function, the frames below it are not visible
+/// except for the functions of the global frame.
+#[derive(Debug, Default)]
+pub struct Frame {
+    slots: HashMap<String, usize>,
+    values: Vec<TypeVal>,
+    names: Vec<String>,
+    annotations: HashMap<String, Type>,
+    immutable: HashSet<String>,
+    functions: HashMap<String, Arc<FunctionDef>>,
+    function: bool,
+}
+
+impl Frame {
+    fn value(&self, variable_name: &str) -> Option<&TypeVal> {
+        self.slots
+            .get(variable_name)
+            .map(|&slot| &self.values[slot])
+    }
+}
+
+/// Break or continue statement leaving the blocks up to the loop it targets, the innermost one
+/// if it has no label.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopExit {
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+impl LoopExit {
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            LoopExit::Break(label) | LoopExit::Continue(label) => label.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for LoopExit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match self {
+            LoopExit::Break(_) => "break",
+            LoopExit::Continue(_) => "continue",
+        };
+        match self.label() {
+            Some(label) => write!(f, "{} {}", keyword, label),
+            None => write!(f, "{}", keyword),
+        }
+    }
+}
+
+/// Stack of the frames of the running blocks, the first one holds the global declarations.
+///
+/// returning, return_value: a return statement was executed and the value it returned.
+///
+/// loop_exit: a break or a continue was executed and no loop handled it yet.
+///
+/// exit_code: exit was called, every block and function is left as with a return.
+///
+/// panic: message and calls of the panic raised, it ends the program.
+///
+/// runtime: state shared by all the frames of the program.
+#[derive(Debug)]
+pub struct Environment {
+    frames: Vec<Frame>,
+    pub returning: bool,
+    pub return_value: TypeVal,
+    pub loop_exit: Option<LoopExit>,
+    pub exit_code: Option<i32>,
+    pub panic: Option<String>,
+    pub runtime: Rc<RefCell<Runtime>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new(Rc::default())
+    }
+}
+
+impl Environment {
+    pub fn new(runtime: Rc<RefCell<Runtime>>) -> Self {
+        Environment {
+            frames: vec![Frame {
+                function: true,
+                ..Default::default()
+            }],
+            returning: false,
+            return_value: TypeVal::default(),
+            loop_exit: None,
+            exit_code: None,
+            panic: None,
+            runtime,
+        }
+    }
+
+    /// Forget every declaration, as if no program ran before.
+    pub fn reset(&mut self) {
+        while self.frames.len() > 1 {
+            self.pop();
+        }
+        let globals = std::mem::take(&mut self.frames[0]);
+        let mut runtime = self.runtime.borrow_mut();
+        for value in &globals.values {
+            runtime.memory.release(value);
+        }
+        self.frames[0].function = true;
+        self.returning = false;
+        self.loop_exit = None;
+        self.exit_code = None;
+        self.panic = None;
+    }
+
+    /// Enter a block, seeing the variables of the enclosing ones.
+    pub fn push_block(&mut self) {
+        self.frames.push(Frame::default());
+    }
+
+    /// Enter the body of a function, seeing only what it declares and the global functions.
+    pub fn push_function(&mut self) {
+        self.frames.push(Frame {
+            function: true,
+            ..Default::default()
+        });
+    }
+
+    /// Leave the current block, releasing its variables.
+    pub fn pop(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            let mut runtime = self.runtime.borrow_mut();
+            for value in &frame.values {
+                runtime.memory.release(value);
+            }
+        }
+    }
+
+    /// Frames seen by the current block, from the innermost.
+    fn visible_frames(&self) -> impl Iterator<Item = &Frame> {
+        let first = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.function)
+            .unwrap_or(0);
+        self.frames[first..].iter().rev()
+    }
+
+    /// Frames whose functions the current block can call, the global frame comes last.
+    fn function_frames(&self) -> impl Iterator<Item = &Frame> {
+        let globals_hidden = self.frames.iter().rposition(|frame| frame.function) != Some(0);
+        self.visible_frames()
+            .chain(self.frames.iter().take(usize::from(globals_hidden)))
+    }
+
+    fn current(&self) -> &Frame {
+        self.frames
+            .last()
+            .expect("the global frame is never popped")
+    }
+
+    fn current_mut(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("the global frame is never popped")
+    }
+
+    /// Insert value for the first time in the current block.
+    pub fn insert_value(&mut self, variable_name: &str, value: &TypeVal) -> Result<String, String> {
+        if self.current().value(variable_name).is_some() {
+            Err(format!(
+                "A variable with this name ({}) already exists and it is in scope",
+                variable_name
+            ))
+        } else if self
+            .visible_frames()
+            .any(|frame| frame.slots.contains_key(variable_name))
+        {
+            Err(format!("You are overshadowing ({})", variable_name))
+        } else {
+            self.bind(variable_name, value.clone())?;
+            Ok("Correct insertion".to_string())
+        }
+    }
+
+    /// Insert function for the first time in the current block.
+    pub fn insert_function(
+        &mut self,
+        function_name: &str,
+        function: Arc<FunctionDef>,
+    ) -> Result<String, String> {
+        if self.current().functions.contains_key(function_name) {
+            Err(format!(
+                "A function with this name ({}) already exists and it is in scope",
+                function_name
+            ))
+        } else {
+            self.current_mut()
+                .functions
+                .insert(function_name.to_string(), function);
+            Ok("Correct insertion".to_string())
+        }
+    }
+
+    /// Get value of a variable, searching from the current block outwards.
+    ///
+    /// On failure the closest visible name is suggested.
+    pub fn get_variable_value(&self, variable_name: &str) -> Result<TypeVal, String> {
+        match self
+            .visible_frames()
+            .find_map(|frame| frame.value(variable_name))
+        {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!(
+                "Variable `{}` does not exist{}",
+                variable_name,
+                did_you_mean(
+                    variable_name,
+                    self.visible_frames().flat_map(|frame| frame.slots.keys())
+                )
+            )),
+        }
+    }
+
+    /// Get the value of a variable in a slot of the block `depth` levels above the current one.
+    ///
+    /// None if the slot does not hold the variable, the blocks differ from the ones resolved.
+    pub fn get_slot(&self, variable_name: &str, depth: usize, slot: usize) -> Option<TypeVal> {
+        let index = self.frames.len().checked_sub(depth + 1)?;
+        let frame = &self.frames[index];
+        match frame.names.get(slot) {
+            Some(name) if name == variable_name => Some(frame.values[slot].clone()),
+            _ => None,
+        }
+    }
+
+    /// Get the definition of a function, searching from the current block outwards and then in the
+    /// global frame.
+    ///
+    /// On failure the closest visible name is suggested.
+    pub fn get_function_info(&self, function_name: &str) -> Result<Arc<FunctionDef>, String> {
+        match self
+            .function_frames()
+            .find_map(|frame| frame.functions.get(function_name))
+        {
+            Some(function) => Ok(Arc::clone(function)),
+            None => Err(format!(
+                "Function `{}` does not exist{}",
+                function_name,
+                did_you_mean(
+                    function_name,
+                    self.function_frames()
+                        .flat_map(|frame| frame.functions.keys())
+                )
+            )),
+        }
+    }
+
+    /// Function declared in the global frame.
+    pub fn global_function(&self, function_name: &str) -> Option<&Arc<FunctionDef>> {
+        self.frames[0].functions.get(function_name)
+    }
+
+    /// Functions declared in the global frame.
+    pub fn global_functions(&self) -> HashMap<String, Arc<FunctionDef>> {
+        self.frames[0].functions.clone()
+    }
+
+    /// Variables declared in the global frame, with whether they can be assigned.
+    pub fn global_variables(&self) -> HashMap<String, bool> {
+        let globals = &self.frames[0];
+        globals
+            .slots
+            .keys()
+            .map(|name| (name.clone(), !globals.immutable.contains(name)))
+            .collect()
+    }
+
+    /// Update value of a variable in the closest block declaring it.
+    pub fn update_value(&mut self, variable_name: &str, value: &TypeVal) -> Result<String, String> {
+        let first = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.function)
+            .unwrap_or(0);
+        let index = match (first..self.frames.len())
+            .rev()
+            .find(|&index| self.frames[index].slots.contains_key(variable_name))
+        {
+            Some(index) => index,
+            None => return Err(format!("{} does not exist", variable_name)),
+        };
+        if self.frames[index].immutable.contains(variable_name) {
+            return Err(format!(
+                "Cannot assign twice to the immutable variable ({}), declare it with let mut",
+                variable_name
+            ));
+        }
+        let annotation = self.frames[index].annotations.get(variable_name).copied();
+        let value = apply_type_annotation(variable_name, value.clone(), &annotation)?;
+        let slot = self.frames[index].slots[variable_name];
+        let mut runtime = self.runtime.borrow_mut();
+        let old = &self.frames[index].values[slot];
+        runtime.memory.bind(Some(old), &value)?;
+        runtime.changed(variable_name, Some(old), &value)?;
+        drop(runtime);
+        self.frames[index].values[slot] = value;
+        Ok("Correct assignment".to_string())
+    }
+
+    /// Bind a value to a variable of the current block, accounting it in the memory of the runtime.
+    ///
+    /// A new variable takes the next free slot.
+    pub fn bind(&mut self, variable_name: &str, value: TypeVal) -> Result<(), String> {
+        let mut runtime = self.runtime.borrow_mut();
+        let old = self.current().value(variable_name);
+        runtime.memory.bind(old, &value)?;
+        runtime.changed(variable_name, old, &value)?;
+        drop(runtime);
+        let frame = self.current_mut();
+        match frame.slots.get(variable_name) {
+            Some(&slot) => frame.values[slot] = value,
+            None => {
+                frame
+                    .slots
+                    .insert(variable_name.to_string(), frame.values.len());
+                frame.values.push(value);
+                frame.names.push(variable_name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the declared type of a variable of the current block.
+    pub fn annotate(&mut self, variable_name: &str, annotation: &Option<Type>) {
+        if let Some(annotation) = annotation {
+            self.current_mut()
+                .annotations
+                .insert(variable_name.to_string(), *annotation);
+        }
+    }
+
+    /// Forbid the assignments to a variable of the current block.
+    pub fn freeze(&mut self, variable_name: &str) {
+        self.current_mut()
+            .immutable
+            .insert(variable_name.to_string());
+    }
+
+    /// Value of a variable bound in the current block.
+    pub fn local_value(&self, variable_name: &str) -> Option<&TypeVal> {
+        self.current().value(variable_name)
+    }
+
+    /// Names of the variables bound in the current block, ordered by slot.
+    pub fn local_names(&self) -> Vec<String> {
+        self.current().names.clone()
+    }
+
+    /// Variables of each block seen by the current one, from the innermost, sorted by name.
+    pub fn visible_scopes(&self) -> Vec<BTreeMap<String, TypeVal>> {
+        self.visible_frames()
+            .map(|frame| {
+                frame
+                    .slots
+                    .iter()
+                    .map(|(name, &slot)| (name.clone(), frame.values[slot].clone()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Values of all the variables seen by the current block, sorted by name.
+    pub fn visible_variables(&self) -> BTreeMap<String, TypeVal> {
+        let mut variables = BTreeMap::new();
+        for frame in self.visible_frames() {
+            for (name, &slot) in &frame.slots {
+                variables
+                    .entry(name.clone())
+                    .or_insert_with(|| frame.values[slot].clone());
+            }
+        }
+        variables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn functions_hide_the_caller_blocks() {
+        let mut env = Environment::default();
+        env.insert_value("a", &TypeVal::Int(1)).unwrap();
+        env.push_block();
+        env.insert_value("b", &TypeVal::Int(2)).unwrap();
+        assert!(env
+            .insert_value("a", &TypeVal::Int(3))
+            .unwrap_err()
+            .contains("overshadowing"));
+        assert_eq!(env.get_slot("a", 1, 0), Some(TypeVal::Int(1)));
+        assert_eq!(env.get_slot("b", 1, 0), None);
+        assert_eq!(env.get_slot("a", 0, 0), None);
+
+        env.push_function();
+        env.insert_value("a", &TypeVal::Int(3)).unwrap();
+        assert_eq!(env.get_variable_value("a"), Ok(TypeVal::Int(3)));
+        assert!(env.get_variable_value("b").is_err());
+        env.pop();
+
+        env.update_value("a", &TypeVal::Int(4)).unwrap();
+        env.pop();
+        assert_eq!(env.get_variable_value("a"), Ok(TypeVal::Int(4)));
+        assert!(env.get_variable_value("b").is_err());
+        assert_eq!(env.runtime.borrow().memory.values, 1);
+    }
+}
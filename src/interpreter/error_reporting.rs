@@ -1,5 +1,47 @@
 use crate::interpreter::interpreter::TypeVal;
 use colored::Colorize;
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// When the messages are colored.
+///
+/// Auto colors them unless NO_COLOR is set to a non empty value or stdout or stderr is not a
+/// terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Read the value of the --color flag.
+    pub fn parse(value: &str) -> Option<ColorChoice> {
+        match value {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// Whether to color, given the value of NO_COLOR and if the output streams are terminals.
+    pub fn enabled(self, no_color: Option<&str>, terminals: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => no_color.is_none_or(str::is_empty) && terminals,
+        }
+    }
+}
+
+/// Color the messages built from now on according to choice.
+pub fn set_color(choice: ColorChoice) {
+    let no_color = env::var("NO_COLOR").ok();
+    let terminals = io::stdout().is_terminal() && io::stderr().is_terminal();
+    colored::control::set_override(choice.enabled(no_color.as_deref(), terminals));
+}
 
 /// Build a generic error message
 pub fn error_reporting_generic(err_message: String) -> Result<TypeVal, String> {
@@ -13,8 +55,7 @@ pub fn error_reporting_unary_operator(
     val1: &TypeVal,
 ) -> Result<TypeVal, String> {
     let err_mess = err_message.red();
-    let var1 = format!("{:?}", val1);
-    Err(format!("{} -> {}", err_mess, var1))
+    Err(format!("{} -> {}", err_mess, val1.describe()))
 }
 
 /// Build and return an error message for binary operator
@@ -24,7 +65,67 @@ pub fn error_reporting_binary_operator(
     val2: &TypeVal,
 ) -> Result<TypeVal, String> {
     let err_mess = err_message.red();
-    let var1 = format!("{:?}", val1);
-    let var2 = format!("{:?}", val2);
-    Err(format!("{} -> {} and {}", err_mess, var1, var2))
+    Err(format!(
+        "{} -> {} and {}",
+        err_mess,
+        val1.describe(),
+        val2.describe()
+    ))
+}
+
+/// Suggest the closest name among the candidates, if any is close enough to be a typo.
+pub fn did_you_mean<'a>(name: &str, candidates: impl Iterator<Item = &'a String>) -> String {
+    let max_distance = name.chars().count() / 3;
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| format!("; did you mean `{}`?", candidate))
+        .unwrap_or_default()
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chooses_when_to_color() {
+        assert_eq!(ColorChoice::parse("never"), Some(ColorChoice::Never));
+        assert_eq!(ColorChoice::parse("sometimes"), None);
+        assert!(ColorChoice::Auto.enabled(None, true));
+        assert!(ColorChoice::Auto.enabled(Some(""), true));
+        assert!(!ColorChoice::Auto.enabled(Some("1"), true));
+        assert!(!ColorChoice::Auto.enabled(None, false));
+        assert!(ColorChoice::Always.enabled(Some("1"), false));
+        assert!(!ColorChoice::Never.enabled(None, true));
+    }
+
+    #[test]
+    fn suggests_closest_name() {
+        let names = [
+            "counter".to_string(),
+            "count".to_string(),
+            "total".to_string(),
+        ];
+        assert_eq!(
+            did_you_mean("contuer", names.iter()),
+            "; did you mean `counter`?"
+        );
+        assert_eq!(did_you_mean("x", names.iter()), "");
+    }
 }
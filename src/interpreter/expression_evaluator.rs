@@ -1,1407 +1,313 @@
-use crate::interpreter::error_reporting::{
-    error_reporting_binary_operator, error_reporting_generic, error_reporting_unary_operator,
+use crate::interpreter::builtins::{
+    call_builtin, call_by_name, call_channel_builtin, call_input_builtin, call_output_builtin,
+    call_process_builtin, call_random_builtin, constant, dump_scope, exit, format, is_builtin,
+    panic,
 };
-use crate::interpreter::interpreter::TypeVal::{Boolean, Float, Int, Str};
-use crate::interpreter::interpreter::{evaluate_ast, Scope, TypeVal};
-use crate::parsing::ast::{BinaryOperator, Expression, Statement, UnaryOperator};
-use std::cell::RefCell;
+use crate::interpreter::environment::Environment;
+use crate::interpreter::error_reporting::error_reporting_generic;
+use crate::interpreter::interpreter::TypeVal::{Boolean, Char, Float, Int, Str};
+use crate::interpreter::interpreter::{apply_type_annotation, evaluate_ast, FunctionDef, TypeVal};
+use crate::parsing::ast::{BinaryOperator, Expression, UnaryOperator};
 use std::iter::zip;
 use std::rc::Rc;
+use std::sync::Arc;
+
+/// Work left to evaluate an expression.
+///
+/// Operands are evaluated before the operation using them, so nested expressions
+/// do not grow the Rust stack.
+enum Task<'a> {
+    Evaluate(&'a Expression),
+    Binary(&'a BinaryOperator),
+    Unary(&'a UnaryOperator),
+    Index,
+    Call {
+        name: &'a str,
+        arguments: &'a [Box<Expression>],
+        evaluated: usize,
+    },
+}
 
 /// Function used to evaluate expression.
-pub fn evaluate_expression(
-    scope: &&mut Rc<RefCell<Scope>>,
-    expr: &Box<Expression>,
-) -> Result<TypeVal, String> {
-    match expr.as_ref() {
-        Expression::Int(x) => Ok(Int(*x)),
-        Expression::Float(x) => Ok(Float(*x)),
-        Expression::Bool(x) => Ok(Boolean(*x)),
-        Expression::Str(x) => Ok(Str(x.clone())),
-        Expression::BinaryOperation { lhs, operator, rhs } => {
-            bin_op_evaluator(scope, lhs, operator, rhs)
-        }
-        Expression::UnaryOperation { operator, rhs } => match operator {
-            UnaryOperator::Minus => {
-                let right = evaluate_expression(scope, &rhs);
-                match right {
-                    Ok(Int(x)) => Ok(Int(-x)),
-                    Ok(Float(x)) => Ok(Float(-x)),
-                    Ok(Boolean(x)) => error_reporting_unary_operator(
-                        "Minus boolean is not supported".to_string(),
-                        &Boolean(x),
-                    ),
-                    Ok(Str(x)) => error_reporting_unary_operator(
-                        "Minus boolean is not supported".to_string(),
-                        &Str(x),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                }
+pub fn evaluate_expression(env: &mut Environment, expr: &Expression) -> Result<TypeVal, String> {
+    match expr {
+        Expression::BinaryOperation { .. }
+        | Expression::UnaryOperation { .. }
+        | Expression::Index { .. }
+        | Expression::FunctionCall { .. } => {}
+        operand => return evaluate_operand(env, operand),
+    }
+    let mut tasks = vec![Task::Evaluate(expr)];
+    let mut values: Vec<Result<TypeVal, String>> = vec![];
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Evaluate(Expression::BinaryOperation { lhs, operator, rhs }) => {
+                tasks.push(Task::Binary(operator));
+                tasks.push(Task::Evaluate(rhs));
+                tasks.push(Task::Evaluate(lhs));
             }
-            UnaryOperator::Not => {
-                let right = evaluate_expression(scope, &rhs);
-                match right {
-                    Ok(Int(x)) => error_reporting_unary_operator(
-                        "Not int is not supported".to_string(),
-                        &Int(x),
-                    ),
-                    Ok(Float(x)) => error_reporting_unary_operator(
-                        "Not float is not supported".to_string(),
-                        &Float(x),
-                    ),
-                    Ok(Boolean(x)) => {
-                        if x {
-                            Ok(Boolean(false))
-                        } else {
-                            Ok(Boolean(true))
-                        }
-                    }
-                    Ok(Str(x)) => error_reporting_unary_operator(
-                        "Not string is not supported".to_string(),
-                        &Str(x),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                }
+            Task::Evaluate(Expression::UnaryOperation { operator, rhs }) => {
+                tasks.push(Task::Unary(operator));
+                tasks.push(Task::Evaluate(rhs));
             }
-        },
-        Expression::Identifier(variable) => {
-            let var = scope.borrow().get_variable_value(variable.as_str());
-            match var {
-                Ok(var) => Ok(var),
-                Err(err) => Err(format!("Error during identifier reading\n{}\n", err)),
+            Task::Evaluate(Expression::Index { target, index }) => {
+                tasks.push(Task::Index);
+                tasks.push(Task::Evaluate(index));
+                tasks.push(Task::Evaluate(target));
             }
-        }
-        Expression::FunctionCall { name, arguments } => {
-            let mut fun_args: Vec<String> = vec![];
-            let mut fun_body: Vec<Statement> = vec![];
-            match scope.borrow().get_function_info(name) {
-                Ok((x, y)) => {
-                    fun_args = x;
-                    fun_body = y;
-                }
-                Err(err) => return Err(format!("Error during function evaluation\n{}\n", err)),
+            Task::Evaluate(Expression::FunctionCall { name, arguments }) => {
+                tasks.push(Task::Call {
+                    name,
+                    arguments,
+                    evaluated: 0,
+                });
             }
-            let mut fun_scope = Rc::new(RefCell::new(Scope::default()));
-            match fun_scope
-                .borrow_mut()
-                .insert_function(name, &fun_args, &fun_body)
-            {
-                Ok(_) => (),
-                Err(err) => return Err(format!("Error during function evaluation\n{}\n", err)),
+            Task::Evaluate(expr) => values.push(evaluate_operand(env, expr)),
+            Task::Binary(operator) => {
+                let right = pop_value(&mut values);
+                let left = pop_value(&mut values);
+                values.push(bin_op_evaluator(left, operator, right));
             }
-
-            // Bind each argument with its value
-            for (f_args, args) in zip(fun_args, arguments) {
-                match evaluate_expression(scope, args) {
-                    Ok(eval_exp) => {
-                        fun_scope
-                            .borrow_mut()
-                            .local_variables
-                            .insert(f_args.clone(), eval_exp);
-                        fun_scope
-                            .borrow_mut()
-                            .reachable_variables
-                            .insert(f_args.clone());
+            Task::Unary(operator) => {
+                let right = pop_value(&mut values);
+                values.push(un_op_evaluator(operator, right));
+            }
+            Task::Index => {
+                let index = pop_value(&mut values);
+                let target = pop_value(&mut values);
+                values.push(index_evaluator(target, index));
+            }
+            Task::Call {
+                name,
+                arguments,
+                evaluated,
+            } => {
+                // The call stops at the first argument that cannot be evaluated
+                if let Some(Err(_)) = values.last() {
+                    if evaluated > 0 {
+                        let err = pop_value(&mut values).unwrap_err();
+                        values.truncate(values.len() - (evaluated - 1));
+                        values.push(Err(format!("Error during function call\n{}\n", err)));
+                        continue;
                     }
-                    Err(_) => return Err("Error during function call\n".to_string()),
+                }
+                if evaluated < arguments.len() {
+                    tasks.push(Task::Call {
+                        name,
+                        arguments,
+                        evaluated: evaluated + 1,
+                    });
+                    tasks.push(Task::Evaluate(&arguments[evaluated]));
+                } else {
+                    let arguments: Result<Vec<TypeVal>, String> = values
+                        .split_off(values.len() - arguments.len())
+                        .into_iter()
+                        .collect();
+                    values
+                        .push(arguments.and_then(|arguments| call_function(env, name, arguments)));
                 }
             }
-
-            // Evaluate function scope
-            let evaluated_function = evaluate_ast(&fun_body, &mut fun_scope);
-            // Get result
-            let res = evaluated_function?;
-            let borrow_scope = res.borrow();
-            let result = borrow_scope.return_value.clone();
-            Ok(result)
         }
     }
+    pop_value(&mut values)
 }
 
-/// Evaluator of binary operations
-pub fn bin_op_evaluator(
-    scope: &&mut Rc<RefCell<Scope>>,
-    lhs: &Box<Expression>,
-    operator: &BinaryOperator,
-    rhs: &Box<Expression>,
-) -> Result<TypeVal, String> {
-    match operator {
-        BinaryOperator::Add
-        | BinaryOperator::Sub
-        | BinaryOperator::Mul
-        | BinaryOperator::Div
-        | BinaryOperator::Mod => match bin_op_arithmetic_evaluator(scope, lhs, operator, rhs) {
-            Ok(result) => Ok(result),
-            Err(err) => {
-                Err(format! {"Error during binary arithmetic expression evaluation\n{}\n", err})
+fn pop_value(values: &mut Vec<Result<TypeVal, String>>) -> Result<TypeVal, String> {
+    values
+        .pop()
+        .unwrap_or_else(|| error_reporting_generic("Missing operand".to_string()))
+}
+
+/// Evaluate an expression without operands.
+fn evaluate_operand(env: &mut Environment, expr: &Expression) -> Result<TypeVal, String> {
+    match expr {
+        Expression::Int(x) => Ok(Int(*x)),
+        Expression::Float(x) => Ok(Float(*x)),
+        Expression::Bool(x) => Ok(Boolean(*x)),
+        Expression::Char(x) => Ok(Char(*x)),
+        Expression::Str(x) => Ok(Str(env.runtime.borrow_mut().intern(x))),
+        Expression::ResolvedIdentifier { name, depth, slot } => {
+            let value = env.get_slot(name, *depth, *slot);
+            match value {
+                Some(value) => Ok(value),
+                None => evaluate_operand(env, &Expression::Identifier(name.clone())),
+            }
+        }
+        Expression::Identifier(variable) => {
+            // The resolver reports the variables declared with the name of a constant
+            if let Some(x) = constant(variable).filter(|_| env.runtime.borrow().prelude) {
+                return Ok(Float(x));
+            }
+            let var = env.get_variable_value(variable.as_str());
+            match var {
+                Ok(var) => Ok(var),
+                Err(err) => Err(format!("Error during identifier reading\n{}\n", err)),
             }
-        },
-        _ => match bin_op_logic_evaluator(scope, lhs, operator, rhs) {
-            Ok(result) => Ok(result),
-            Err(err) => Err(format! {"Error during binary logic expression evaluation\n{}", err}),
-        },
+        }
+        _ => error_reporting_generic("Expression with operands".to_string()),
     }
 }
 
-/// Evaluate binary arithmetic expressions.
-pub fn bin_op_arithmetic_evaluator(
-    scope: &&mut Rc<RefCell<Scope>>,
-    lhs: &Box<Expression>,
-    operator: &BinaryOperator,
-    rhs: &Box<Expression>,
+/// Evaluator of unary operations
+fn un_op_evaluator(
+    operator: &UnaryOperator,
+    right: Result<TypeVal, String>,
+) -> Result<TypeVal, String> {
+    right
+        .and_then(|right| right.unary(operator))
+        .map_err(|err| format! {"Error during logic expression evaluation\n{}\n", err})
+}
+
+/// Evaluator of indexing, a string is indexed by the position of a character, from 0.
+fn index_evaluator(
+    target: Result<TypeVal, String>,
+    index: Result<TypeVal, String>,
 ) -> Result<TypeVal, String> {
-    match operator {
-        BinaryOperator::Add => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Int(x + y)),
-                    Ok(Float(y)) => Ok(Float(x as f64 + y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Float(x + y as f64)),
-                    Ok(Float(y)) => Ok(Float(x + y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Sum between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Err(err) => {
-                    Err(format! {"Error during arithmetic expression evaluation\n{}\n", err})
+    target
+        .and_then(|target| target.index(&index?))
+        .map_err(|err| format! {"Error during indexing\n{}\n", err})
+}
+
+/// Call a function seen by the current block, a native function or a builtin, with the values of
+/// its arguments.
+pub fn call_function(
+    env: &mut Environment,
+    name: &str,
+    values: Vec<TypeVal>,
+) -> Result<TypeVal, String> {
+    let runtime = Rc::clone(&env.runtime);
+    for observer in runtime.borrow_mut().observers.iter_mut() {
+        observer.on_call(name, &values);
+    }
+    let function = match env.get_function_info(name) {
+        Ok(function) => function,
+        Err(err) => {
+            let native = runtime.borrow().plugins.get(name);
+            return match native {
+                Some((plugin, function)) => function(&values).map_err(|err| {
+                    format!(
+                        "Error in function ({}) of the {} plugin\n{}\n",
+                        name, plugin, err
+                    )
+                }),
+                None if !runtime.borrow().sandbox.allows(name) => {
+                    Err(format!("{} is not allowed in this sandbox", name))
                 }
-            }
-        }
-        BinaryOperator::Sub => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Int(x - y)),
-                    Ok(Float(y)) => Ok(Float(x as f64 - y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Float(x - y as f64)),
-                    Ok(Float(y)) => Ok(Float(x - y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Difference between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Err(err) => {
-                    Err(format! {"Error during arithmetic expression evaluation\n{}\n", err})
+                None if runtime.borrow().prelude && name == "exit" => exit(env, &values),
+                None if runtime.borrow().prelude && name == "panic" => panic(env, &values),
+                None if runtime.borrow().prelude && name == "call" => call_by_name(env, values),
+                None if runtime.borrow().prelude && name == "format" => format(env, &values),
+                None if runtime.borrow().prelude && name == "dump_scope" => {
+                    dump_scope(env, &values)
                 }
-            }
-        }
-        BinaryOperator::Mul => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Int(x * y)),
-                    Ok(Float(y)) => Ok(Float(x as f64 * y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Float(x * y as f64)),
-                    Ok(Float(y)) => Ok(Float(x * y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Product between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Err(err) => {
-                    Err(format! {"Error during arithmetic expression evaluation\n{}\n", err})
+                None if runtime.borrow().prelude && is_builtin(name) => {
+                    let mut runtime = runtime.borrow_mut();
+                    call_output_builtin(&mut runtime, name, &values)
+                        .or_else(|| call_random_builtin(&mut runtime, name, &values))
+                        .or_else(|| call_input_builtin(&mut runtime, name, &values))
+                        .or_else(|| call_channel_builtin(&mut runtime, name, &values))
+                        .or_else(|| call_process_builtin(&mut runtime, name, &values))
+                        .unwrap_or_else(|| call_builtin(name, values))
                 }
-            }
+                None => Err(format!("Error during function evaluation\n{}\n", err)),
+            };
         }
-        BinaryOperator::Div => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => {
-                        if x % y == 0 {
-                            Ok(Int(x / y))
-                        } else {
-                            Ok(Float((x as f64) / (y as f64)))
-                        }
-                    }
-                    Ok(Float(y)) => Ok(Float(x as f64 / y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Float(x / y as f64)),
-                    Ok(Float(y)) => Ok(Float(x / y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Division between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Err(err) => {
-                    Err(format! {"Error during arithmetic expression evaluation\n{}\n", err})
-                }
-            }
+    };
+    if function.arguments.len() != values.len() {
+        return Err(format!(
+            "Function ({}) takes {} arguments but {} are given",
+            name,
+            function.arguments.len(),
+            values.len()
+        ));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(result) = runtime.borrow_mut().call_native(name, &function, &values) {
+        return Ok(result);
+    }
+    env.push_function();
+    env.returning = false;
+    env.return_value = TypeVal::default();
+    let evaluated_function = evaluate_function(env, name, &function, values);
+    let returning = std::mem::take(&mut env.returning);
+    let result = std::mem::take(&mut env.return_value);
+    let loop_exit = env.loop_exit.take();
+    env.pop();
+    evaluated_function?;
+    // The caller is left too, up to the program
+    if env.exit_code.is_some() {
+        env.returning = true;
+        return Ok(result);
+    }
+    if let Some(exit) = loop_exit {
+        return Err(format!("`{}` is not in a loop of ({})", exit, name));
+    }
+
+    let fun_return_type = function.return_type;
+    match fun_return_type {
+        Some(return_type) if !returning => Err(format!(
+            "Function ({}) ended without returning a {}",
+            name, return_type
+        )),
+        Some(return_type) => {
+            let returned_type = result.get_type();
+            apply_type_annotation(name, result, &fun_return_type).map_err(|_| {
+                format!(
+                    "Function ({}) should return a {} but returned a {}",
+                    name, return_type, returned_type
+                )
+            })
         }
-        BinaryOperator::Mod => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Int(x % y)),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Int(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Float(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Float(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Modulo between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => Err(
-                        format! {"Error during binary arithmetic expression evaluation\n{}\n", err},
-                    ),
-                },
-                Err(err) => {
-                    Err(format! {"Error during arithmetic expression evaluation\n{}\n", err})
-                }
-            }
+        None => Ok(result),
+    }
+}
+
+/// Bind the arguments of a function in its frame and execute its body.
+fn evaluate_function(
+    env: &mut Environment,
+    name: &str,
+    function: &Arc<FunctionDef>,
+    values: Vec<TypeVal>,
+) -> Result<(), String> {
+    // The function can call itself
+    if let Err(err) = env.insert_function(name, Arc::clone(function)) {
+        return Err(format!("Error during function evaluation\n{}\n", err));
+    }
+
+    // Bind each argument with its value
+    for (f_args, value) in zip(&function.arguments, values) {
+        let value = match apply_type_annotation(&f_args.name, value, &f_args.type_annotation) {
+            Ok(x) => x,
+            Err(err) => return Err(format!("Error during function call\n{}\n", err)),
+        };
+        if let Err(err) = env.bind(&f_args.name, value) {
+            return Err(format!("Error during function call\n{}\n", err));
         }
-        _ => error_reporting_generic("Unrecognized binary arithmetic operation".to_string()),
+        env.annotate(&f_args.name, &f_args.type_annotation);
     }
+
+    // Evaluate function body
+    let runtime = Rc::clone(&env.runtime);
+    runtime.borrow_mut().enter_function(name)?;
+    let evaluated_function = evaluate_ast(&function.body, env);
+    runtime.borrow_mut().exit_function();
+    evaluated_function
 }
 
-/// Evaluate binary logic expressions.
-pub fn bin_op_logic_evaluator(
-    scope: &&mut Rc<RefCell<Scope>>,
-    lhs: &Box<Expression>,
+/// Evaluator of binary operations
+fn bin_op_evaluator(
+    left: Result<TypeVal, String>,
     operator: &BinaryOperator,
-    rhs: &Box<Expression>,
+    right: Result<TypeVal, String>,
 ) -> Result<TypeVal, String> {
-    match operator {
-        BinaryOperator::And => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Int(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Int(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Float(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Float(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => Ok(Boolean(x && y)),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical AND between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::Or => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Int(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Int(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Float(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Float(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => Ok(Boolean(x || y)),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical OR between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::Less => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x < y)),
-                    Ok(Float(y)) => Ok(Boolean(x < y as i64)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x < y as f64)),
-                    Ok(Float(y)) => Ok(Boolean(x < y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LESS between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::Greater => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x > y)),
-                    Ok(Float(y)) => Ok(Boolean(x > y as i64)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x > y as f64)),
-                    Ok(Float(y)) => Ok(Boolean(x > y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GREATER between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::LessEq => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x <= y)),
-                    Ok(Float(y)) => Ok(Boolean(x <= y as i64)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x <= y as f64)),
-                    Ok(Float(y)) => Ok(Boolean(x <= y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical LEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::GreaterEq => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x >= y)),
-                    Ok(Float(y)) => Ok(Boolean(x >= y as i64)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x >= y as f64)),
-                    Ok(Float(y)) => Ok(Boolean(x >= y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical GEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::CompareEq => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x == y)),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => Ok(Boolean(x == y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => Ok(Boolean(x == y)),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical EQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => Ok(Boolean(x == y)),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        BinaryOperator::CompareNeq => {
-            let left = evaluate_expression(scope, &lhs);
-            let right = evaluate_expression(scope, &rhs);
-            match left {
-                Ok(Int(x)) => match right {
-                    Ok(Int(y)) => Ok(Boolean(x != y)),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Int(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Float(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => Ok(Boolean(x != y)),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Float(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Boolean(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => Ok(Boolean(x != y)),
-                    Ok(Str(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Boolean(x),
-                        &Str(y),
-                    ),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Ok(Str(x)) => match right {
-                    Ok(Int(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Int(y),
-                    ),
-                    Ok(Float(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Float(y),
-                    ),
-                    Ok(Boolean(y)) => error_reporting_binary_operator(
-                        "Logical NEQ between incompatible types".to_string(),
-                        &Str(x),
-                        &Boolean(y),
-                    ),
-                    Ok(Str(y)) => Ok(Boolean(x != y)),
-                    Err(err) => {
-                        Err(format! {"Error during logic expression evaluation\n{}\n", err})
-                    }
-                },
-                Err(err) => Err(format! {"Error during logic expression evaluation\n{}\n", err}),
-            }
-        }
-        _ => error_reporting_generic("Unrecognized binary logic operation".to_string()),
-    }
+    let kind = match operator {
+        BinaryOperator::Add
+        | BinaryOperator::Sub
+        | BinaryOperator::Mul
+        | BinaryOperator::Div
+        | BinaryOperator::Mod => "arithmetic",
+        _ => "logic",
+    };
+    left.and_then(|left| left.binary(operator, &right?))
+        .map_err(|err| format! {"Error during binary {} expression evaluation\n{}\n", kind, err})
 }
@@ -0,0 +1,153 @@
+use crate::interpreter::interpreter::TypeVal;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::mem::transmute;
+
+/// Arguments a C function can take.
+pub const MAX_ARGUMENTS: usize = 4;
+
+const RTLD_NOW: c_int = 2;
+
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlerror() -> *const c_char;
+}
+
+/// Last error of the dynamic loader.
+fn loader_error() -> String {
+    // SAFETY: dlerror returns null or a string valid until the next call to the loader
+    unsafe {
+        let message = dlerror();
+        match message.is_null() {
+            true => "unknown error".to_string(),
+            false => CStr::from_ptr(message).to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// Address of a symbol of a shared library, the library stays loaded for the rest of the process.
+fn lookup(library: &str, symbol: &str) -> Result<*mut c_void, String> {
+    let library = CString::new(library).map_err(|err| err.to_string())?;
+    let symbol = CString::new(symbol).map_err(|err| err.to_string())?;
+    // SAFETY: both strings are null terminated, loading a library runs its initializers
+    unsafe {
+        let handle = dlopen(library.as_ptr(), RTLD_NOW);
+        if handle.is_null() {
+            return Err(loader_error());
+        }
+        let address = dlsym(handle, symbol.as_ptr());
+        match address.is_null() {
+            true => Err(loader_error()),
+            false => Ok(address),
+        }
+    }
+}
+
+/// Call a C function whose arguments and result are all C long.
+///
+/// # Safety
+/// function must be a C function with this signature.
+unsafe fn call_long(function: *mut c_void, arguments: &[i64]) -> i64 {
+    match *arguments {
+        [] => transmute::<*mut c_void, extern "C" fn() -> i64>(function)(),
+        [a] => transmute::<*mut c_void, extern "C" fn(i64) -> i64>(function)(a),
+        [a, b] => transmute::<*mut c_void, extern "C" fn(i64, i64) -> i64>(function)(a, b),
+        [a, b, c] => {
+            transmute::<*mut c_void, extern "C" fn(i64, i64, i64) -> i64>(function)(a, b, c)
+        }
+        [a, b, c, d, ..] => {
+            transmute::<*mut c_void, extern "C" fn(i64, i64, i64, i64) -> i64>(function)(a, b, c, d)
+        }
+    }
+}
+
+/// Call a C function whose arguments and result are all C double.
+///
+/// # Safety
+/// function must be a C function with this signature.
+unsafe fn call_double(function: *mut c_void, arguments: &[f64]) -> f64 {
+    match *arguments {
+        [] => transmute::<*mut c_void, extern "C" fn() -> f64>(function)(),
+        [a] => transmute::<*mut c_void, extern "C" fn(f64) -> f64>(function)(a),
+        [a, b] => transmute::<*mut c_void, extern "C" fn(f64, f64) -> f64>(function)(a, b),
+        [a, b, c] => {
+            transmute::<*mut c_void, extern "C" fn(f64, f64, f64) -> f64>(function)(a, b, c)
+        }
+        [a, b, c, d, ..] => {
+            transmute::<*mut c_void, extern "C" fn(f64, f64, f64, f64) -> f64>(function)(a, b, c, d)
+        }
+    }
+}
+
+/// Call the symbol of a shared library with the arguments after the library and the symbol.
+///
+/// The arguments are all ints, passed as C long, or all floats, passed as C double, and the
+/// function returns the same type. Nothing checks that the C function has this signature.
+pub fn ffi_call(arguments: &[TypeVal]) -> Result<TypeVal, String> {
+    let [library @ TypeVal::Str(_), symbol @ TypeVal::Str(_), arguments @ ..] = arguments else {
+        return Err(
+            "ffi_call expects a library, a symbol and the arguments of the function".to_string(),
+        );
+    };
+    if arguments.len() > MAX_ARGUMENTS {
+        return Err(format!(
+            "ffi_call passes at most {} arguments",
+            MAX_ARGUMENTS
+        ));
+    }
+    let function = lookup(&library.to_string(), &symbol.to_string())
+        .map_err(|err| format!("Cannot load {} from {}: {}", symbol, library, err))?;
+    let longs: Option<Vec<i64>> = arguments
+        .iter()
+        .map(|argument| match argument {
+            TypeVal::Int(x) => Some(*x),
+            _ => None,
+        })
+        .collect();
+    let doubles: Option<Vec<f64>> = arguments
+        .iter()
+        .map(|argument| match argument {
+            TypeVal::Float(x) => Some(*x),
+            _ => None,
+        })
+        .collect();
+    // SAFETY: the user states the signature of the function with the types of the arguments
+    match (longs, doubles) {
+        (Some(longs), _) => Ok(TypeVal::Int(unsafe { call_long(function, &longs) })),
+        (_, Some(doubles)) => Ok(TypeVal::Float(unsafe { call_double(function, &doubles) })),
+        _ => Err("The arguments of ffi_call must be all ints or all floats".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn calls_c_functions() {
+        let libm = TypeVal::Str("\"libm.so.6\"".into());
+        let libc = TypeVal::Str("\"libc.so.6\"".into());
+        assert_eq!(
+            ffi_call(&[
+                libm.clone(),
+                TypeVal::Str("\"pow\"".into()),
+                TypeVal::Float(2.0),
+                TypeVal::Float(10.0)
+            ]),
+            Ok(TypeVal::Float(1024.0))
+        );
+        assert_eq!(
+            ffi_call(&[libc, TypeVal::Str("\"labs\"".into()), TypeVal::Int(-7)]),
+            Ok(TypeVal::Int(7))
+        );
+        assert!(ffi_call(&[
+            libm.clone(),
+            TypeVal::Str("\"pow\"".into()),
+            TypeVal::Float(2.0),
+            TypeVal::Int(1)
+        ])
+        .is_err());
+        assert!(ffi_call(&[libm, TypeVal::Str("\"no_such_symbol\"".into())]).is_err());
+    }
+}
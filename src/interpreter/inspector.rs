@@ -0,0 +1,166 @@
+use crate::interpreter::environment::Environment;
+use crate::interpreter::error_reporting::did_you_mean;
+use crate::interpreter::interpreter::TypeVal;
+use crate::parsing::ast::Statement;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+
+const HELP: &str = "Commands:\n  \
+    v, vars           show the variables seen by the failed statement\n  \
+    s, scopes         show the variables block by block, from the innermost\n  \
+    p, print NAME     show the value of a variable\n  \
+    q, quit           end the inspection";
+
+/// Statement that raised a runtime error and the blocks it could see.
+///
+/// line: line of the statement, unknown when the source code is not available.
+///
+/// scopes: variables of each visible block, from the innermost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub line: Option<usize>,
+    pub source_line: String,
+    pub scopes: Vec<BTreeMap<String, TypeVal>>,
+}
+
+/// Post-mortem inspector, keeping the scope of the statement that raised the runtime error.
+#[derive(Debug, Default)]
+pub struct Inspector {
+    pub failure: Option<Failure>,
+}
+
+impl Inspector {
+    /// Record the scope of a failed statement, the enclosing statements failing with it are ignored.
+    pub fn statement_failed(&mut self, stmt: &Statement, src: &str, env: &Environment) {
+        if self.failure.is_some() {
+            return;
+        }
+        let line = (!src.is_empty()).then(|| stmt.span.line_and_column(src).0);
+        let source_line = line
+            .and_then(|line| src.lines().nth(line - 1))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        self.failure = Some(Failure {
+            line,
+            source_line,
+            scopes: env.visible_scopes(),
+        });
+    }
+
+    /// Read commands until the user quits or the input is over, the values cannot be changed.
+    pub fn inspect(&self) {
+        let Some(failure) = &self.failure else {
+            return;
+        };
+        let header = match failure.line {
+            Some(line) => format!(
+                "Inspecting the failure at line {}: {}",
+                line, failure.source_line
+            ),
+            None => "Inspecting the failure".to_string(),
+        };
+        eprintln!("{}", header.cyan());
+        loop {
+            eprint!("(inspect) ");
+            io::stderr().flush().unwrap();
+            let mut command = String::new();
+            if io::stdin().read_line(&mut command).unwrap_or(0) == 0 {
+                return;
+            }
+            match failure.run_command(command.trim()) {
+                Some(text) => eprintln!("{}", text),
+                None => return,
+            }
+        }
+    }
+}
+
+impl Failure {
+    /// Answer a command, None ends the inspection.
+    fn run_command(&self, command: &str) -> Option<String> {
+        let show =
+            |name: &str, value: &TypeVal| format!("{} = {} ({})", name, value, value.get_type());
+        match command.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["q" | "quit"] => None,
+            ["v" | "vars"] => {
+                // The innermost blocks hide nothing, overshadowing is not allowed
+                let variables: BTreeMap<&String, &TypeVal> =
+                    self.scopes.iter().flat_map(|scope| scope.iter()).collect();
+                Some(
+                    variables
+                        .into_iter()
+                        .map(|(name, value)| show(name, value))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                )
+            }
+            ["s" | "scopes"] => Some(
+                self.scopes
+                    .iter()
+                    .enumerate()
+                    .map(|(depth, scope)| {
+                        let mut lines = vec![format!("block {}", depth)];
+                        lines.extend(
+                            scope
+                                .iter()
+                                .map(|(name, value)| format!("  {}", show(name, value))),
+                        );
+                        lines.join("\n")
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            ["p" | "print", name] => Some(
+                match self.scopes.iter().find_map(|scope| scope.get(*name)) {
+                    Some(value) => show(name, value),
+                    None => format!(
+                        "Variable `{}` does not exist{}",
+                        name,
+                        did_you_mean(name, self.scopes.iter().flat_map(|scope| scope.keys()))
+                    ),
+                },
+            ),
+            _ => Some(HELP.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::language_runner::run_language::run_source;
+
+    #[test]
+    fn keeps_the_scope_of_the_failure() {
+        let src = "let total = 0;\nfn f (n: int) -> int {\n    let half = n / 2;\n    \
+                   return half + true;\n}\nlet x = f(7);";
+        let mut interpreter = Interpreter::builder()
+            .source(src)
+            .inspector(Inspector::default())
+            .build();
+        assert!(run_source(src, &mut interpreter).is_err());
+        let runtime = interpreter.runtime();
+        let failure = runtime
+            .inspector
+            .as_ref()
+            .unwrap()
+            .failure
+            .as_ref()
+            .unwrap();
+        assert_eq!(failure.line, Some(4));
+        assert_eq!(failure.source_line, "return half + true;");
+        assert_eq!(
+            failure.run_command("vars"),
+            Some("half = 3.5 (float)\nn = 7 (int)".to_string())
+        );
+        assert_eq!(
+            failure.run_command("p totl"),
+            Some("Variable `totl` does not exist".to_string())
+        );
+        assert_eq!(failure.run_command("quit"), None);
+    }
+}
@@ -1,18 +1,43 @@
-use crate::interpreter::expression_evaluator::evaluate_expression;
-use crate::interpreter::interpreter::TypeVal::{Boolean, Float, Int, Str};
-use crate::parsing::ast::Statement::{
-    AssignmentStatement, FunctionCallStatement, FunctionDeclaration, IfElseStatement, IfStatement,
-    InputStatement, PrintLineStatement, PrintStatement, ReturnStatement,
+use crate::analysis::slots::resolve_slots;
+use crate::interpreter::coercion::{coerce, condition};
+use crate::interpreter::concurrency::{spawn, Channel};
+use crate::interpreter::coverage::Coverage;
+use crate::interpreter::debugger::Debugger;
+use crate::interpreter::decimal::Decimal;
+use crate::interpreter::environment::{Environment, LoopExit};
+use crate::interpreter::expression_evaluator::{call_function, evaluate_expression};
+use crate::interpreter::inspector::Inspector;
+use crate::interpreter::interpreter::TypeVal::{
+    Boolean, Channel as Chan, Char, Decimal as Dec, Float, Int, Str,
+};
+use crate::interpreter::io::{Input, Output};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::interpreter::jit::Jit;
+use crate::interpreter::observer::Observer;
+use crate::interpreter::plugins::GrimPlugin;
+use crate::interpreter::profiler::Profiler;
+use crate::interpreter::runtime::{Runtime, Sandbox};
+use crate::interpreter::set::Set;
+use crate::interpreter::tracer::Tracer;
+use crate::parsing::ast::StatementKind::{
+    AssignmentStatement, BreakStatement, BreakpointStatement, ContinueStatement,
+    DestructuringStatement, ForStatement, FunctionCallStatement, FunctionDeclaration,
+    IfElseStatement, IfStatement, IncludeStatement, InputStatement, MeasureStatement,
+    PrintLineStatement, PrintStatement, ReturnStatement, SpawnStatement,
     VariableDeclarationStatement, WhileStatement,
 };
-use crate::parsing::ast::{Expression, Statement};
+use crate::parsing::ast::{Expression, Parameter, Statement, Type};
+use crate::parsing::include::not_included;
+use crate::parsing::lexer::{quote_char, quote_string};
 use colored::Colorize;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 use std::cmp::PartialEq;
-use std::collections::{HashMap, HashSet};
-use std::io::Write;
+use std::fmt;
+use std::iter::zip;
 use std::rc::Rc;
-use std::{fmt, io};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
 
 /// Typeval contains the primitive types available in Grim.
 #[derive(Debug, Clone, PartialEq)]
@@ -20,7 +45,58 @@ pub enum TypeVal {
     Int(i64),
     Float(f64),
     Boolean(bool),
-    Str(String),
+    Str(Arc<str>),
+    Channel(Channel),
+    Decimal(Decimal),
+    Char(char),
+    Set(Set),
+}
+
+impl TypeVal {
+    /// Get the type of the value.
+    pub fn get_type(&self) -> Type {
+        match self {
+            Int(_) => Type::Int,
+            Float(_) => Type::Float,
+            Boolean(_) => Type::Bool,
+            Str(_) => Type::Str,
+            Chan(_) => Type::Channel,
+            Dec(_) => Type::Decimal,
+            Char(_) => Type::Char,
+            TypeVal::Set(_) => Type::Set,
+        }
+    }
+
+    /// Type and value for the error messages, such as int 3 or str "a", Display only writes the
+    /// value as printed by the program.
+    pub fn describe(&self) -> String {
+        match self {
+            Str(_) => format!("str {}", quote_string(&self.to_string())),
+            Char(x) => format!("char {}", quote_char(*x)),
+            Chan(_) => "channel".to_string(),
+            value => format!("{} {}", value.get_type(), value),
+        }
+    }
+}
+
+/// Check a value against an optional type annotation.
+///
+/// A value is converted with the implicit conversions, every other mismatch is an error.
+pub fn apply_type_annotation(
+    variable_name: &str,
+    value: TypeVal,
+    annotation: &Option<Type>,
+) -> Result<TypeVal, String> {
+    let Some(expected) = annotation else {
+        return Ok(value);
+    };
+    let given = value.get_type();
+    coerce(value, *expected).ok_or_else(|| {
+        format!(
+            "({}) is declared as {} but a {} is given",
+            variable_name, expected, given
+        )
+    })
 }
 
 impl Default for TypeVal {
@@ -36,205 +112,346 @@ impl fmt::Display for TypeVal {
             Int(x) => write!(f, "{}", x),
             Str(x) => write!(f, "{}", x[1..x.len() - 1].to_string()),
             Boolean(x) => write!(f, "{}", x),
+            Chan(_) => write!(f, "channel"),
+            Dec(x) => write!(f, "{}", x),
+            Char(x) => write!(f, "{}", x),
+            TypeVal::Set(x) => write!(f, "{}", x),
         }
     }
 }
 
-/// A local scope is composed by two fields:
-///
-/// parent: It contains the reference (counted using Reference Counter) to an eventual father.
-///
-/// local_variables: it contains all the local variables bound with their value.
-///
-/// reachable_variables: it contains all the variables seen by the scope.
-#[derive(Debug, Default, Clone)]
-pub struct Scope {
-    pub parent: Option<Rc<RefCell<Scope>>>,
-    pub local_variables: HashMap<String, TypeVal>,
-    pub local_functions: HashMap<String, (Vec<String>, Vec<Statement>)>,
-    pub reachable_variables: HashSet<String>,
-    pub reachable_functions: HashSet<String>,
-    pub return_value: TypeVal,
-    pub returning: bool,
+impl From<i64> for TypeVal {
+    fn from(x: i64) -> Self {
+        Int(x)
+    }
 }
 
-impl Scope {
-    /// Insert value for the first time in the scope.
-    pub fn insert_value(&mut self, variable_name: &str, value: &TypeVal) -> Result<String, String> {
-        if let Some(&ref _value) = self.local_variables.get(variable_name) {
-            Err(format!(
-                "A variable with this name ({}) already exists and it is in scope",
-                variable_name
-            ))
-        } else {
-            match value {
-                Int(x) => {
-                    if self
-                        .reachable_variables
-                        .contains(&variable_name.to_string())
-                    {
-                        return Err(format!("You are overshadowing ({})", variable_name));
-                    }
-                    self.local_variables
-                        .insert(variable_name.to_string(), Int(x.clone()));
-                    self.reachable_variables.insert(variable_name.to_string());
-                }
-                Float(x) => {
-                    if self
-                        .reachable_variables
-                        .contains(&variable_name.to_string())
-                    {
-                        return Err(format!("You are overshadowing ({})", variable_name));
-                    }
-                    self.local_variables
-                        .insert(variable_name.to_string(), Float(x.clone()));
-                    self.reachable_variables.insert(variable_name.to_string());
-                }
-                Boolean(x) => {
-                    if self
-                        .reachable_variables
-                        .contains(&variable_name.to_string())
-                    {
-                        return Err(format!("You are overshadowing ({})", variable_name));
-                    }
-                    self.local_variables
-                        .insert(variable_name.to_string(), Boolean(x.clone()));
-                    self.reachable_variables.insert(variable_name.to_string());
-                }
-                Str(x) => {
-                    if self
-                        .reachable_variables
-                        .contains(&variable_name.to_string())
-                    {
-                        return Err(format!("You are overshadowing ({})", variable_name));
-                    }
-                    self.local_variables
-                        .insert(variable_name.to_string(), Str(x.clone()));
-                    self.reachable_variables.insert(variable_name.to_string());
-                }
-            }
-            Ok("Correct insertion".to_string())
+impl From<f64> for TypeVal {
+    fn from(x: f64) -> Self {
+        Float(x)
+    }
+}
+
+impl From<bool> for TypeVal {
+    fn from(x: bool) -> Self {
+        Boolean(x)
+    }
+}
+
+impl From<char> for TypeVal {
+    fn from(x: char) -> Self {
+        Char(x)
+    }
+}
+
+/// Str value holding the text, stored between quotes as the literals.
+impl From<&str> for TypeVal {
+    fn from(text: &str) -> Self {
+        Str(format!("\"{}\"", text).into())
+    }
+}
+
+impl From<String> for TypeVal {
+    fn from(text: String) -> Self {
+        TypeVal::from(text.as_str())
+    }
+}
+
+/// Error of a value converted to a Rust type it does not hold.
+fn conversion_error(expected: Type, value: &TypeVal) -> String {
+    format!(
+        "Expected a value of type {}, {} ({}) is given",
+        expected,
+        value,
+        value.get_type()
+    )
+}
+
+impl TryFrom<TypeVal> for i64 {
+    type Error = String;
+
+    fn try_from(value: TypeVal) -> Result<Self, Self::Error> {
+        match value {
+            Int(x) => Ok(x),
+            value => Err(conversion_error(Type::Int, &value)),
         }
     }
+}
 
-    /// Insert function for the first time in the scope.
-    pub fn insert_function(
-        &mut self,
-        function_name: &str,
-        arguments: &Vec<String>,
-        body: &Vec<Statement>,
-    ) -> Result<String, String> {
-        if let Some(&ref _value) = self.local_functions.get(function_name) {
-            Err(format!(
-                "A function with this name ({}) already exists and it is in scope",
-                function_name
-            ))
-        } else {
-            self.local_functions
-                .insert(function_name.to_string(), (arguments.clone(), body.clone()));
-            self.reachable_functions.insert(function_name.to_string());
-            Ok("Correct insertion".to_string())
+/// An int is promoted, as when it is assigned to a float variable.
+impl TryFrom<TypeVal> for f64 {
+    type Error = String;
+
+    fn try_from(value: TypeVal) -> Result<Self, Self::Error> {
+        match coerce(value.clone(), Type::Float) {
+            Some(Float(x)) => Ok(x),
+            _ => Err(conversion_error(Type::Float, &value)),
         }
     }
+}
 
-    /// Get value of a variable.
-    ///
-    /// If the variable is found then it is returned, if not a mutable reference to the parent is borrowed and the search recursively goes up.
-    pub fn get_variable_value(&self, variable_name: &str) -> Result<TypeVal, String> {
-        if let Some(&ref value) = self.local_variables.get(variable_name) {
-            Ok(value.clone())
-        } else if let Some(parent) = self.parent.as_ref() {
-            parent.borrow_mut().get_variable_value(variable_name)
-        } else {
-            return Err(format!("Variable {} does not exist", variable_name));
+impl TryFrom<TypeVal> for bool {
+    type Error = String;
+
+    fn try_from(value: TypeVal) -> Result<Self, Self::Error> {
+        match value {
+            Boolean(x) => Ok(x),
+            value => Err(conversion_error(Type::Bool, &value)),
         }
     }
+}
 
-    /// Get argument list and body of a function.
-    ///
-    /// If the function is found then it is returned, if not a mutable reference to the parent is borrowed and the search recursively goes up.
-    pub fn get_function_info(
-        &self,
-        function_name: &str,
-    ) -> Result<(Vec<String>, Vec<Statement>), String> {
-        if let Some(&ref value) = self.local_functions.get(function_name) {
-            Ok(value.clone())
-        } else if let Some(parent) = self.parent.as_ref() {
-            parent.borrow_mut().get_function_info(function_name)
-        } else {
-            return Err(format! {"Function {} does not exist", function_name});
+impl TryFrom<TypeVal> for char {
+    type Error = String;
+
+    fn try_from(value: TypeVal) -> Result<Self, Self::Error> {
+        match value {
+            Char(x) => Ok(x),
+            value => Err(conversion_error(Type::Char, &value)),
         }
     }
+}
 
-    /// Update value of a variable in the scope.
-    ///
-    /// If the variable is found then it is updated, if not a mutable reference to the parent is borrowed and the search recursively goes up.
-    pub fn update_value(&mut self, variable_name: &str, value: &TypeVal) -> Result<String, String> {
-        if let Some(&ref _some) = self.local_variables.get(variable_name) {
-            match value {
-                Int(value) => {
-                    self.local_variables
-                        .insert(variable_name.to_string(), Int(value.clone()));
-                }
-                Float(value) => {
-                    self.local_variables
-                        .insert(variable_name.to_string(), Float(value.clone()));
-                }
-                Boolean(value) => {
-                    self.local_variables
-                        .insert(variable_name.to_string(), Boolean(value.clone()));
-                }
-                Str(value) => {
-                    self.local_variables
-                        .insert(variable_name.to_string(), Str(value.clone()));
-                }
-            }
-        } else if let Some(parent) = self.parent.as_mut() {
-            parent.borrow_mut().update_value(variable_name, &value)?;
-        } else {
-            return Err(format!("{} does not exist", variable_name));
+/// Text of a str, without its quotes.
+impl TryFrom<TypeVal> for String {
+    type Error = String;
+
+    fn try_from(value: TypeVal) -> Result<Self, Self::Error> {
+        match value {
+            Str(_) => Ok(value.to_string()),
+            value => Err(conversion_error(Type::Str, &value)),
         }
-        Ok("Correct assignment".to_string())
+    }
+}
+
+/// A declared function, shared by the frames that can call it.
+#[derive(Debug, PartialEq)]
+pub struct FunctionDef {
+    pub arguments: Vec<Parameter>,
+    pub return_type: Option<Type>,
+    pub body: Arc<Vec<Statement>>,
+}
+
+/// Interpreter of Grim programs, the declarations of every run are kept in its global frame.
+#[derive(Debug)]
+pub struct Interpreter {
+    pub(crate) env: Environment,
+    pub(crate) runtime: Rc<RefCell<Runtime>>,
+}
+
+/// Configuration of an interpreter, by default it uses the standard IO without limits.
+#[derive(Debug, Default)]
+pub struct InterpreterBuilder {
+    runtime: Runtime,
+}
+
+impl InterpreterBuilder {
+    /// Source code of the programs, used to report the lines to the debugging tools.
+    pub fn source(mut self, src: &str) -> Self {
+        self.runtime.set_source(src);
+        self
+    }
+
+    /// Maximum number of nested function calls.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.runtime.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Limit the number of values bound to variables.
+    pub fn max_values(mut self, max_values: usize) -> Self {
+        self.runtime.memory.max_values = Some(max_values);
+        self
+    }
+
+    /// Limit the approximate number of bytes of the values bound to variables.
+    pub fn max_memory(mut self, max_bytes: usize) -> Self {
+        self.runtime.memory.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Stop the program before its next statement once the flag is set.
+    pub fn cancel_token(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.runtime.cancelled = Some(cancelled);
+        self
     }
 
-    /// Set parent of the given scope.
-    pub fn set_parent(&mut self, parent: Rc<RefCell<Scope>>) {
-        self.parent = Some(parent);
+    pub fn output(mut self, output: impl Output + 'static) -> Self {
+        self.runtime.output = Box::new(output);
+        self
     }
 
-    /// Set variable reachable from self scope.
-    pub fn set_reachable_variables(&mut self, reachable_variables: HashSet<String>) {
-        self.reachable_variables = reachable_variables;
+    /// Destination of eprint and eprintl, the standard error by default.
+    pub fn error_output(mut self, output: impl Output + 'static) -> Self {
+        self.runtime.error_output = Box::new(output);
+        self
     }
 
-    /// Set functions reachable from self scope.
-    pub fn set_reachable_functions(&mut self, reachable_functions: HashSet<String>) {
-        self.reachable_functions = reachable_functions;
+    pub fn input(mut self, input: impl Input + 'static) -> Self {
+        self.runtime.input = Box::new(input);
+        self
     }
 
-    /// Set return value of current scope.
-    pub fn set_return_value(&mut self, return_value: &TypeVal) {
-        self.return_value = return_value.clone();
-        if let Some(parent) = self.parent.as_mut() {
-            parent.borrow_mut().set_return_value(&return_value);
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.runtime.sandbox = sandbox;
+        self
+    }
+
+    /// Make the builtin functions available.
+    pub fn prelude(mut self, prelude: bool) -> Self {
+        self.runtime.prelude = prelude;
+        self
+    }
+
+    /// Make the native functions of a plugin available, they hide the builtins with the same name.
+    pub fn plugin(mut self, plugin: impl GrimPlugin) -> Self {
+        self.runtime.plugins.register(&plugin);
+        self
+    }
+
+    pub fn debugger(mut self, debugger: Debugger) -> Self {
+        self.runtime.debugger = Some(debugger);
+        self
+    }
+
+    pub fn tracer(mut self, tracer: Tracer) -> Self {
+        self.runtime.tracer = Some(tracer);
+        self
+    }
+
+    pub fn profiler(mut self, profiler: Profiler) -> Self {
+        self.runtime.profiler = Some(profiler);
+        self
+    }
+
+    pub fn coverage(mut self, coverage: Coverage) -> Self {
+        self.runtime.coverage = Some(coverage);
+        self
+    }
+
+    pub fn inspector(mut self, inspector: Inspector) -> Self {
+        self.runtime.inspector = Some(inspector);
+        self
+    }
+
+    /// Call the hooks of an observer as the program runs, after the ones registered before.
+    pub fn observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.runtime.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Report the changes of the variables with this name to the observers, in every scope.
+    pub fn watch(mut self, name: &str) -> Self {
+        self.runtime.watches.insert(name.to_string());
+        self
+    }
+
+    /// Compile the functions called most often to native code.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn jit(mut self) -> Self {
+        self.runtime.jit = Some(Jit::default());
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        Interpreter::with_runtime(self.runtime)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::builder().build()
+    }
+}
+
+impl Interpreter {
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    pub(crate) fn with_runtime(runtime: Runtime) -> Interpreter {
+        let runtime = Rc::new(RefCell::new(runtime));
+        let env = Environment::new(Rc::clone(&runtime));
+        Interpreter { env, runtime }
+    }
+
+    /// Execute a program in the global frame, waiting for the functions it spawned.
+    ///
+    /// The identifiers of the tree are resolved in place to the slots holding their value.
+    pub fn run(&mut self, tree: &mut [Statement]) -> Result<(), String> {
+        // A return at the top level only ends the program it belongs to
+        self.env.returning = false;
+        self.env.exit_code = None;
+        self.env.panic = None;
+        let globals = self.env.local_names();
+        resolve_slots(tree, globals);
+        let result = evaluate_ast(tree, &mut self.env);
+        // The text printed without a newline is shown before the error or the next program
+        if let Err(err) = self.runtime.borrow_mut().output.flush() {
+            return Err(format!("Error while flushing the output {}", err));
+        }
+        // The calls of a panic replace the statements the error went through
+        result.map_err(|err| self.env.panic.clone().unwrap_or(err))?;
+        if let Some(exit) = self.env.loop_exit.take() {
+            return Err(format!("`{}` is not in a loop", exit).red().to_string());
         }
+        self.runtime.borrow_mut().join_spawned()
+    }
+
+    /// Evaluate an expression in the global frame.
+    pub fn eval_expr(&mut self, expr: &Expression) -> Result<TypeVal, String> {
+        evaluate_expression(&mut self.env, expr)
+    }
+
+    /// Call a function declared in the global frame, or a builtin, with the given arguments.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        arguments: Vec<TypeVal>,
+    ) -> Result<TypeVal, String> {
+        self.env.panic = None;
+        call_function(&mut self.env, name, arguments)
+            .map_err(|err| self.env.panic.clone().unwrap_or(err))
     }
 
-    /// Set returning property.
+    /// Call a function declared by the programs run so far, such as a callback of the host
+    /// called many times after loading its script once.
     ///
-    /// The property is set also for the father scope to ensure current.
-    pub fn set_returning(&mut self, returning: bool) {
-        self.returning = returning;
-        if let Some(parent) = self.parent.as_mut() {
-            parent.borrow_mut().set_returning(returning);
+    /// The number of arguments is checked, and an exit or a panic of a previous call does not
+    /// stop the next one.
+    pub fn call(&mut self, name: &str, arguments: &[TypeVal]) -> Result<TypeVal, String> {
+        if self.env.global_function(name).is_none() {
+            return Err(format!(
+                "Function ({}) is not declared by the program",
+                name
+            ));
         }
+        self.env.returning = false;
+        self.env.exit_code = None;
+        self.call_function(name, arguments.to_vec())
+    }
+
+    /// Value of a variable declared at the top level by the programs run so far.
+    pub fn variable(&self, name: &str) -> Result<TypeVal, String> {
+        self.env.get_variable_value(name)
+    }
+
+    /// Value of a variable declared at the top level, converted to a Rust type such as i64 or
+    /// String, for the hosts reading the results of a configuration script.
+    pub fn get<T: TryFrom<TypeVal, Error = String>>(&self, name: &str) -> Result<T, String> {
+        let value = self.variable(name)?;
+        T::try_from(value).map_err(|err| format!("{} for the variable `{}`", err, name))
     }
-}
 
-/// Start the interpreter.
-pub fn boot_interpreter(tree: &Vec<Statement>) -> Result<Rc<RefCell<Scope>>, String> {
-    let mut main_scope = Rc::new(RefCell::new(Scope::default()));
-    evaluate_ast(&tree, &mut main_scope)
+    /// Exit code given to the exit builtin by the last program, if it called it.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.env.exit_code
+    }
+
+    /// Runtime of the interpreter, to read what the tools collected during the execution.
+    pub fn runtime(&self) -> Ref<'_, Runtime> {
+        self.runtime.borrow()
+    }
 }
 
 impl PartialEq<TypeVal> for &TypeVal {
@@ -244,361 +461,936 @@ impl PartialEq<TypeVal> for &TypeVal {
 }
 
 /// AST evaluation.
-pub fn evaluate_ast(
-    tree: &Vec<Statement>,
-    scope: &mut Rc<RefCell<Scope>>,
-) -> Result<Rc<RefCell<Scope>>, String> {
+///
+/// The functions of the block are declared first, they can be called before their declaration.
+pub(crate) fn evaluate_ast(tree: &[Statement], env: &mut Environment) -> Result<(), String> {
     for stmt in tree {
-        if scope.borrow().returning {
-            return Ok(scope.to_owned());
-        }
-        match stmt {
-            VariableDeclarationStatement { name, value } => {
-                match evaluate_expression(&scope, value) {
-                    Ok(evaluated_expr) => {
-                        match scope.borrow_mut().insert_value(&name, &evaluated_expr) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                return Err(
-                                    format! {"Error during variable declaration\n{}\n", err},
-                                )
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        return Err(format! {"Error during variable declaration\n{}\n", err})
-                    }
-                }
+        if let FunctionDeclaration {
+            name,
+            arguments,
+            return_type,
+            body,
+            ..
+        } = &stmt.kind
+        {
+            let function = Arc::new(FunctionDef {
+                arguments: arguments.clone(),
+                return_type: *return_type,
+                body: Arc::clone(body),
+            });
+            if let Err(err) = env.insert_function(name, function) {
+                return Err(format! {"Error during function declaration\n{}\n", err});
             }
-            AssignmentStatement { name, value } => match evaluate_expression(&scope, value) {
+        }
+    }
+    for stmt in tree {
+        if env.returning || env.loop_exit.is_some() {
+            return Ok(());
+        }
+        let runtime = Rc::clone(&env.runtime);
+        runtime.borrow_mut().before_statement(stmt, env)?;
+        if let Err(err) = evaluate_statement(stmt, env) {
+            runtime.borrow_mut().statement_failed(stmt, env);
+            return Err(err);
+        }
+        runtime.borrow_mut().after_statement(env)?;
+    }
+    Ok(())
+}
+
+/// Execute a single statement in the current block.
+fn evaluate_statement(stmt: &Statement, env: &mut Environment) -> Result<(), String> {
+    let runtime = Rc::clone(&env.runtime);
+    match &stmt.kind {
+        VariableDeclarationStatement {
+            name,
+            type_annotation,
+            value,
+            mutable,
+        } => {
+            match evaluate_expression(env, value)
+                .and_then(|res| apply_type_annotation(name, res, type_annotation))
+            {
                 Ok(evaluated_expr) => {
-                    match scope.borrow_mut().update_value(&name, &evaluated_expr) {
+                    match env.insert_value(name, &evaluated_expr) {
                         Ok(_) => (),
                         Err(err) => {
-                            return Err(format! {"Error during variable assignment\n{}\n", err})
+                            return Err(format! {"Error during variable declaration\n{}\n", err})
                         }
                     }
+                    env.annotate(name, type_annotation);
+                    if !mutable {
+                        env.freeze(name);
+                    }
                 }
+                Err(err) => return Err(format! {"Error during variable declaration\n{}\n", err}),
+            }
+        }
+        AssignmentStatement { name, value } => match evaluate_expression(env, value) {
+            Ok(evaluated_expr) => match env.update_value(name, &evaluated_expr) {
+                Ok(_) => (),
                 Err(err) => return Err(format! {"Error during variable assignment\n{}\n", err}),
             },
-            IfStatement { cond, then_part } => {
-                let evaluated_expr = evaluate_expression(&scope, cond);
-                match evaluated_expr {
-                    Ok(Boolean(true)) => {
-                        // Create new local scope
-                        let mut new_scope = Rc::new(RefCell::new(Scope::default()));
-                        // Set parent for local scope
-                        new_scope.borrow_mut().set_parent(Rc::clone(&scope));
-                        // Update reachable variables
-                        new_scope
-                            .borrow_mut()
-                            .set_reachable_variables(scope.borrow().reachable_variables.clone());
-                        // Update reachable functions
-                        new_scope
-                            .borrow_mut()
-                            .set_reachable_functions(scope.borrow().reachable_functions.clone());
-
-                        // Execute then_part
-                        match evaluate_ast(then_part, &mut new_scope) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                return Err(format! {"Error during if-else evaluation\n{}\n", err})
-                            }
-                        }
-                    }
-                    Ok(Int(_)) => {
-                        return Err("Int cannot be used as if condition".red().to_string())
-                    }
-                    Ok(Float(_)) => {
-                        return Err("Float cannot be used as if condition".red().to_string())
-                    }
-                    Ok(Str(_)) => {
-                        return Err("Str cannot be used as if condition".red().to_string())
-                    }
-                    Err(err) => return Err(format! {"Error during if evaluation\n{}\n", err}),
-                    _ => {}
-                }
+            Err(err) => return Err(format! {"Error during variable assignment\n{}\n", err}),
+        },
+        DestructuringStatement {
+            names,
+            values,
+            declaration,
+            mutable,
+        } => {
+            if names.len() != values.len() {
+                return Err(format!(
+                    "{} names are bound to {} values",
+                    names.len(),
+                    values.len()
+                ));
             }
-            IfElseStatement {
-                cond,
-                then_part,
-                else_part,
-            } => {
-                let evaluated_expr = evaluate_expression(&scope, cond);
-                match evaluated_expr {
-                    Ok(Boolean(true)) => {
-                        // Create new local scope
-                        let mut new_scope = Rc::new(RefCell::new(Scope::default()));
-                        // Set parent for local scope
-                        new_scope.borrow_mut().set_parent(Rc::clone(&scope));
-                        // Update reachable variables
-                        new_scope
-                            .borrow_mut()
-                            .set_reachable_variables(scope.borrow().reachable_variables.clone());
-                        // Update reachable functions
-                        new_scope
-                            .borrow_mut()
-                            .set_reachable_functions(scope.borrow().reachable_functions.clone());
-
-                        // Execute then_part
-                        match evaluate_ast(then_part, &mut new_scope) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                return Err(format! {"Error during if-else evaluation\n{}\n", err})
-                            }
-                        }
-                    }
-                    Ok(Boolean(false)) => {
-                        // Create new local scope
-                        let mut new_scope = Rc::new(RefCell::new(Scope::default()));
-                        // Set parent for local scope
-                        new_scope.borrow_mut().set_parent(Rc::clone(&scope));
-                        // Update reachable variables
-                        new_scope
-                            .borrow_mut()
-                            .set_reachable_variables(scope.borrow().reachable_variables.clone());
-                        // Update reachable functions
-                        new_scope
-                            .borrow_mut()
-                            .set_reachable_functions(scope.borrow().reachable_functions.clone());
-
-                        // Execute else_part
-                        match evaluate_ast(else_part, &mut new_scope) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                return Err(format! {"Error during if-else evaluation\n{}\n", err})
-                            }
-                        }
-                    }
-                    Ok(Int(_)) => {
-                        return Err("Int cannot be used as if condition".red().to_string())
-                    }
-                    Ok(Float(_)) => {
-                        return Err("Float cannot be used as if condition".red().to_string())
-                    }
-                    Ok(Str(_)) => {
-                        return Err("Str cannot be used as if condition".red().to_string())
-                    }
-                    Err(err) => return Err(format! {"Error during if-else evaluation\n{}\n", err}),
+            // Every value is read before the names change, a, b = b, a swaps them
+            let evaluated = values
+                .iter()
+                .map(|value| evaluate_expression(env, value))
+                .collect::<Result<Vec<TypeVal>, String>>()
+                .map_err(|err| format! {"Error during destructuring\n{}\n", err})?;
+            for (name, value) in zip(names, evaluated) {
+                let bound = match declaration {
+                    true => env.insert_value(name, &value),
+                    false => env.update_value(name, &value),
+                };
+                if let Err(err) = bound {
+                    return Err(format! {"Error during destructuring\n{}\n", err});
+                }
+                if *declaration && !mutable {
+                    env.freeze(name);
                 }
             }
-            WhileStatement { cond, body } => {
-                // Create new local scope
-                let mut new_scope = Rc::new(RefCell::new(Scope::default()));
-                // Set parent for local scope
-                new_scope.borrow_mut().set_parent(Rc::clone(&scope));
-                // Update reachable variables
-                new_scope
-                    .borrow_mut()
-                    .set_reachable_variables(scope.borrow().reachable_variables.clone());
-                // Update reachable functions
-                new_scope
-                    .borrow_mut()
-                    .set_reachable_functions(scope.borrow().reachable_functions.clone());
-
-                loop {
-                    let evaluated_expr = evaluate_expression(&scope, cond);
-                    match evaluated_expr {
-                        Ok(Boolean(true)) => match evaluate_ast(body, &mut new_scope) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                return Err(format! {"Error during while evaluation\n{}\n", err})
-                            }
-                        },
-                        Ok(Boolean(false)) => {
-                            break;
-                        }
-                        Ok(Int(_)) => {
-                            return Err("Int cannot be used as if condition".red().to_string())
-                        }
-                        Ok(Float(_)) => {
-                            return Err("Float cannot be used as if condition".red().to_string())
-                        }
-                        Ok(Str(_)) => {
-                            return Err("Str cannot be used as if condition".red().to_string())
-                        }
-                        Err(err) => {
-                            return Err(format! {"Error during while evaluation\n{}\n", err})
-                        }
-                    }
+        }
+        IfStatement { cond, then_part } => {
+            let cond = evaluate_expression(env, cond)
+                .map_err(|err| format! {"Error during if evaluation\n{}\n", err})?;
+            if condition(&cond, "if").map_err(|err| err.red().to_string())? {
+                env.push_block();
+                let result = evaluate_ast(then_part, env);
+                env.pop();
+                if let Err(err) = result {
+                    return Err(format! {"Error during if-else evaluation\n{}\n", err});
                 }
             }
+        }
+        IfElseStatement {
+            cond,
+            then_part,
+            else_part,
+        } => {
+            let cond = evaluate_expression(env, cond)
+                .map_err(|err| format! {"Error during if-else evaluation\n{}\n", err})?;
+            let branch = match condition(&cond, "if").map_err(|err| err.red().to_string())? {
+                true => then_part,
+                false => else_part,
+            };
+            env.push_block();
+            let result = evaluate_ast(branch, env);
+            env.pop();
+            if let Err(err) = result {
+                return Err(format! {"Error during if-else evaluation\n{}\n", err});
+            }
+        }
+        WhileStatement { cond, body, label } => evaluate_while(cond, body, label, env)?,
+        ForStatement {
+            variable,
+            start,
+            end,
+            step,
+            body,
+            label,
+        } => {
+            let bounds = [Some(start), Some(end), step.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|bound| evaluate_expression(env, bound))
+                .collect::<Result<Vec<TypeVal>, String>>()
+                .map_err(|err| format! {"Error during for evaluation\n{}\n", err})?;
+            let (start, end, step) = match bounds[..] {
+                [Int(start), Int(end)] => (start, end, 1),
+                [Int(_), Int(_), Int(0)] => {
+                    return Err("The step of a for loop cannot be 0".red().to_string())
+                }
+                [Int(start), Int(end), Int(step)] => (start, end, step),
+                _ => {
+                    return Err("The bounds and the step of a for loop must be ints"
+                        .red()
+                        .to_string())
+                }
+            };
+            evaluate_for(variable, (start, end, step), body, label, env)?;
+        }
+
+        // Declared when the block started
+        FunctionDeclaration { .. } => (),
 
-            FunctionDeclaration {
-                name,
-                arguments,
-                body,
-            } => match scope.borrow_mut().insert_function(name, arguments, body) {
+        FunctionCallStatement { name, arguments } => {
+            let called_function = Box::from(Expression::FunctionCall {
+                name: name.clone(),
+                arguments: arguments.clone(),
+            });
+            match evaluate_expression(env, &called_function) {
                 Ok(_) => (),
-                Err(err) => return Err(format! {"Error during function declaration\n{}\n", err}),
-            },
+                Err(err) => return Err(format! {"Error during function call\n{}\n", err}),
+            }
+        }
 
-            FunctionCallStatement { name, arguments } => {
-                let called_function = Box::from(Expression::FunctionCall {
-                    name: name.clone(),
-                    arguments: arguments.clone(),
-                });
-                match evaluate_expression(&scope, &called_function) {
-                    Ok(_) => (),
-                    Err(err) => return Err(format! {"Error during function call\n{}\n", err}),
+        SpawnStatement { name, arguments } => {
+            let mut values = vec![];
+            for arg in arguments {
+                match evaluate_expression(env, arg) {
+                    Ok(value) => values.push(value),
+                    Err(err) => return Err(format! {"Error during spawn\n{}\n", err}),
                 }
             }
+            let function = match env.get_function_info(name) {
+                Ok(function) => function,
+                Err(err) => return Err(format! {"Error during spawn\n{}\n", err}),
+            };
+            let mut functions = env.global_functions();
+            functions.insert(name.clone(), function);
+            let handle = spawn(&runtime.borrow(), name, functions, values);
+            runtime.borrow_mut().spawned.push(handle);
+        }
 
-            ReturnStatement { value } => {
-                scope.borrow_mut().set_returning(true);
-                match evaluate_expression(&scope, value) {
-                    Ok(res) => scope.borrow_mut().set_return_value(&res),
-                    Err(err) => return Err(format! {"Error during return statement\n{}\n", err}),
-                };
-                break;
+        ReturnStatement { value } => match evaluate_expression(env, value) {
+            Ok(res) => {
+                env.return_value = res;
+                env.returning = true;
             }
+            Err(err) => return Err(format! {"Error during return statement\n{}\n", err}),
+        },
 
-            PrintStatement { content } => match evaluate_expression(&scope, content) {
-                Ok(x) => {
-                    print!("{}", x);
-                    io::stdout().flush().unwrap();
+        PrintStatement { content } => match evaluate_expression(env, content) {
+            Ok(x) => {
+                if let Err(err) = runtime.borrow_mut().output.print(&x.to_string()) {
+                    return Err(format! {"Error during print statement {}", err});
                 }
-                Err(x) => return Err(x),
-            },
+            }
+            Err(x) => return Err(x),
+        },
 
-            PrintLineStatement { content } => match evaluate_expression(&scope, content) {
-                Ok(x) => {
-                    println!("{}", x);
-                    io::stdout().flush().unwrap();
+        PrintLineStatement { content } => match evaluate_expression(env, content) {
+            Ok(x) => {
+                if let Err(err) = runtime.borrow_mut().output.print(&format!("{}\n", x)) {
+                    return Err(format! {"Error during print statement {}", err});
                 }
-                Err(x) => return Err(x),
-            },
+            }
+            Err(x) => return Err(x),
+        },
 
-            InputStatement { name } => {
-                let mut input = String::new();
-                let mut recognized = false;
-                match io::stdin().read_line(&mut input) {
-                    Ok(_) => (),
-                    Err(x) => return Err(format! {"Error during input statement {}", x}),
-                };
-                let mut parsed_input = Box::from(Expression::Int(0));
-                // Try to parse as i64
-                match input.trim().parse::<i64>() {
-                    Ok(x) => {
-                        parsed_input = Box::from(Expression::Int(x));
-                        match scope.borrow().local_variables.get(name) {
-                            Some(Int(_)) => recognized = true,
-                            Some(Float(_)) => {
-                                return Err(format!(
-                                    "Error of type incoherence, \"{name}\" is a float"
-                                ))
-                            }
-                            Some(Boolean(_)) => {
-                                return Err(format!(
-                                    "Error of type incoherence, \"{name}\" is a boolean"
-                                ))
-                            }
-                            Some(Str(_)) => {
-                                return Err(format!(
-                                    "Error of type incoherence, \"{name}\" is a string"
-                                ))
-                            }
-                            _ => return Err(format!("Input variable {name} does not exist")),
-                        };
-                    }
-                    Err(_) => (),
-                };
-                // Try to parse as f64
-                match input.trim().parse::<f64>() {
-                    Ok(x) => {
-                        if !recognized {
-                            parsed_input = Box::from(Expression::Float(x));
-                            match scope.borrow().local_variables.get(name) {
-                                Some(Float(_)) => recognized = true,
-                                Some(Int(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is an integer"
-                                    ))
-                                }
-                                Some(Boolean(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is a boolean"
-                                    ))
-                                }
-                                Some(Str(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is a string"
-                                    ))
-                                }
-                                _ => return Err(format!("Input variable {name} does not exist")),
-                            }
-                        }
-                    }
-                    Err(_) => (),
-                }
-                // Try to parse as boolean
-                match input.trim().parse::<bool>() {
-                    Ok(x) => {
-                        if !recognized {
-                            parsed_input = Box::from(Expression::Bool(x));
-                            match scope.borrow().local_variables.get(name) {
-                                Some(Boolean(_)) => recognized = true,
-                                Some(Int(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is an integer"
-                                    ))
-                                }
-                                Some(Float(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is a float"
-                                    ))
-                                }
-                                Some(Str(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is a string"
-                                    ))
-                                }
-                                _ => return Err(format!("Input variable {name} does not exist")),
-                            };
-                        }
-                    }
-                    Err(_) => (),
-                };
-                // Otherwise parse as string
-                match input.trim().parse::<String>() {
-                    Ok(x) => {
-                        if !recognized {
-                            parsed_input = Box::from(Expression::Str(x));
-                            match scope.borrow().local_variables.get(name) {
-                                Some(Str(_)) => recognized = true,
-                                Some(Int(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is an integer"
-                                    ))
-                                }
-                                Some(Float(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is a float"
-                                    ))
-                                }
-                                Some(Boolean(_)) => {
-                                    return Err(format!(
-                                        "Error of type incoherence, \"{name}\" is a boolean"
-                                    ))
-                                }
-                                _ => return Err(format!("Input variable {name} does not exist")),
-                            };
-                        }
-                    }
-                    Err(_) => return Err("Cannot parse given value".to_string()),
-                };
-                let evaluated_expr = match evaluate_expression(&scope, &parsed_input) {
-                    Ok(x) => x,
-                    Err(err) => return Err(format! {"Error during input statement {}", err}),
-                };
-                match scope.borrow_mut().update_value(&name, &evaluated_expr) {
-                    Ok(_) => (),
-                    Err(err) => {
-                        return Err(format! {"Error during variable assignment\n{}\n", err})
-                    }
+        InputStatement { name } => {
+            if !runtime.borrow().sandbox.allow_input {
+                return Err("Input is not allowed in this sandbox".to_string());
+            }
+            let mut input = String::new();
+            // A prompt printed without a newline is shown before waiting for the user
+            if let Err(err) = runtime.borrow_mut().output.flush() {
+                return Err(format! {"Error during input statement {}", err});
+            }
+            match runtime.borrow_mut().read_line(&mut input) {
+                // An empty read is the end of the input, not an empty line
+                Ok(0) => return Err(format!("End of the input, no line left to read `{name}`")),
+                Ok(_) => (),
+                Err(x) => return Err(format! {"Error during input statement {}", x}),
+            };
+            // The line is read as the most specific literal, then converted to the type of the
+            // variable
+            let current = match env.local_value(name) {
+                Some(current) => current.get_type(),
+                None => return Err(format!("Input variable {name} does not exist")),
+            };
+            let line = input.trim();
+            let value = if let Ok(x) = line.parse::<i64>() {
+                Int(x)
+            } else if let Ok(x) = line.parse::<f64>() {
+                Float(x)
+            } else if let Ok(x) = line.parse::<bool>() {
+                Boolean(x)
+            } else {
+                TypeVal::from(line)
+            };
+            let value = match coerce(value, current) {
+                Some(value) => value,
+                None => {
+                    return Err(format!(
+                        "Error of type incoherence, \"{name}\" is of type {current}"
+                    ))
                 }
+            };
+            if let Err(err) = env.update_value(name, &value) {
+                return Err(format! {"Error during variable assignment\n{}\n", err});
+            }
+        }
+
+        BreakStatement { label } => env.loop_exit = Some(LoopExit::Break(label.clone())),
+        ContinueStatement { label } => env.loop_exit = Some(LoopExit::Continue(label.clone())),
+
+        BreakpointStatement => (),
+        IncludeStatement { path } => return Err(not_included(path)),
+        MeasureStatement { label, body } => {
+            // The time is printed as well when the body leaves with a return or a break
+            let start = Instant::now();
+            env.push_block();
+            let result = evaluate_ast(body, env);
+            env.pop();
+            if let Err(err) = result {
+                return Err(format! {"Error during measure block\n{}\n", err});
+            }
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+            let mut runtime = runtime.borrow_mut();
+            let printed = runtime.output.flush().and_then(|_| {
+                runtime
+                    .error_output
+                    .print(&format!("{} took {:.3} ms\n", label, elapsed))
+            });
+            if let Err(err) = printed {
+                return Err(format! {"Error during measure block {}", err});
             }
         }
     }
-    Ok(scope.to_owned())
+    Ok(())
+}
+
+/// Whether the loop with the given label ends after its body ran, consuming its break or continue.
+///
+/// A break or a continue naming another loop is kept for the enclosing loops.
+fn leaves_loop(env: &mut Environment, label: &Option<String>) -> bool {
+    match env.loop_exit.take() {
+        Some(exit) if exit.label().is_some() && exit.label() != label.as_deref() => {
+            env.loop_exit = Some(exit);
+            true
+        }
+        Some(LoopExit::Break(_)) => true,
+        Some(LoopExit::Continue(_)) | None => env.returning,
+    }
+}
+
+/// Execute a for statement, each iteration runs in a new block holding the variable first.
+fn evaluate_for(
+    variable: &str,
+    (start, end, step): (i64, i64, i64),
+    body: &[Statement],
+    label: &Option<String>,
+    env: &mut Environment,
+) -> Result<(), String> {
+    let mut next = Some(start);
+    while let Some(i) = next.filter(|i| if step > 0 { *i < end } else { *i > end }) {
+        // A value assigned to the variable by the body does not change the next iteration
+        env.push_block();
+        let result = env
+            .insert_value(variable, &Int(i))
+            .and_then(|_| evaluate_ast(body, env));
+        env.pop();
+        if let Err(err) = result {
+            return Err(format! {"Error during for evaluation\n{}\n", err});
+        }
+        // A return, a break or an exit of an enclosing loop ends the loop
+        if leaves_loop(env, label) {
+            break;
+        }
+        next = i.checked_add(step);
+    }
+    Ok(())
+}
+
+/// Execute a while statement, each iteration runs its body in a new block.
+fn evaluate_while(
+    cond: &Expression,
+    body: &[Statement],
+    label: &Option<String>,
+    env: &mut Environment,
+) -> Result<(), String> {
+    loop {
+        let cond = evaluate_expression(env, cond)
+            .map_err(|err| format! {"Error during while evaluation\n{}\n", err})?;
+        if !condition(&cond, "while").map_err(|err| err.red().to_string())? {
+            break;
+        }
+        env.push_block();
+        let result = evaluate_ast(body, env);
+        env.pop();
+        if let Err(err) = result {
+            return Err(format! {"Error during while evaluation\n{}\n", err});
+        }
+        // A return, a break or an exit of an enclosing loop ends the loop
+        if leaves_loop(env, label) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::{ExpressionParser, ProgramParser};
+    use crate::parsing::lexer::Lexer;
+
+    fn parse(src: &str) -> Vec<Statement> {
+        ProgramParser::new().parse(Lexer::new(src)).unwrap()
+    }
+
+    #[test]
+    fn runs_evaluates_and_calls() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse("let mut a = 2; fn sq (x) -> { return x * x; }"))
+            .unwrap();
+        interpreter.run(&mut parse("a = a + 1;")).unwrap();
+
+        let expr = ExpressionParser::new().parse(Lexer::new("a * 10")).unwrap();
+        assert_eq!(interpreter.eval_expr(&expr), Ok(Int(30)));
+        assert_eq!(interpreter.call_function("sq", vec![Int(4)]), Ok(Int(16)));
+    }
+
+    #[test]
+    fn compares_strings_lexicographically() {
+        let mut interpreter = Interpreter::default();
+        let compare = |interpreter: &mut Interpreter, src: &str| {
+            let expr = ExpressionParser::new().parse(Lexer::new(src)).unwrap();
+            interpreter.eval_expr(&expr)
+        };
+        assert_eq!(
+            compare(&mut interpreter, "\"apple\" < \"banana\""),
+            Ok(Boolean(true))
+        );
+        assert_eq!(
+            compare(&mut interpreter, "\"a\" < \"a!\""),
+            Ok(Boolean(true))
+        );
+        assert_eq!(
+            compare(&mut interpreter, "\"Z\" >= \"a\""),
+            Ok(Boolean(false))
+        );
+        assert_eq!(
+            compare(&mut interpreter, "\"é\" > \"e\""),
+            Ok(Boolean(true))
+        );
+        assert!(compare(&mut interpreter, "\"1\" < 2").is_err());
+    }
+
+    #[test]
+    fn indexes_strings_by_character() {
+        let mut interpreter = Interpreter::default();
+        let mut eval = |src: &str| {
+            let expr = ExpressionParser::new().parse(Lexer::new(src)).unwrap();
+            interpreter.eval_expr(&expr)
+        };
+        assert_eq!(eval("\"héllo\"[1]"), Ok(Char('é')));
+        assert_eq!(eval("\"abc\"[2] == 'c'"), Ok(Boolean(true)));
+        assert_eq!(eval("'a' < 'b'"), Ok(Boolean(true)));
+        assert_eq!(eval("'Z' >= 'a'"), Ok(Boolean(false)));
+        assert!(eval("\"abc\"[3]")
+            .unwrap_err()
+            .contains("Index 3 is out of a string of 3 characters"));
+        assert!(eval("\"abc\"[-1]").is_err());
+        assert!(eval("'a' == \"a\"").is_err());
+        assert!(eval("'a' + 'b'").is_err());
+    }
+
+    #[test]
+    fn applies_configuration() {
+        let mut program = parse("fn down (n) -> { if n == 0 { return 0; } return down(n - 1); }");
+        let mut interpreter = Interpreter::builder().max_depth(10).build();
+        interpreter.run(&mut program).unwrap();
+        assert_eq!(interpreter.call_function("down", vec![Int(9)]), Ok(Int(0)));
+        assert!(interpreter
+            .call_function("down", vec![Int(10)])
+            .unwrap_err()
+            .contains("exceeds the maximum call depth of 10"));
+
+        let mut interpreter = Interpreter::builder()
+            .sandbox(Sandbox::strict())
+            .prelude(false)
+            .build();
+        assert!(interpreter
+            .run(&mut parse("let a = 0; input(a);"))
+            .unwrap_err()
+            .contains("Input is not allowed"));
+        assert!(interpreter
+            .call_function("assert", vec![Boolean(true)])
+            .is_err());
+
+        let mut interpreter = Interpreter::builder().sandbox(Sandbox::strict()).build();
+        assert!(interpreter
+            .call_function("exec", vec![Str("\"ls\"".into())])
+            .unwrap_err()
+            .contains("exec is not allowed in this sandbox"));
+    }
+
+    #[test]
+    fn limits_memory() {
+        let mut interpreter = Interpreter::builder()
+            .max_values(50)
+            .max_memory(4000)
+            .build();
+        interpreter
+            .run(&mut parse(
+                "fn f (n) -> { let m = n * 2; return m; } let a = f(1);",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.runtime().memory.values, 1);
+        assert!(interpreter
+            .run(&mut parse("fn g (n) -> { return g(n + 1); } g(0);"))
+            .unwrap_err()
+            .contains("exceeds the maximum of 50 live values"));
+        let long = "x".repeat(4000);
+        assert!(interpreter
+            .run(&mut parse(&format!("let s = \"{}\";", long)))
+            .unwrap_err()
+            .contains("exceeds the memory limit of 4000 bytes"));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut interpreter = Interpreter::builder()
+            .cancel_token(Arc::clone(&cancelled))
+            .build();
+        interpreter.run(&mut parse("let mut a = 1;")).unwrap();
+        cancelled.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert!(interpreter
+            .run(&mut parse("while true { a = a + 1; }"))
+            .unwrap_err()
+            .contains("Execution cancelled"));
+    }
+
+    #[test]
+    fn spawns_functions_communicating_by_channels() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "fn square (n, out) -> { send(out, n * n); return 0; } \
+                 let results = channel(); spawn square(3, results); spawn square(4, results); \
+                 let total = recv(results) + recv(results);",
+            ))
+            .unwrap();
+        let total = interpreter.env.get_variable_value("total");
+        assert_eq!(total, Ok(Int(25)));
+        assert!(interpreter
+            .run(&mut parse(
+                "fn fail () -> { assert(false); return 0; } spawn fail();"
+            ))
+            .unwrap_err()
+            .contains("Error in spawned function"));
+    }
+
+    #[test]
+    fn prints_spawned_functions_to_the_outputs_of_the_program() {
+        use crate::interpreter::io::{SharedBuffer, WriteOutput};
+
+        let output = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .build();
+        interpreter
+            .run(&mut parse(
+                "fn hello (out) -> { printl(\"hello\"); send(out, 1); return 0; } \
+                 let done = channel(); spawn hello(done); let sent = recv(done); printl(sent);",
+            ))
+            .unwrap();
+        assert_eq!(output.contents(), "hello\n1\n");
+        assert!(interpreter
+            .run(&mut parse(
+                "fn ask () -> { let mut a = 0; input(a); return a; } spawn ask();"
+            ))
+            .unwrap_err()
+            .contains("a spawned function cannot read the input"));
+    }
+
+    #[test]
+    fn fails_to_recv_when_every_thread_waits() {
+        let mut interpreter = Interpreter::default();
+        assert!(interpreter
+            .run(&mut parse("let c = channel(); let v = recv(c);"))
+            .unwrap_err()
+            .contains("every thread of the program is waiting"));
+        assert!(interpreter
+            .run(&mut parse(
+                "fn wait (c) -> { return recv(c); } let d = channel(); spawn wait(d);"
+            ))
+            .unwrap_err()
+            .contains("every thread of the program is waiting"));
+    }
+
+    #[test]
+    fn shares_function_definitions() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "fn f (n) -> { return n; } if true { let a = f(1); }",
+            ))
+            .unwrap();
+        let first = interpreter.env.get_function_info("f").unwrap();
+        let second = interpreter.env.get_function_info("f").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.arguments[0].name, "n");
+    }
+
+    #[test]
+    fn returns_from_loops_and_recovers_from_errors() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let a = 1; fn f (n) -> { let mut i = 0; while i < 5 { if i == n { return i; } \
+                 i = i + 1; } return 99; }",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.call_function("f", vec![Int(2)]), Ok(Int(2)));
+        assert!(interpreter
+            .run(&mut parse(
+                "if true { let mut b = 1; while true { b = c; } }"
+            ))
+            .is_err());
+        interpreter.run(&mut parse("let b = a + 1;")).unwrap();
+        assert_eq!(interpreter.env.get_variable_value("b"), Ok(Int(2)));
+    }
+
+    #[test]
+    fn evaluates_deeply_nested_expressions() {
+        let mut interpreter = Interpreter::default();
+        let sum = vec!["1"; 1000].join(" + ");
+        interpreter
+            .run(&mut parse(&format!(
+                "fn f (x, y) -> {{ return x - y; }} let a = {}; let b = -f({}, 1);",
+                sum, sum
+            )))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("a"), Ok(Int(1000)));
+        assert_eq!(interpreter.env.get_variable_value("b"), Ok(Int(-999)));
+    }
+
+    #[test]
+    fn destructures_values() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse("let mut (a, b) = (1, 2.5); a, b = b, a;"))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("a"), Ok(Float(2.5)));
+        assert_eq!(interpreter.env.get_variable_value("b"), Ok(Int(1)));
+        assert!(interpreter
+            .run(&mut parse("a, b = 1, 2, 3;"))
+            .unwrap_err()
+            .contains("2 names are bound to 3 values"));
+    }
+
+    #[test]
+    fn assigns_only_mutable_variables() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let a = 1; let mut b = a; b = b + 1; let mut (c, d) = (1, 2); c, d = d, c;",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("b"), Ok(Int(2)));
+        assert_eq!(interpreter.env.get_variable_value("c"), Ok(Int(2)));
+        assert!(interpreter
+            .run(&mut parse("a = 2;"))
+            .unwrap_err()
+            .contains("Cannot assign twice to the immutable variable (a)"));
+        assert!(interpreter
+            .run(&mut parse("let (e, f) = (1, 2); e, f = f, e;"))
+            .is_err());
+        assert_eq!(interpreter.env.get_variable_value("a"), Ok(Int(1)));
+    }
+
+    #[test]
+    fn counts_with_for_loops() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let mut up = 0; for i in 0..5 { up = up * 10 + i; i = 9; } \
+                 let mut down = 0; for i in 10..0 step -4 { down = down * 100 + i; }",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("up"), Ok(Int(1234)));
+        assert_eq!(interpreter.env.get_variable_value("down"), Ok(Int(100602)));
+        interpreter
+            .run(&mut parse(
+                "let mut sum = 0; for i = 1 to 4 { sum = sum + i; } for i = 1 to 0 { sum = 0; }",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("sum"), Ok(Int(10)));
+        assert!(interpreter
+            .run(&mut parse("for i in 0..1 step 0.5 { }"))
+            .unwrap_err()
+            .contains("must be ints"));
+    }
+
+    #[test]
+    fn reads_variables_of_nested_blocks() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let mut a = 1; let b = 2; \
+                 fn f (b, a) -> { if true { let c = a; if true { return b * 10 + c; } } return 0; } \
+                 let d = f(3, 4); if true { let e = b; if true { let g = a + e; a = g; } }",
+            ))
+            .unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(interpreter.env.get_variable_value("d"), Ok(Int(34)));
+        assert_eq!(interpreter.env.get_variable_value("a"), Ok(Int(3)));
+    }
+
+    #[test]
+    fn declares_variables_in_loop_bodies() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let mut sum = 0; for j in 0..3 { let y = j * 2; sum = sum + y; } \
+                 let mut i = 0; while i < 3 { let z = i; i = z + 1; sum = sum + z; }",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("sum"), Ok(Int(9)));
+    }
+
+    #[test]
+    fn shares_function_bodies_with_the_tree() {
+        let mut tree = parse("fn f () -> { return 1; }");
+        let mut interpreter = Interpreter::default();
+        interpreter.run(&mut tree).unwrap();
+        let FunctionDeclaration { body, .. } = &tree[0].kind else {
+            panic!("expected a function declaration");
+        };
+        let declared = interpreter.env.global_function("f").unwrap();
+        assert!(Arc::ptr_eq(body, &declared.body));
+    }
+
+    #[test]
+    fn describes_values_in_errors() {
+        assert_eq!(Int(3).describe(), "int 3");
+        assert_eq!(TypeVal::from("a\nb").describe(), "str \"a\\nb\"");
+        assert_eq!(Char('\'').describe(), "char '\\''");
+        let mut interpreter = Interpreter::default();
+        let err = interpreter
+            .run(&mut parse("let a = 1 + true;"))
+            .unwrap_err();
+        assert!(err.contains("int 1 and bool true"), "{}", err);
+    }
+
+    #[test]
+    fn converts_rust_values() {
+        assert_eq!(TypeVal::from(3), Int(3));
+        assert_eq!(TypeVal::from("hi"), Str("\"hi\"".into()));
+        assert_eq!(
+            String::try_from(TypeVal::from("hi".to_string())),
+            Ok("hi".to_string())
+        );
+        assert_eq!(f64::try_from(TypeVal::from(2)), Ok(2.0));
+        assert_eq!(bool::try_from(TypeVal::from(true)), Ok(true));
+        assert_eq!(char::try_from(TypeVal::from('c')), Ok('c'));
+        assert_eq!(
+            i64::try_from(TypeVal::from(1.5)),
+            Err("Expected a value of type int, 1.5 (float) is given".to_string())
+        );
+    }
+
+    #[test]
+    fn calls_a_callback_many_times() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "fn score (hits: int, misses: int) -> int { if hits < 0 { exit(1); } \
+                 return hits * 10 - misses; }",
+            ))
+            .unwrap();
+        let scores: Vec<TypeVal> = (0..3)
+            .map(|hits| interpreter.call("score", &[hits.into(), 1.into()]).unwrap())
+            .collect();
+        assert_eq!(scores, [Int(-1), Int(9), Int(19)]);
+        interpreter.call("score", &[(-1).into(), 0.into()]).unwrap();
+        assert_eq!(interpreter.exit_code(), Some(1));
+        assert_eq!(
+            interpreter.call("score", &[2.into(), 0.into()]),
+            Ok(Int(20))
+        );
+        assert_eq!(interpreter.exit_code(), None);
+        assert_eq!(
+            interpreter.call("score", &[2.into()]),
+            Err("Function (score) takes 2 arguments but 1 are given".to_string())
+        );
+        assert!(interpreter.call("abs", &[2.into()]).is_err());
+    }
+
+    #[test]
+    fn reads_the_variables_after_a_run() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let name = \"server\"; let port = 8000 + 80; let ratio = 0.5;",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.get::<String>("name"), Ok("server".to_string()));
+        assert_eq!(interpreter.get::<i64>("port"), Ok(8080));
+        assert_eq!(interpreter.variable("ratio"), Ok(Float(0.5)));
+        assert_eq!(
+            interpreter.get::<bool>("port"),
+            Err(
+                "Expected a value of type bool, 8080 (int) is given for the variable `port`"
+                    .to_string()
+            )
+        );
+        assert!(interpreter.get::<i64>("missing").is_err());
+    }
+
+    #[test]
+    fn increments_and_decrements() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let mut up = 0; let mut down = 0; while up < 3 { up++; down--; down--; }",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("up"), Ok(Int(3)));
+        assert_eq!(interpreter.env.get_variable_value("down"), Ok(Int(-6)));
+    }
+
+    #[test]
+    fn pipes_values_into_calls() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "fn double (n) -> { return n * 2; } fn add (a, b) -> { return a + b; } \
+                 let x = -3 |> abs |> double |> add(1); let y = 1 + 2 |> double;",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("x"), Ok(Int(7)));
+        assert_eq!(interpreter.env.get_variable_value("y"), Ok(Int(6)));
+    }
+
+    #[test]
+    fn traces_to_the_error_output() {
+        use crate::interpreter::io::{SharedBuffer, WriteOutput};
+
+        let output = SharedBuffer::default();
+        let errors = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .source("let a = 1;")
+            .tracer(Tracer::default())
+            .output(WriteOutput(output.clone()))
+            .error_output(WriteOutput(errors.clone()))
+            .build();
+        interpreter.run(&mut parse("let a = 1;")).unwrap();
+        assert_eq!(output.contents(), "");
+        let traced = errors.contents();
+        assert!(
+            traced.contains("line 1: let a = 1;") && traced.contains("    a = 1"),
+            "{}",
+            traced
+        );
+    }
+
+    #[test]
+    fn reports_watched_changes_to_the_error_output() {
+        use crate::interpreter::io::{SharedBuffer, WriteOutput};
+
+        let output = SharedBuffer::default();
+        let errors = SharedBuffer::default();
+        let mut debugger = Debugger::default();
+        debugger.watches.insert("a".to_string());
+        let mut interpreter = Interpreter::builder()
+            .source("let mut a = 1;\na = 2;")
+            .debugger(debugger)
+            .output(WriteOutput(output.clone()))
+            .error_output(WriteOutput(errors.clone()))
+            .build();
+        interpreter
+            .run(&mut parse("let mut a = 1;\na = 2;"))
+            .unwrap();
+        assert_eq!(output.contents(), "");
+        assert_eq!(
+            errors.contents(),
+            "`a` declared as 1 at line 1\n`a` changed from 1 to 2 at line 2\n"
+        );
+    }
+
+    #[test]
+    fn measures_a_block() {
+        use crate::interpreter::io::{SharedBuffer, WriteOutput};
+
+        let errors = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .error_output(WriteOutput(errors.clone()))
+            .build();
+        interpreter
+            .run(&mut parse(
+                "fn sum (n) -> { measure \"sum\" { let mut total = 0; \
+                 for i in 0..n { total = total + i; } return total; } } \
+                 let total = sum(10);",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("total"), Ok(Int(45)));
+        let printed = errors.contents();
+        assert!(
+            printed.starts_with("sum took ") && printed.ends_with(" ms\n"),
+            "{}",
+            printed
+        );
+        assert!(interpreter
+            .run(&mut parse("measure \"fail\" { let x = 1 + true; }"))
+            .is_err());
+        assert_eq!(errors.contents(), printed);
+    }
+
+    #[test]
+    fn calls_global_functions() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "fn is_even (n) -> { if n == 0 { return true; } return is_odd(n - 1); } \
+                 fn is_odd (n) -> { if n == 0 { return false; } return is_even(n - 1); } \
+                 let limit = 3; let odd = is_odd(7);",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("odd"), Ok(Boolean(true)));
+        // The global variables stay hidden
+        assert!(interpreter
+            .run(&mut parse("fn f () -> { return limit; } f();"))
+            .unwrap_err()
+            .contains("Variable `limit` does not exist"));
+    }
+
+    #[test]
+    fn calls_functions_before_their_declaration() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let mut x = twice(3); if true { x = x + inc(x); fn inc (n) -> { return n + 1; } } \
+                 fn twice (n) -> { return 2 * n; }",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.env.get_variable_value("x"), Ok(Int(13)));
+        // The functions of a nested block are not declared outside of it
+        assert!(interpreter
+            .run(&mut parse(
+                "if true { fn g () -> { return 1; } } let y = g();"
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn leaves_labeled_loops() {
+        let mut interpreter = Interpreter::default();
+        interpreter
+            .run(&mut parse(
+                "let mut seen = 0; let mut j = 0; \
+                 outer: for i in 1..5 { j = 0; while true { j = j + 1; \
+                 if j == 2 { continue; } if j > 3 { break; } \
+                 if i == 3 { continue outer; } if i == 4 { break outer; } \
+                 seen = seen * 100 + i * 10 + j; } }",
+            ))
+            .unwrap();
+        assert_eq!(
+            interpreter.env.get_variable_value("seen"),
+            Ok(Int(11132123))
+        );
+        assert!(interpreter
+            .run(&mut parse("fn f () -> { break; } f();"))
+            .unwrap_err()
+            .contains("`break` is not in a loop"));
+    }
 }
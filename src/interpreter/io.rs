@@ -0,0 +1,229 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+/// Destination of the text printed by the program.
+pub trait Output: fmt::Debug {
+    fn print(&mut self, text: &str) -> io::Result<()>;
+
+    /// Show the text printed so far, called before reading an input and when the program ends.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Source of the lines read by the program.
+pub trait Input: fmt::Debug {
+    /// Append the next line to buf and return the number of bytes read, 0 at the end of the input.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+}
+
+/// Output sent to a writer, like the standard output, a file or a buffer.
+///
+/// The writer is flushed only when the output is, the standard output still shows every line.
+#[derive(Debug)]
+pub struct WriteOutput<W: Write>(pub W);
+
+impl<W: Write + fmt::Debug> Output for WriteOutput<W> {
+    fn print(&mut self, text: &str) -> io::Result<()> {
+        self.0.write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// In memory buffer, its clones share the same content so the output can be read after the run.
+#[derive(Debug, Default, Clone)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Standard input of the process, shared with the debugger.
+#[derive(Debug, Default)]
+pub struct StdInput;
+
+impl Input for StdInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        io::stdin().read_line(buf)
+    }
+}
+
+/// Input read from a buffered reader, like a file or a string with the lines to give.
+#[derive(Debug)]
+pub struct ReadInput<R: BufRead>(pub R);
+
+impl<R: BufRead + fmt::Debug> Input for ReadInput<R> {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.0.read_line(buf)
+    }
+}
+
+/// Output given to a callback, for hosts without a standard output like browsers.
+pub struct CallbackOutput(pub Box<dyn FnMut(&str)>);
+
+impl Output for CallbackOutput {
+    fn print(&mut self, text: &str) -> io::Result<()> {
+        (self.0)(text);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CallbackOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CallbackOutput")
+    }
+}
+
+/// Input asked to a callback, None means that the input is over.
+pub struct CallbackInput(pub Box<dyn FnMut() -> Option<String>>);
+
+impl Input for CallbackInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        match (self.0)() {
+            Some(line) => {
+                buf.push_str(&line);
+                buf.push('\n');
+                Ok(line.len() + 1)
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl fmt::Debug for CallbackInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CallbackInput")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::language_runner::run_language::run_source;
+
+    #[test]
+    fn program_uses_callbacks() {
+        let printed = Rc::new(RefCell::new(String::new()));
+        let sink = Rc::clone(&printed);
+        let mut lines = vec!["4".to_string()].into_iter();
+        let mut interpreter = Interpreter::builder()
+            .output(CallbackOutput(Box::new(move |text| {
+                sink.borrow_mut().push_str(text)
+            })))
+            .input(CallbackInput(Box::new(move || lines.next())))
+            .build();
+        let result = run_source(
+            "let mut a = 0; input(a); print(\"a: \"); printl(a * 2);",
+            &mut interpreter,
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(*printed.borrow(), "a: 8\n");
+    }
+
+    #[test]
+    fn output_captured_in_buffer() {
+        let buffer = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(buffer.clone()))
+            .build();
+        let result = run_source(
+            "let mut i = 0; while i < 3 { print(i); i = i + 1; }",
+            &mut interpreter,
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.contents(), "012");
+    }
+
+    #[test]
+    fn input_read_from_reader() {
+        let buffer = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(buffer.clone()))
+            .input(ReadInput(io::Cursor::new("3\n1.5\ntrue\n")))
+            .build();
+        let src =
+            "let mut a = 0; let mut b = 0.0; let mut c = false; input(a); input(b); input(c); \
+                   printl(a + b); printl(c);";
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        assert_eq!(buffer.contents(), "4.5\ntrue\n");
+        assert!(run_source("let mut d = \"\"; input(d);", &mut interpreter)
+            .unwrap_err()
+            .contains("End of the input, no line left to read `d`"));
+    }
+
+    #[test]
+    fn reads_words_and_lines() {
+        let buffer = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(buffer.clone()))
+            .input(ReadInput(io::Cursor::new(
+                "3 4\n\n  5.5 word rest of line\nlast\n7 8\n",
+            )))
+            .build();
+        let src = "printl(read_int() + read_int()); printl(read_float()); printl(read_word()); \
+                   printl(read_line()); printl(read_line()); printl(read_int()); \
+                   let mut n = 0; input(n); printl(n);";
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        assert_eq!(
+            buffer.contents(),
+            "7\n5.5\nword\nrest of line\nlast\n7\n8\n"
+        );
+        assert!(run_source("read_word();", &mut interpreter)
+            .unwrap_err()
+            .contains("End of the input"));
+    }
+
+    #[test]
+    fn flushes_before_input_and_at_the_end() {
+        let buffer = SharedBuffer::default();
+        let shown = buffer.clone();
+        let prompts = Rc::new(RefCell::new(vec![]));
+        let seen = Rc::clone(&prompts);
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(io::BufWriter::new(buffer.clone())))
+            .input(CallbackInput(Box::new(move || {
+                seen.borrow_mut().push(shown.contents());
+                Some("2".to_string())
+            })))
+            .build();
+        let src = "let mut a = 0; print(\"a? \"); input(a); print(a * 2); flush(); print(\"!\");";
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        assert_eq!(*prompts.borrow(), vec!["a? ".to_string()]);
+        assert_eq!(buffer.contents(), "a? 4!");
+    }
+
+    #[test]
+    fn prints_diagnostics_to_the_error_output() {
+        let output = SharedBuffer::default();
+        let errors = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .error_output(WriteOutput(errors.clone()))
+            .build();
+        let src = "printl(1); eprint(\"warning: \"); eprintl(2.5); print(3);";
+        assert_eq!(run_source(src, &mut interpreter), Ok(()));
+        assert_eq!(output.contents(), "1\n3");
+        assert_eq!(errors.contents(), "warning: 2.5\n");
+        assert!(run_source("eprint(1, 2);", &mut interpreter).is_err());
+    }
+}
@@ -0,0 +1,666 @@
+use crate::interpreter::interpreter::{FunctionDef, TypeVal};
+use crate::parsing::ast::{
+    BinaryOperator, Expression, Statement, StatementKind, Type, UnaryOperator,
+};
+use cranelift::prelude::*;
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Module};
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::zip;
+use std::mem;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Weak};
+
+/// Calls after which a function is compiled to native code.
+pub const HOT_CALLS: usize = 100;
+
+/// Nested calls allowed to native code when the program has no depth limit.
+pub const MAX_NATIVE_DEPTH: i64 = 10_000;
+
+/// Types handled by native code, both held in an i64.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Bool,
+}
+
+/// Entry point of a compiled function.
+///
+/// It takes the arguments, the nested calls left, the cancellation flag and where to write the
+/// result, and returns 0 on success.
+type Entry = unsafe extern "C" fn(*const i64, i64, *const AtomicBool, *mut i64) -> i64;
+
+struct NativeFunction {
+    entry: Entry,
+    parameters: Vec<Kind>,
+    result: Kind,
+}
+
+enum Compilation {
+    Counting(usize),
+    Native(NativeFunction),
+    Interpreted,
+}
+
+/// State of a function, the weak reference keeps its address from being reused.
+struct HotFunction {
+    function: Weak<FunctionDef>,
+    compilation: Compilation,
+}
+
+/// Compiler of the functions called most often to native code.
+///
+/// Only functions taking annotated ints and bools and without side effects are compiled. Their
+/// native code stops on anything the interpreter would report, such as an overflow or the maximum
+/// call depth, and the interpreter runs the call again to report it.
+#[derive(Default)]
+pub struct Jit {
+    module: Option<JITModule>,
+    functions: HashMap<usize, HotFunction>,
+    live: usize,
+    compiled: usize,
+}
+
+impl fmt::Debug for Jit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Jit")
+            .field("functions", &self.functions.len())
+            .field("compiled", &self.compiled)
+            .finish()
+    }
+}
+
+impl Jit {
+    /// Count a call to a function and run it natively once it is hot.
+    ///
+    /// depth: nested calls left before the maximum call depth.
+    ///
+    /// Returns None when the interpreter must run the call.
+    pub fn call(
+        &mut self,
+        name: &str,
+        function: &Arc<FunctionDef>,
+        arguments: &[TypeVal],
+        depth: i64,
+        cancelled: Option<&AtomicBool>,
+    ) -> Option<TypeVal> {
+        // Forget the functions whose declaration ended
+        if self.functions.len() > 2 * self.live.max(64) {
+            self.functions
+                .retain(|_, hot| hot.function.strong_count() > 0);
+            self.live = self.functions.len();
+        }
+        let hot = self
+            .functions
+            .entry(Arc::as_ptr(function) as usize)
+            .or_insert_with(|| HotFunction {
+                function: Arc::downgrade(function),
+                compilation: Compilation::Counting(0),
+            });
+        if let Compilation::Counting(calls) = &mut hot.compilation {
+            *calls += 1;
+            if *calls < HOT_CALLS {
+                return None;
+            }
+            hot.compilation = match compile(&mut self.module, name, function) {
+                Ok(native) => {
+                    self.compiled += 1;
+                    Compilation::Native(native)
+                }
+                Err(_) => Compilation::Interpreted,
+            };
+        }
+        let Compilation::Native(native) = &hot.compilation else {
+            return None;
+        };
+
+        if arguments.len() != native.parameters.len() {
+            return None;
+        }
+        let arguments: Vec<i64> = zip(arguments, &native.parameters)
+            .map(|(argument, kind)| match (argument, kind) {
+                (TypeVal::Int(x), Kind::Int) => Some(*x),
+                (TypeVal::Boolean(x), Kind::Bool) => Some(*x as i64),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        let never = AtomicBool::new(false);
+        let mut result = 0;
+        // The code was generated for these argument types, and the module is never freed
+        let status = unsafe {
+            (native.entry)(
+                arguments.as_ptr(),
+                depth,
+                cancelled.unwrap_or(&never),
+                &mut result,
+            )
+        };
+        match (status, native.result) {
+            (0, Kind::Int) => Some(TypeVal::Int(result)),
+            (0, Kind::Bool) => Some(TypeVal::Boolean(result != 0)),
+            _ => None,
+        }
+    }
+}
+
+fn new_module() -> Result<JITModule, String> {
+    let mut flags = settings::builder();
+    flags
+        .set("opt_level", "speed")
+        .map_err(|err| err.to_string())?;
+    let isa = cranelift_native::builder()?
+        .finish(settings::Flags::new(flags))
+        .map_err(|err| err.to_string())?;
+    Ok(JITModule::new(JITBuilder::with_isa(
+        isa,
+        default_libcall_names(),
+    )))
+}
+
+fn kind_of(annotation: &Option<Type>) -> Result<Kind, String> {
+    match annotation {
+        Some(Type::Int) => Ok(Kind::Int),
+        Some(Type::Bool) => Ok(Kind::Bool),
+        _ => Err("Only int and bool values can be compiled".to_string()),
+    }
+}
+
+/// Compile a function to native code, failing if it uses anything native code does not handle.
+fn compile(
+    module: &mut Option<JITModule>,
+    name: &str,
+    function: &FunctionDef,
+) -> Result<NativeFunction, String> {
+    let parameters: Vec<Kind> = function
+        .arguments
+        .iter()
+        .map(|arg| kind_of(&arg.type_annotation))
+        .collect::<Result<_, _>>()?;
+    // Functions without a return type return 0 when they end
+    let result = match function.return_type {
+        None => Kind::Int,
+        Some(_) => kind_of(&function.return_type)?,
+    };
+    let module = match module {
+        Some(module) => module,
+        None => module.insert(new_module()?),
+    };
+    let pointer = module.target_config().pointer_type();
+
+    let mut signature = module.make_signature();
+    for _ in &parameters {
+        signature.params.push(AbiParam::new(types::I64));
+    }
+    signature.params.push(AbiParam::new(types::I64));
+    signature.params.push(AbiParam::new(pointer));
+    signature.returns.push(AbiParam::new(types::I64));
+    signature.returns.push(AbiParam::new(types::I64));
+    let body = module
+        .declare_anonymous_function(&signature)
+        .map_err(|err| err.to_string())?;
+
+    let mut context = module.make_context();
+    context.func.signature = signature;
+    let mut builder_context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+    let callee = module.declare_func_in_func(body, builder.func);
+    let entry = builder.create_block();
+    builder.append_block_params_for_function_params(entry);
+    builder.switch_to_block(entry);
+    builder.seal_block(entry);
+    let values = builder.block_params(entry).to_vec();
+
+    let mut translator = Translator {
+        builder,
+        name,
+        parameters: parameters.clone(),
+        result,
+        annotated: function.return_type.is_some(),
+        scopes: vec![HashMap::new()],
+        variables: 0,
+        depth: values[parameters.len()],
+        cancelled: values[parameters.len() + 1],
+        callee,
+    };
+    // The arguments are bound in the frame of the body
+    for (arg, (value, kind)) in zip(&function.arguments, zip(values, &parameters)) {
+        translator.declare(&arg.name, *kind, value)?;
+    }
+    let exhausted =
+        translator
+            .builder
+            .ins()
+            .icmp_imm(IntCC::SignedLessThanOrEqual, translator.depth, 0);
+    translator.bail_if(exhausted);
+    if !translator.statements(&function.body)? {
+        // Reaching the end is an error when a value must be returned
+        let status = translator.annotated as i64;
+        translator.finish(0, status);
+    }
+    translator.builder.finalize();
+    module
+        .define_function(body, &mut context)
+        .map_err(|err| err.to_string())?;
+    module.clear_context(&mut context);
+
+    let entry = define_entry(module, body, parameters.len(), &mut builder_context)?;
+    module
+        .finalize_definitions()
+        .map_err(|err| err.to_string())?;
+    let entry = module.get_finalized_function(entry);
+    Ok(NativeFunction {
+        entry: unsafe { mem::transmute::<*const u8, Entry>(entry) },
+        parameters,
+        result,
+    })
+}
+
+/// Define the function called from the interpreter, loading the arguments from memory.
+fn define_entry(
+    module: &mut JITModule,
+    body: FuncId,
+    arguments: usize,
+    builder_context: &mut FunctionBuilderContext,
+) -> Result<FuncId, String> {
+    let pointer = module.target_config().pointer_type();
+    let mut signature = module.make_signature();
+    signature.params.push(AbiParam::new(pointer));
+    signature.params.push(AbiParam::new(types::I64));
+    signature.params.push(AbiParam::new(pointer));
+    signature.params.push(AbiParam::new(pointer));
+    signature.returns.push(AbiParam::new(types::I64));
+    let entry = module
+        .declare_anonymous_function(&signature)
+        .map_err(|err| err.to_string())?;
+
+    let mut context = module.make_context();
+    context.func.signature = signature;
+    let mut builder = FunctionBuilder::new(&mut context.func, builder_context);
+    let callee = module.declare_func_in_func(body, builder.func);
+    let block = builder.create_block();
+    builder.append_block_params_for_function_params(block);
+    builder.switch_to_block(block);
+    builder.seal_block(block);
+    let params = builder.block_params(block).to_vec();
+    let mut values: Vec<Value> = (0..arguments)
+        .map(|index| {
+            builder
+                .ins()
+                .load(types::I64, MemFlags::trusted(), params[0], 8 * index as i32)
+        })
+        .collect();
+    values.push(params[1]);
+    values.push(params[2]);
+    let call = builder.ins().call(callee, &values);
+    let results = builder.inst_results(call).to_vec();
+    builder
+        .ins()
+        .store(MemFlags::trusted(), results[0], params[3], 0);
+    builder.ins().return_(&[results[1]]);
+    builder.finalize();
+    module
+        .define_function(entry, &mut context)
+        .map_err(|err| err.to_string())?;
+    module.clear_context(&mut context);
+    Ok(entry)
+}
+
+/// Generator of the body of a function.
+///
+/// depth, cancelled: parameters with the nested calls left and the cancellation flag.
+///
+/// annotated: the return type is declared, so reaching the end of the body is an error.
+struct Translator<'a> {
+    builder: FunctionBuilder<'a>,
+    name: &'a str,
+    parameters: Vec<Kind>,
+    result: Kind,
+    annotated: bool,
+    scopes: Vec<HashMap<String, (Variable, Kind)>>,
+    variables: u32,
+    depth: Value,
+    cancelled: Value,
+    callee: codegen::ir::FuncRef,
+}
+
+impl Translator<'_> {
+    fn lookup(&self, name: &str) -> Option<(Variable, Kind)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn declare(&mut self, name: &str, kind: Kind, value: Value) -> Result<(), String> {
+        // The interpreter refuses to overshadow a variable
+        if self.lookup(name).is_some() {
+            return Err(format!("({}) is declared twice", name));
+        }
+        let variable = Variable::from_u32(self.variables);
+        self.variables += 1;
+        self.builder.declare_var(variable, types::I64);
+        self.builder.def_var(variable, value);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), (variable, kind));
+        }
+        Ok(())
+    }
+
+    /// Return a value and a status, nonzero to let the interpreter run the call.
+    fn finish(&mut self, value: i64, status: i64) {
+        let value = self.builder.ins().iconst(types::I64, value);
+        let status = self.builder.ins().iconst(types::I64, status);
+        self.builder.ins().return_(&[value, status]);
+    }
+
+    /// Give the call back to the interpreter when cond is true.
+    fn bail_if(&mut self, cond: Value) {
+        let bail = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.ins().brif(cond, bail, &[], next, &[]);
+        self.builder.seal_block(bail);
+        self.builder.seal_block(next);
+        self.builder.switch_to_block(bail);
+        self.finish(0, 1);
+        self.builder.switch_to_block(next);
+    }
+
+    /// Generate the statements of a block, returns true if it always returns.
+    fn block(&mut self, tree: &[Statement]) -> Result<bool, String> {
+        self.scopes.push(HashMap::new());
+        let returns = self.statements(tree);
+        self.scopes.pop();
+        returns
+    }
+
+    fn statements(&mut self, tree: &[Statement]) -> Result<bool, String> {
+        for stmt in tree {
+            if self.statement(stmt)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Result<bool, String> {
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => {
+                let (value, kind) = self.expression(value)?;
+                if type_annotation.is_some() && kind_of(type_annotation)? != kind {
+                    return Err(format!("({}) does not match its annotation", name));
+                }
+                self.declare(name, kind, value)?;
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                let (value, kind) = self.expression(value)?;
+                match self.lookup(name) {
+                    Some((variable, declared)) if declared == kind => {
+                        self.builder.def_var(variable, value)
+                    }
+                    _ => return Err(format!("({}) cannot be assigned", name)),
+                }
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                let cond = self.condition(cond)?;
+                let then_block = self.builder.create_block();
+                let merge = self.builder.create_block();
+                self.builder.ins().brif(cond, then_block, &[], merge, &[]);
+                self.builder.seal_block(then_block);
+                self.builder.switch_to_block(then_block);
+                if !self.block(then_part)? {
+                    self.builder.ins().jump(merge, &[]);
+                }
+                self.builder.seal_block(merge);
+                self.builder.switch_to_block(merge);
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                let cond = self.condition(cond)?;
+                let then_block = self.builder.create_block();
+                let else_block = self.builder.create_block();
+                let merge = self.builder.create_block();
+                self.builder
+                    .ins()
+                    .brif(cond, then_block, &[], else_block, &[]);
+                self.builder.seal_block(then_block);
+                self.builder.seal_block(else_block);
+                self.builder.switch_to_block(then_block);
+                let then_returns = self.block(then_part)?;
+                if !then_returns {
+                    self.builder.ins().jump(merge, &[]);
+                }
+                self.builder.switch_to_block(else_block);
+                let else_returns = self.block(else_part)?;
+                if !else_returns {
+                    self.builder.ins().jump(merge, &[]);
+                }
+                if then_returns && else_returns {
+                    return Ok(true);
+                }
+                self.builder.seal_block(merge);
+                self.builder.switch_to_block(merge);
+            }
+            StatementKind::WhileStatement { cond, body, .. } => {
+                let header = self.builder.create_block();
+                let body_block = self.builder.create_block();
+                let exit = self.builder.create_block();
+                self.builder.ins().jump(header, &[]);
+                self.builder.switch_to_block(header);
+                let cancelled =
+                    self.builder
+                        .ins()
+                        .load(types::I8, MemFlags::trusted(), self.cancelled, 0);
+                self.bail_if(cancelled);
+                let cond = self.condition(cond)?;
+                self.builder.ins().brif(cond, body_block, &[], exit, &[]);
+                self.builder.seal_block(body_block);
+                self.builder.switch_to_block(body_block);
+                if !self.block(body)? {
+                    self.builder.ins().jump(header, &[]);
+                }
+                self.builder.seal_block(header);
+                self.builder.seal_block(exit);
+                self.builder.switch_to_block(exit);
+            }
+            StatementKind::FunctionCallStatement { name, arguments } => {
+                self.call(name, arguments)?;
+            }
+            StatementKind::ReturnStatement { value } => {
+                let (value, kind) = self.expression(value)?;
+                if kind != self.result {
+                    return Err(format!("({}) returns a different type", self.name));
+                }
+                let status = self.builder.ins().iconst(types::I64, 0);
+                self.builder.ins().return_(&[value, status]);
+                return Ok(true);
+            }
+            _ => return Err("The statement cannot be compiled".to_string()),
+        }
+        Ok(false)
+    }
+
+    fn condition(&mut self, cond: &Expression) -> Result<Value, String> {
+        match self.expression(cond)? {
+            (cond, Kind::Bool) => Ok(cond),
+            _ => Err("The condition is not a bool".to_string()),
+        }
+    }
+
+    /// Call the function itself, the calls to the other functions are not compiled.
+    fn call(&mut self, name: &str, arguments: &[Box<Expression>]) -> Result<Value, String> {
+        if name != self.name || arguments.len() != self.parameters.len() {
+            return Err(format!("The call to ({}) cannot be compiled", name));
+        }
+        let mut values = vec![];
+        for (argument, kind) in zip(arguments, self.parameters.clone()) {
+            match self.expression(argument)? {
+                (value, argument_kind) if argument_kind == kind => values.push(value),
+                _ => return Err(format!("The call to ({}) has wrong arguments", name)),
+            }
+        }
+        let depth = self.builder.ins().iadd_imm(self.depth, -1);
+        values.push(depth);
+        values.push(self.cancelled);
+        let call = self.builder.ins().call(self.callee, &values);
+        let results = self.builder.inst_results(call).to_vec();
+        self.bail_if(results[1]);
+        Ok(results[0])
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Result<(Value, Kind), String> {
+        Ok(match expr {
+            Expression::Int(x) => (self.builder.ins().iconst(types::I64, *x), Kind::Int),
+            Expression::Bool(x) => (self.builder.ins().iconst(types::I64, *x as i64), Kind::Bool),
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                match self.lookup(name) {
+                    Some((variable, kind)) => (self.builder.use_var(variable), kind),
+                    None => return Err(format!("({}) is not a local variable", name)),
+                }
+            }
+            Expression::FunctionCall { name, arguments } => {
+                (self.call(name, arguments)?, self.result)
+            }
+            Expression::BinaryOperation { lhs, operator, rhs } => {
+                let lhs = self.expression(lhs)?;
+                let rhs = self.expression(rhs)?;
+                self.binary(lhs, operator, rhs)?
+            }
+            Expression::UnaryOperation { operator, rhs } => match (operator, self.expression(rhs)?)
+            {
+                (UnaryOperator::Minus, (x, Kind::Int)) => {
+                    let overflows = self.builder.ins().icmp_imm(IntCC::Equal, x, i64::MIN);
+                    self.bail_if(overflows);
+                    (self.builder.ins().ineg(x), Kind::Int)
+                }
+                (UnaryOperator::Not, (x, Kind::Bool)) => {
+                    (self.builder.ins().bxor_imm(x, 1), Kind::Bool)
+                }
+                _ => return Err("The operation cannot be compiled".to_string()),
+            },
+            _ => return Err("The expression cannot be compiled".to_string()),
+        })
+    }
+
+    fn binary(
+        &mut self,
+        (x, lhs): (Value, Kind),
+        operator: &BinaryOperator,
+        (y, rhs): (Value, Kind),
+    ) -> Result<(Value, Kind), String> {
+        let cond = match (operator, lhs, rhs) {
+            (
+                BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul,
+                Kind::Int,
+                Kind::Int,
+            ) => {
+                let (result, overflows) = match operator {
+                    BinaryOperator::Add => self.builder.ins().sadd_overflow(x, y),
+                    BinaryOperator::Sub => self.builder.ins().ssub_overflow(x, y),
+                    _ => self.builder.ins().smul_overflow(x, y),
+                };
+                self.bail_if(overflows);
+                return Ok((result, Kind::Int));
+            }
+            (BinaryOperator::Mod, Kind::Int, Kind::Int) => {
+                let by_zero = self.builder.ins().icmp_imm(IntCC::Equal, y, 0);
+                self.bail_if(by_zero);
+                let min = self.builder.ins().icmp_imm(IntCC::Equal, x, i64::MIN);
+                let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, y, -1);
+                let overflows = self.builder.ins().band(min, minus_one);
+                self.bail_if(overflows);
+                return Ok((self.builder.ins().srem(x, y), Kind::Int));
+            }
+            (BinaryOperator::And, Kind::Bool, Kind::Bool) => {
+                return Ok((self.builder.ins().band(x, y), Kind::Bool))
+            }
+            (BinaryOperator::Or, Kind::Bool, Kind::Bool) => {
+                return Ok((self.builder.ins().bor(x, y), Kind::Bool))
+            }
+            (BinaryOperator::Less, Kind::Int, Kind::Int) => Some(IntCC::SignedLessThan),
+            (BinaryOperator::Greater, Kind::Int, Kind::Int) => Some(IntCC::SignedGreaterThan),
+            (BinaryOperator::LessEq, Kind::Int, Kind::Int) => Some(IntCC::SignedLessThanOrEqual),
+            (BinaryOperator::GreaterEq, Kind::Int, Kind::Int) => {
+                Some(IntCC::SignedGreaterThanOrEqual)
+            }
+            (BinaryOperator::CompareEq, lhs, rhs) if lhs == rhs => Some(IntCC::Equal),
+            (BinaryOperator::CompareNeq, lhs, rhs) if lhs == rhs => Some(IntCC::NotEqual),
+            _ => None,
+        };
+        match cond {
+            Some(cond) => {
+                let result = self.builder.ins().icmp(cond, x, y);
+                Ok((self.builder.ins().uextend(types::I64, result), Kind::Bool))
+            }
+            None => Err("The operation cannot be compiled".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::interpreter::TypeVal::{Boolean, Int};
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    #[test]
+    fn compiles_hot_functions_and_falls_back() {
+        let mut interpreter = Interpreter::builder().max_depth(50).jit().build();
+        let src = "fn down (n: int) -> int { if n == 0 { return 0; } return 1 + down(n - 1); } \
+                   fn odd (n: int, strict: bool) -> bool { if strict { return !(n % 2 == 0); } }";
+        interpreter
+            .run(&mut ProgramParser::new().parse(Lexer::new(src)).unwrap())
+            .unwrap();
+        for n in 0..200 {
+            assert_eq!(
+                interpreter.call_function("down", vec![Int(n % 20)]),
+                Ok(Int(n % 20))
+            );
+            assert_eq!(
+                interpreter.call_function("odd", vec![Int(n), Boolean(true)]),
+                Ok(Boolean(n % 2 == 1))
+            );
+        }
+        assert_eq!(interpreter.runtime().jit.as_ref().unwrap().compiled, 2);
+
+        // The interpreter reports what stopped the native code
+        assert!(interpreter
+            .call_function("down", vec![Int(60)])
+            .unwrap_err()
+            .contains("maximum call depth"));
+        assert!(interpreter
+            .call_function("odd", vec![Int(1), Boolean(false)])
+            .unwrap_err()
+            .contains("ended without returning"));
+        assert!(interpreter
+            .call_function("down", vec![Boolean(true)])
+            .is_err());
+    }
+
+    #[test]
+    fn compiles_declarations_in_loops() {
+        let mut interpreter = Interpreter::builder().jit().build();
+        let src = "fn squares (n: int) -> int { let mut total = 0; let mut i = 0; \
+                   while i < n { let square = i * i; total = total + square; i = i + 1; } \
+                   return total; }";
+        interpreter
+            .run(&mut ProgramParser::new().parse(Lexer::new(src)).unwrap())
+            .unwrap();
+        for _ in 0..200 {
+            assert_eq!(
+                interpreter.call_function("squares", vec![Int(4)]),
+                Ok(Int(14))
+            );
+        }
+        assert_eq!(interpreter.runtime().jit.as_ref().unwrap().compiled, 1);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::interpreter::interpreter::TypeVal;
+use std::mem;
+
+/// Values bound to the variables of a running program and the limits they must respect.
+///
+/// values, bytes: number and approximate size of the live values.
+///
+/// max_values, max_bytes: caps over which binding a value is an error.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Memory {
+    pub values: usize,
+    pub bytes: usize,
+    pub max_values: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+/// Approximate number of bytes used by a value.
+pub fn size_of_value(value: &TypeVal) -> usize {
+    match value {
+        TypeVal::Str(x) => mem::size_of::<TypeVal>() + x.len(),
+        _ => mem::size_of::<TypeVal>(),
+    }
+}
+
+impl Memory {
+    /// Account a value bound to a variable, replacing the previous value if any.
+    ///
+    /// Nothing is accounted when a limit would be exceeded.
+    pub fn bind(&mut self, previous: Option<&TypeVal>, value: &TypeVal) -> Result<(), String> {
+        let (values, bytes) = match previous {
+            Some(previous) => (
+                self.values,
+                self.bytes.saturating_sub(size_of_value(previous)),
+            ),
+            None => (self.values + 1, self.bytes),
+        };
+        let bytes = bytes + size_of_value(value);
+        if let Some(max_values) = self.max_values.filter(|max_values| values > *max_values) {
+            return Err(format!(
+                "The program exceeds the maximum of {} live values",
+                max_values
+            ));
+        }
+        if let Some(max_bytes) = self.max_bytes.filter(|max_bytes| bytes > *max_bytes) {
+            return Err(format!(
+                "The program exceeds the memory limit of {} bytes",
+                max_bytes
+            ));
+        }
+        self.values = values;
+        self.bytes = bytes;
+        Ok(())
+    }
+
+    /// Release a value no longer bound to any variable.
+    pub fn release(&mut self, value: &TypeVal) {
+        self.values = self.values.saturating_sub(1);
+        self.bytes = self.bytes.saturating_sub(size_of_value(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_limits() {
+        let mut memory = Memory {
+            max_values: Some(2),
+            max_bytes: Some(2 * mem::size_of::<TypeVal>() + 4),
+            ..Default::default()
+        };
+        memory.bind(None, &TypeVal::Int(1)).unwrap();
+        memory.bind(None, &TypeVal::Str("ab".into())).unwrap();
+        assert!(memory.bind(None, &TypeVal::Int(2)).is_err());
+        assert!(memory
+            .bind(Some(&TypeVal::Int(1)), &TypeVal::Str("abc".into()))
+            .is_err());
+        memory
+            .bind(Some(&TypeVal::Int(1)), &TypeVal::Str("ab".into()))
+            .unwrap();
+        memory.release(&TypeVal::Str("ab".into()));
+        assert_eq!(memory.values, 1);
+        assert_eq!(memory.bytes, mem::size_of::<TypeVal>() + 2);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::interpreter::environment::Environment;
+use crate::interpreter::interpreter::TypeVal;
+use crate::parsing::ast::Statement;
+use std::fmt;
+
+/// Hooks called by the interpreter as the program runs, registered with the builder.
+///
+/// Embedders build their own profilers, debuggers or visualizers on them without changing the
+/// evaluator. Every hook does nothing by default, and the functions spawned on other threads are
+/// not observed.
+pub trait Observer: fmt::Debug {
+    /// Called right before a statement is executed, with the scope it runs in.
+    fn on_statement(&mut self, _stmt: &Statement, _env: &Environment) {}
+
+    /// Called before a function, a native function or a builtin runs, with its evaluated arguments.
+    fn on_call(&mut self, _name: &str, _arguments: &[TypeVal]) {}
+
+    /// Called when a variable watched with the builder changes, with the line of the statement
+    /// changing it. old is None when the variable is declared.
+    fn on_watch(&mut self, _name: &str, _old: Option<&TypeVal>, _new: &TypeVal, _line: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::language_runner::run_language::run_source;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug, Default)]
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Observer for Recorder {
+        fn on_statement(&mut self, _stmt: &Statement, env: &Environment) {
+            let x = env.get_variable_value("x").ok();
+            self.0.borrow_mut().push(format!("statement x={:?}", x));
+        }
+
+        fn on_call(&mut self, name: &str, arguments: &[TypeVal]) {
+            self.0
+                .borrow_mut()
+                .push(format!("call {} {:?}", name, arguments));
+        }
+
+        fn on_watch(&mut self, name: &str, old: Option<&TypeVal>, new: &TypeVal, line: usize) {
+            self.0
+                .borrow_mut()
+                .push(format!("line {}: {} {:?} -> {}", line, name, old, new));
+        }
+    }
+
+    #[test]
+    fn observes_statements_and_calls() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::builder()
+            .observer(Recorder(events.clone()))
+            .build();
+        run_source(
+            "fn f (n) -> { return abs(n); } let x = f(-1);",
+            &mut interpreter,
+        )
+        .unwrap();
+        assert_eq!(
+            *events.borrow(),
+            [
+                "statement x=None",
+                "statement x=None",
+                "call f [Int(-1)]",
+                "statement x=None",
+                "call abs [Int(-1)]",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_changes_of_watched_variables() {
+        let src = "let mut n = 1;\nlet mut other = 0;\nn = 1;\nn = n + 1;\nother = 2;";
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::builder()
+            .source(src)
+            .observer(Recorder(events.clone()))
+            .watch("n")
+            .build();
+        run_source(src, &mut interpreter).unwrap();
+        let watched: Vec<String> = events
+            .borrow()
+            .iter()
+            .filter(|event| event.starts_with("line"))
+            .cloned()
+            .collect();
+        assert_eq!(
+            watched,
+            ["line 1: n None -> 1", "line 4: n Some(Int(1)) -> 2"]
+        );
+    }
+}
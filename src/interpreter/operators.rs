@@ -0,0 +1,354 @@
+use crate::interpreter::coercion::{coerce, common_type, is_number};
+use crate::interpreter::decimal::Decimal;
+use crate::interpreter::error_reporting::{
+    error_reporting_binary_operator, error_reporting_generic, error_reporting_unary_operator,
+};
+use crate::interpreter::interpreter::TypeVal::{
+    self, Boolean, Channel as Chan, Char, Decimal as Dec, Float, Int, Str,
+};
+use crate::parsing::ast::{BinaryOperator, UnaryOperator};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// Operands of a numeric operation converted to their common type.
+enum Numbers {
+    Ints(i64, i64),
+    Floats(f64, f64),
+    Decimals(Decimal, Decimal),
+}
+
+/// Numbers converted to their common type, None if either is not a number or they have none.
+fn promote(lhs: &TypeVal, rhs: &TypeVal) -> Option<Numbers> {
+    let common = common_type(lhs.get_type(), rhs.get_type()).filter(|t| is_number(*t))?;
+    match (coerce(lhs.clone(), common)?, coerce(rhs.clone(), common)?) {
+        (Int(x), Int(y)) => Some(Numbers::Ints(x, y)),
+        (Float(x), Float(y)) => Some(Numbers::Floats(x, y)),
+        (Dec(x), Dec(y)) => Some(Numbers::Decimals(x, y)),
+        _ => None,
+    }
+}
+
+/// Name of a binary operator in the error messages.
+fn operator_name(operator: &BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "Sum",
+        BinaryOperator::Sub => "Difference",
+        BinaryOperator::Mul => "Product",
+        BinaryOperator::Div => "Division",
+        BinaryOperator::Mod => "Modulo",
+        BinaryOperator::And => "Logical AND",
+        BinaryOperator::Or => "Logical OR",
+        BinaryOperator::Less => "Logical LESS",
+        BinaryOperator::Greater => "Logical GREATER",
+        BinaryOperator::LessEq => "Logical LEQ",
+        BinaryOperator::GreaterEq => "Logical GEQ",
+        BinaryOperator::CompareEq => "Logical EQ",
+        BinaryOperator::CompareNeq => "Logical NEQ",
+    }
+}
+
+/// Str value joining the texts of strs, with a single allocation.
+pub fn concat_strings(parts: &[&Arc<str>]) -> TypeVal {
+    // Every str is stored between its quotes
+    let mut joined =
+        String::with_capacity(parts.iter().map(|part| part.len() - 2).sum::<usize>() + 2);
+    joined.push('"');
+    for part in parts {
+        joined.push_str(&part[1..part.len() - 1]);
+    }
+    joined.push('"');
+    Str(joined.into())
+}
+
+/// Lexicographic order of two strings, by code point and without their quotes.
+fn compare_strings(x: &str, y: &str) -> Ordering {
+    x[1..x.len() - 1].cmp(&y[1..y.len() - 1])
+}
+
+impl TypeVal {
+    /// Apply a binary operator to the value and rhs.
+    pub fn binary(&self, operator: &BinaryOperator, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match operator {
+            BinaryOperator::Add => self.add(rhs),
+            BinaryOperator::Sub => self.sub(rhs),
+            BinaryOperator::Mul => self.mul(rhs),
+            BinaryOperator::Div => self.div(rhs),
+            BinaryOperator::Mod => self.rem(rhs),
+            BinaryOperator::And => self.and(rhs),
+            BinaryOperator::Or => self.or(rhs),
+            BinaryOperator::Less => self.order(operator, rhs, Ordering::is_lt),
+            BinaryOperator::Greater => self.order(operator, rhs, Ordering::is_gt),
+            BinaryOperator::LessEq => self.order(operator, rhs, Ordering::is_le),
+            BinaryOperator::GreaterEq => self.order(operator, rhs, Ordering::is_ge),
+            BinaryOperator::CompareEq => self.equal(operator, rhs).map(Boolean),
+            BinaryOperator::CompareNeq => self.equal(operator, rhs).map(|eq| Boolean(!eq)),
+        }
+    }
+
+    /// Apply a unary operator to the value.
+    pub fn unary(&self, operator: &UnaryOperator) -> Result<TypeVal, String> {
+        match operator {
+            UnaryOperator::Minus => self.neg(),
+            UnaryOperator::Not => self.not(),
+        }
+    }
+
+    pub fn add(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        if let (Str(x), Str(y)) = (self, rhs) {
+            return Ok(concat_strings(&[x, y]));
+        }
+        match promote(self, rhs) {
+            Some(Numbers::Ints(x, y)) => match x.checked_add(y) {
+                Some(result) => Ok(Int(result)),
+                None => self.failed(&BinaryOperator::Add, rhs),
+            },
+            Some(Numbers::Floats(x, y)) => Ok(Float(x + y)),
+            Some(Numbers::Decimals(x, y)) => x.add(&y).map(Dec),
+            None => self.unsupported(&BinaryOperator::Add, rhs),
+        }
+    }
+
+    pub fn sub(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match promote(self, rhs) {
+            Some(Numbers::Ints(x, y)) => match x.checked_sub(y) {
+                Some(result) => Ok(Int(result)),
+                None => self.failed(&BinaryOperator::Sub, rhs),
+            },
+            Some(Numbers::Floats(x, y)) => Ok(Float(x - y)),
+            Some(Numbers::Decimals(x, y)) => x.sub(&y).map(Dec),
+            None => self.unsupported(&BinaryOperator::Sub, rhs),
+        }
+    }
+
+    pub fn mul(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match promote(self, rhs) {
+            Some(Numbers::Ints(x, y)) => match x.checked_mul(y) {
+                Some(result) => Ok(Int(result)),
+                None => self.failed(&BinaryOperator::Mul, rhs),
+            },
+            Some(Numbers::Floats(x, y)) => Ok(Float(x * y)),
+            Some(Numbers::Decimals(x, y)) => x.mul(&y).map(Dec),
+            None => self.unsupported(&BinaryOperator::Mul, rhs),
+        }
+    }
+
+    /// Division of ints is an int when it is exact, a float otherwise.
+    pub fn div(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match promote(self, rhs) {
+            Some(Numbers::Ints(x, y)) => match (x.checked_div(y), x.checked_rem(y)) {
+                (Some(quotient), Some(0)) => Ok(Int(quotient)),
+                (Some(_), Some(_)) => Ok(Float(x as f64 / y as f64)),
+                _ => self.failed(&BinaryOperator::Div, rhs),
+            },
+            Some(Numbers::Floats(x, y)) => Ok(Float(x / y)),
+            _ => self.unsupported(&BinaryOperator::Div, rhs),
+        }
+    }
+
+    pub fn rem(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match (self, rhs) {
+            (Int(x), Int(y)) => match x.checked_rem(*y) {
+                Some(result) => Ok(Int(result)),
+                None => self.failed(&BinaryOperator::Mod, rhs),
+            },
+            _ => self.unsupported(&BinaryOperator::Mod, rhs),
+        }
+    }
+
+    pub fn and(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match (self, rhs) {
+            (Boolean(x), Boolean(y)) => Ok(Boolean(*x && *y)),
+            _ => self.unsupported(&BinaryOperator::And, rhs),
+        }
+    }
+
+    pub fn or(&self, rhs: &TypeVal) -> Result<TypeVal, String> {
+        match (self, rhs) {
+            (Boolean(x), Boolean(y)) => Ok(Boolean(*x || *y)),
+            _ => self.unsupported(&BinaryOperator::Or, rhs),
+        }
+    }
+
+    /// Compare numbers, strings or chars, test tells if their order satisfies the operator.
+    ///
+    /// NaN is neither smaller nor larger than any number.
+    fn order(
+        &self,
+        operator: &BinaryOperator,
+        rhs: &TypeVal,
+        test: fn(Ordering) -> bool,
+    ) -> Result<TypeVal, String> {
+        let order = match (self, rhs) {
+            (Str(x), Str(y)) => Some(compare_strings(x, y)),
+            (Char(x), Char(y)) => Some(x.cmp(y)),
+            _ => match promote(self, rhs) {
+                Some(Numbers::Ints(x, y)) => Some(x.cmp(&y)),
+                Some(Numbers::Floats(x, y)) => x.partial_cmp(&y),
+                Some(Numbers::Decimals(x, y)) => Some(x.cmp(&y)),
+                None => return self.unsupported(operator, rhs),
+            },
+        };
+        Ok(Boolean(order.is_some_and(test)))
+    }
+
+    /// Equality of two values of the same type, numbers are compared in their common type.
+    fn equal(&self, operator: &BinaryOperator, rhs: &TypeVal) -> Result<bool, String> {
+        match (self, rhs) {
+            (Chan(_), _) | (_, Chan(_)) => self.unsupported(operator, rhs).map(|_| false),
+            _ => match promote(self, rhs) {
+                Some(Numbers::Ints(x, y)) => Ok(x == y),
+                Some(Numbers::Floats(x, y)) => Ok(x == y),
+                Some(Numbers::Decimals(x, y)) => Ok(x == y),
+                None if self.get_type() == rhs.get_type() => Ok(self == rhs),
+                None => self.unsupported(operator, rhs).map(|_| false),
+            },
+        }
+    }
+
+    pub fn neg(&self) -> Result<TypeVal, String> {
+        match self {
+            Int(x) => match x.checked_neg() {
+                Some(result) => Ok(Int(result)),
+                None => error_reporting_unary_operator("Minus overflows".to_string(), self),
+            },
+            Float(x) => Ok(Float(-x)),
+            Dec(x) => Ok(Dec(x.neg())),
+            Chan(_) => {
+                error_reporting_generic("Channels are not supported by operators".to_string())
+            }
+            _ => error_reporting_unary_operator(
+                format!("Minus {} is not supported", self.get_type()),
+                self,
+            ),
+        }
+    }
+
+    pub fn not(&self) -> Result<TypeVal, String> {
+        match self {
+            Boolean(x) => Ok(Boolean(!x)),
+            Chan(_) => {
+                error_reporting_generic("Channels are not supported by operators".to_string())
+            }
+            _ => error_reporting_unary_operator(
+                format!("Not {} is not supported", self.get_type()),
+                self,
+            ),
+        }
+    }
+
+    /// Character of a string at the position of index, from 0.
+    pub fn index(&self, index: &TypeVal) -> Result<TypeVal, String> {
+        match (self, index) {
+            (Str(x), Int(i)) => {
+                let text = &x[1..x.len() - 1];
+                usize::try_from(*i)
+                    .ok()
+                    .and_then(|i| text.chars().nth(i))
+                    .map(Char)
+                    .ok_or_else(|| {
+                        format!(
+                            "Index {} is out of a string of {} characters",
+                            i,
+                            text.chars().count()
+                        )
+                    })
+            }
+            (Str(_), index) => Err(format!(
+                "A string is indexed by an int, not by a {}",
+                index.get_type()
+            )),
+            (target, _) => Err(format!(
+                "Values of type {} cannot be indexed",
+                target.get_type()
+            )),
+        }
+    }
+
+    /// Error of an operation on ints without a result, a division by zero or an overflow.
+    fn failed(&self, operator: &BinaryOperator, rhs: &TypeVal) -> Result<TypeVal, String> {
+        let message = match rhs {
+            Int(0) => format!("{} by zero", operator_name(operator)),
+            _ => format!("{} overflows", operator_name(operator)),
+        };
+        error_reporting_binary_operator(message, self, rhs)
+    }
+
+    /// Error of an operator not applying to the value and rhs.
+    fn unsupported(&self, operator: &BinaryOperator, rhs: &TypeVal) -> Result<TypeVal, String> {
+        let message = match (self, rhs) {
+            (Chan(_), _) | (_, Chan(_)) => {
+                return error_reporting_generic(
+                    "Channels are not supported by operators".to_string(),
+                )
+            }
+            (Char(_), Char(_)) => format!("{} of chars is not supported", operator_name(operator)),
+            (TypeVal::Set(_), TypeVal::Set(_)) => format!(
+                "{} of sets is not supported, use union, intersect or difference",
+                operator_name(operator)
+            ),
+            (Dec(_), Dec(_) | Int(_)) | (Int(_), Dec(_)) => {
+                format!("{} of decimals is not supported", operator_name(operator))
+            }
+            _ => format!("{} between incompatible types", operator_name(operator)),
+        };
+        error_reporting_binary_operator(message, self, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn promotes_the_operands() {
+        let decimal = Dec(Decimal::parse("1.5").unwrap());
+        assert_eq!(Int(1).add(&Float(0.5)), Ok(Float(1.5)));
+        assert_eq!(Int(7).div(&Int(2)), Ok(Float(3.5)));
+        assert_eq!(Int(6).div(&Int(2)), Ok(Int(3)));
+        assert_eq!(
+            Int(2).mul(&decimal),
+            Ok(Dec(Decimal::parse("3.0").unwrap()))
+        );
+        assert_eq!(
+            Int(1).binary(&BinaryOperator::Less, &Float(1.5)),
+            Ok(Boolean(true))
+        );
+        assert_eq!(
+            Float(f64::NAN).binary(&BinaryOperator::GreaterEq, &Int(1)),
+            Ok(Boolean(false))
+        );
+        assert_eq!(
+            TypeVal::from("a").binary(&BinaryOperator::Less, &TypeVal::from("b")),
+            Ok(Boolean(true))
+        );
+        assert_eq!(
+            Int(1).binary(&BinaryOperator::CompareEq, &decimal),
+            Ok(Boolean(false))
+        );
+        assert!(Float(1.0).add(&decimal).is_err());
+        assert_eq!(
+            Int(1).binary(&BinaryOperator::CompareEq, &Float(1.0)),
+            Ok(Boolean(true))
+        );
+        assert!(Int(1)
+            .binary(&BinaryOperator::CompareEq, &Boolean(true))
+            .is_err());
+        let err = Char('a').add(&Char('b')).unwrap_err();
+        assert!(err.contains("Sum of chars is not supported"), "{}", err);
+        for (lhs, operator, rhs, message) in [
+            (1, BinaryOperator::Div, 0, "Division by zero"),
+            (5, BinaryOperator::Mod, 0, "Modulo by zero"),
+            (i64::MIN, BinaryOperator::Div, -1, "Division overflows"),
+            (i64::MIN, BinaryOperator::Mod, -1, "Modulo overflows"),
+            (i64::MAX, BinaryOperator::Add, 1, "Sum overflows"),
+            (i64::MIN, BinaryOperator::Sub, 1, "Difference overflows"),
+            (i64::MAX, BinaryOperator::Mul, 2, "Product overflows"),
+        ] {
+            let err = Int(lhs).binary(&operator, &Int(rhs)).unwrap_err();
+            assert!(err.contains(message), "{}", err);
+        }
+        assert!(Int(i64::MIN).neg().is_err());
+        let err = Boolean(true).neg().unwrap_err();
+        assert!(err.contains("Minus bool is not supported"), "{}", err);
+    }
+}
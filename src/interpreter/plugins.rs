@@ -0,0 +1,102 @@
+use crate::interpreter::interpreter::TypeVal;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Function implemented in Rust, called with the evaluated arguments of the call.
+pub type NativeFunction = Arc<dyn Fn(&[TypeVal]) -> Result<TypeVal, String> + Send + Sync>;
+
+/// Extension of the interpreter with native functions, registered with the builder.
+///
+/// Plugins are compiled in the program embedding the interpreter: a crate exposing bindings
+/// (graphics, databases...) implements the trait and the embedder registers it.
+pub trait GrimPlugin {
+    /// Name of the plugin, reported in the errors of its functions.
+    fn name(&self) -> &str;
+
+    /// Functions provided by the plugin, with the name scripts call them by.
+    fn functions(&self) -> Vec<(String, NativeFunction)>;
+}
+
+/// Native functions of the registered plugins, with the name of the plugin providing them.
+///
+/// A function declared by the program hides a native function, which hides a builtin.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    functions: HashMap<String, (String, NativeFunction)>,
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl Plugins {
+    /// Add the functions of a plugin, replacing the ones with the same name registered before.
+    pub fn register(&mut self, plugin: &dyn GrimPlugin) {
+        for (name, function) in plugin.functions() {
+            self.functions
+                .insert(name, (plugin.name().to_string(), function));
+        }
+    }
+
+    /// Names of the native functions, sorted.
+    pub fn names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        names
+    }
+
+    /// The native function with the given name and the plugin providing it.
+    pub fn get(&self, name: &str) -> Option<(String, NativeFunction)> {
+        self.functions.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::io::{SharedBuffer, WriteOutput};
+    use crate::language_runner::run_language::run_source;
+
+    struct Arithmetic;
+
+    impl GrimPlugin for Arithmetic {
+        fn name(&self) -> &str {
+            "arithmetic"
+        }
+
+        fn functions(&self) -> Vec<(String, NativeFunction)> {
+            let twice: NativeFunction = Arc::new(|arguments| match arguments {
+                [TypeVal::Int(x)] => Ok(TypeVal::Int(x * 2)),
+                _ => Err("twice expects an int".to_string()),
+            });
+            vec![("twice".to_string(), twice)]
+        }
+    }
+
+    #[test]
+    fn calls_native_functions() {
+        let output = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .plugin(Arithmetic)
+            .build();
+        run_source("printl(twice(21));", &mut interpreter).unwrap();
+        assert_eq!(output.contents(), "42\n");
+
+        let err = run_source("twice(true);", &mut interpreter).unwrap_err();
+        assert!(err.contains("arithmetic plugin"));
+        assert!(err.contains("twice expects an int"));
+
+        // A function of the program hides the native one
+        run_source(
+            "fn twice (x) -> { return 0; } printl(twice(1));",
+            &mut interpreter,
+        )
+        .unwrap();
+        assert_eq!(output.contents(), "42\n0\n");
+    }
+}
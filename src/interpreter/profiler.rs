@@ -0,0 +1,138 @@
+use crate::parsing::ast::{Statement, StatementKind};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Number of executions and cumulative time of a function or of a kind of statement.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Measure {
+    pub count: u64,
+    pub time: Duration,
+}
+
+/// Profiler of the execution.
+///
+/// Times are cumulative: they include the nested calls and statements, recursive calls and
+/// nested statements of the same kind are timed only once.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    pub functions: HashMap<String, Measure>,
+    pub statements: HashMap<&'static str, Measure>,
+    active_functions: Vec<(String, Instant)>,
+    active_statements: Vec<(&'static str, Instant)>,
+}
+
+/// Name of the kind of statement shown in the report.
+fn statement_kind(stmt: &Statement) -> &'static str {
+    match stmt.kind {
+        StatementKind::VariableDeclarationStatement { .. } => "let",
+        StatementKind::AssignmentStatement { .. } => "assignment",
+        StatementKind::DestructuringStatement { .. } => "destructuring",
+        StatementKind::IfStatement { .. } => "if",
+        StatementKind::IfElseStatement { .. } => "if-else",
+        StatementKind::WhileStatement { .. } => "while",
+        StatementKind::ForStatement { .. } => "for",
+        StatementKind::FunctionDeclaration { .. } => "fn",
+        StatementKind::FunctionCallStatement { .. } => "call",
+        StatementKind::ReturnStatement { .. } => "return",
+        StatementKind::SpawnStatement { .. } => "spawn",
+        StatementKind::BreakStatement { .. } => "break",
+        StatementKind::ContinueStatement { .. } => "continue",
+        StatementKind::PrintStatement { .. } => "print",
+        StatementKind::PrintLineStatement { .. } => "printl",
+        StatementKind::InputStatement { .. } => "input",
+        StatementKind::BreakpointStatement => "breakpoint",
+        StatementKind::IncludeStatement { .. } => "include",
+        StatementKind::MeasureStatement { .. } => "measure",
+    }
+}
+
+impl Profiler {
+    pub fn enter_function(&mut self, name: &str) {
+        self.functions.entry(name.to_string()).or_default().count += 1;
+        self.active_functions
+            .push((name.to_string(), Instant::now()));
+    }
+
+    pub fn exit_function(&mut self) {
+        if let Some((name, start)) = self.active_functions.pop() {
+            if self
+                .active_functions
+                .iter()
+                .all(|(active, _)| *active != name)
+            {
+                self.functions.entry(name).or_default().time += start.elapsed();
+            }
+        }
+    }
+
+    pub fn enter_statement(&mut self, stmt: &Statement) {
+        let kind = statement_kind(stmt);
+        self.statements.entry(kind).or_default().count += 1;
+        self.active_statements.push((kind, Instant::now()));
+    }
+
+    pub fn exit_statement(&mut self) {
+        if let Some((kind, start)) = self.active_statements.pop() {
+            if self
+                .active_statements
+                .iter()
+                .all(|(active, _)| *active != kind)
+            {
+                self.statements.entry(kind).or_default().time += start.elapsed();
+            }
+        }
+    }
+
+    /// Summary table with the slowest entries first.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&table("function", &self.functions));
+        out.push('\n');
+        let statements = self
+            .statements
+            .iter()
+            .map(|(kind, measure)| (kind.to_string(), *measure))
+            .collect();
+        out.push_str(&table("statement", &statements));
+        out
+    }
+}
+
+fn table(title: &str, measures: &HashMap<String, Measure>) -> String {
+    let mut rows: Vec<(&String, &Measure)> = measures.iter().collect();
+    rows.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+    let mut out = format!("{:<20} {:>10} {:>12}\n", title, "calls", "time (ms)");
+    for (name, measure) in rows {
+        out.push_str(&format!(
+            "{:<20} {:>10} {:>12.3}\n",
+            name,
+            measure.count,
+            measure.time.as_secs_f64() * 1000.0
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_recursive_calls_once_in_time() {
+        let mut profiler = Profiler::default();
+        profiler.enter_function("f");
+        profiler.enter_function("f");
+        std::thread::sleep(Duration::from_millis(2));
+        profiler.exit_function();
+        let inner = profiler.functions["f"].time;
+        profiler.exit_function();
+
+        let f = profiler.functions["f"];
+        assert_eq!(f.count, 2);
+        assert_eq!(inner, Duration::ZERO);
+        assert!(f.time >= Duration::from_millis(2));
+        assert!(profiler
+            .report()
+            .contains("f                             2"));
+    }
+}
@@ -0,0 +1,489 @@
+use crate::interpreter::builtins::random::Rng;
+use crate::interpreter::concurrency::{Printed, Threads, WAIT};
+use crate::interpreter::coverage::Coverage;
+use crate::interpreter::debugger::Debugger;
+use crate::interpreter::environment::Environment;
+use crate::interpreter::inspector::Inspector;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::interpreter::interpreter::FunctionDef;
+use crate::interpreter::interpreter::TypeVal;
+use crate::interpreter::io::{Input, Output, StdInput, WriteOutput};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::interpreter::jit::{Jit, MAX_NATIVE_DEPTH};
+use crate::interpreter::memory::Memory;
+use crate::interpreter::observer::Observer;
+use crate::interpreter::plugins::Plugins;
+use crate::interpreter::profiler::Profiler;
+use crate::interpreter::tracer::Tracer;
+use crate::parsing::ast::{Span, Statement};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// Capabilities given to the program, everything is allowed by default.
+///
+/// allow_input: the program can read with `input` and the read builtins.
+///
+/// allow_ffi: the program can call C functions with `ffi_call`, when built with the ffi feature.
+///
+/// allow_exec: the program can run other programs with `exec` and `exec_status`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sandbox {
+    pub allow_input: bool,
+    pub allow_ffi: bool,
+    pub allow_exec: bool,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox {
+            allow_input: true,
+            allow_ffi: true,
+            allow_exec: true,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Sandbox denying every capability.
+    pub fn strict() -> Self {
+        Sandbox {
+            allow_input: false,
+            allow_ffi: false,
+            allow_exec: false,
+        }
+    }
+
+    /// Whether the program can call the builtin, some reach outside of the interpreter.
+    pub fn allows(&self, builtin: &str) -> bool {
+        match builtin {
+            "ffi_call" => self.allow_ffi,
+            "exec" | "exec_status" => self.allow_exec,
+            "read_line" | "read_word" | "read_int" | "read_float" => self.allow_input,
+            _ => true,
+        }
+    }
+}
+
+/// State shared by every frame of a running program, it holds the tools observing the execution.
+///
+/// src: source code of the program, used to map statements to their lines.
+///
+/// line_starts: offsets in src where each line starts, set with src by set_source.
+///
+/// output, input: where the program prints and reads.
+///
+/// unread: rest of the line of the input whose first words were read by read_word.
+///
+/// error_output: where the program prints its diagnostics with eprint and eprintl, and where
+/// --trace prints the statements and the debugger the changes of the watched variables.
+///
+/// prelude: the builtin functions are available.
+///
+/// plugins: native functions registered by the program embedding the interpreter.
+///
+/// max_depth: maximum number of nested function calls.
+///
+/// calls: functions running, from the outermost, with the statement calling each one.
+///
+/// statement: statement running in the innermost function, or in the program.
+///
+/// memory: values bound to the variables of the program.
+///
+/// cancelled: set from any thread to stop the program before its next statement.
+///
+/// spawned: functions running on other threads, the program waits for them before ending.
+///
+/// printed: text printed by the spawned functions, written to the outputs by the thread that
+/// spawned them.
+///
+/// threads: threads of the program not waiting, a recv waited for by all of them fails.
+///
+/// strings: string literals already evaluated, shared by all the values created from them. Only
+/// the literals of the source code are kept, so the set is bounded by the size of the programs.
+///
+/// executed: what the last command run by exec_status printed on stdout and stderr.
+///
+/// random: generator of the numbers returned by random, seeded again with seed_random.
+///
+/// inspector: records the scope of the statement raising a runtime error, enabled with --inspect.
+///
+/// observers: hooks of the program embedding the interpreter.
+///
+/// watches: variables whose changes are reported to the observers.
+///
+/// jit: compiler of the hot functions to native code, enabled with --jit.
+#[derive(Debug)]
+pub struct Runtime {
+    pub src: String,
+    line_starts: Vec<usize>,
+    pub output: Box<dyn Output>,
+    pub error_output: Box<dyn Output>,
+    pub input: Box<dyn Input>,
+    pub unread: String,
+    pub sandbox: Sandbox,
+    pub prelude: bool,
+    pub plugins: Plugins,
+    pub max_depth: Option<usize>,
+    pub calls: Vec<(String, Span)>,
+    pub statement: Span,
+    pub memory: Memory,
+    pub cancelled: Option<Arc<AtomicBool>>,
+    pub spawned: Vec<JoinHandle<Result<(), String>>>,
+    pub printed: (Sender<Printed>, Receiver<Printed>),
+    pub threads: Threads,
+    pub strings: HashSet<Arc<str>>,
+    pub executed: (String, String),
+    pub random: Rng,
+    pub debugger: Option<Debugger>,
+    pub tracer: Option<Tracer>,
+    pub profiler: Option<Profiler>,
+    pub coverage: Option<Coverage>,
+    pub inspector: Option<Inspector>,
+    pub observers: Vec<Box<dyn Observer>>,
+    pub watches: HashSet<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub jit: Option<Jit>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Runtime {
+            src: String::new(),
+            line_starts: vec![0],
+            output: Box::new(WriteOutput(io::stdout())),
+            error_output: Box::new(WriteOutput(io::stderr())),
+            input: Box::new(StdInput),
+            unread: String::new(),
+            sandbox: Sandbox::default(),
+            prelude: true,
+            plugins: Plugins::default(),
+            max_depth: None,
+            calls: vec![],
+            statement: Span::default(),
+            memory: Memory::default(),
+            cancelled: None,
+            spawned: vec![],
+            printed: mpsc::channel(),
+            threads: Threads::default(),
+            strings: HashSet::new(),
+            executed: (String::new(), String::new()),
+            random: Rng::default(),
+            debugger: None,
+            tracer: None,
+            profiler: None,
+            coverage: None,
+            inspector: None,
+            observers: vec![],
+            watches: HashSet::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            jit: None,
+        }
+    }
+}
+
+impl Runtime {
+    /// Runtime of the program whose source code is src.
+    pub fn new(src: &str) -> Self {
+        let mut runtime = Runtime::default();
+        runtime.set_source(src);
+        runtime
+    }
+
+    /// Source code of the program, the offsets of its lines are computed once here.
+    pub fn set_source(&mut self, src: &str) {
+        self.src = src.to_string();
+        self.line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+    }
+
+    /// Line, from 1, where the span starts in the source code.
+    pub fn line(&self, span: Span) -> usize {
+        self.line_starts
+            .partition_point(|start| *start <= span.start)
+            .max(1)
+    }
+
+    /// Called right before a statement is executed in the given env.
+    pub fn before_statement(&mut self, stmt: &Statement, env: &Environment) -> Result<(), String> {
+        if self
+            .cancelled
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+        {
+            return Err("Execution cancelled".to_string());
+        }
+        self.statement = stmt.span;
+        if !self.spawned.is_empty() {
+            self.print_spawned();
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.mark(stmt);
+        }
+        if self.tracer.is_some() || self.debugger.is_some() {
+            self.before_statement_debugged(stmt, env)?;
+        }
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.enter_statement(stmt);
+        }
+        for observer in self.observers.iter_mut() {
+            observer.on_statement(stmt, env);
+        }
+        Ok(())
+    }
+
+    /// Report the statement to the tracer and the debugger, the only tools needing its line.
+    fn before_statement_debugged(
+        &mut self,
+        stmt: &Statement,
+        env: &Environment,
+    ) -> Result<(), String> {
+        let line = self.line(stmt.span);
+        if let Some(tracer) = self.tracer.as_mut() {
+            let traced = tracer.before_statement(stmt, line, &self.src, env);
+            self.trace(&traced)?;
+        }
+        if let Some(debugger) = self.debugger.as_mut() {
+            self.output.flush().map_err(|err| err.to_string())?;
+            debugger.before_statement(stmt, line, &self.src, env)?;
+        }
+        Ok(())
+    }
+
+    /// Calls leading to the running statement, from the innermost, with the line of the statement
+    /// running in each function when the source code is available.
+    pub fn stack_trace(&self) -> String {
+        let location = |span: Span| match self.src.is_empty() {
+            true => String::new(),
+            false => format!(" at line {}", self.line(span)),
+        };
+        let mut lines = vec![];
+        let mut statement = self.statement;
+        for (name, caller) in self.calls.iter().rev() {
+            lines.push(format!("  in ({}){}", name, location(statement)));
+            statement = *caller;
+        }
+        lines.push(format!("  in the program{}", location(statement)));
+        lines.join("\n")
+    }
+
+    /// Called right after a statement is executed successfully in the given env.
+    pub fn after_statement(&mut self, env: &Environment) -> Result<(), String> {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.exit_statement();
+        }
+        if let Some(tracer) = self.tracer.as_mut() {
+            for traced in tracer.after_statement(env) {
+                self.trace(&traced.cyan().to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Print a line of the trace after the text printed so far by the program.
+    fn trace(&mut self, traced: &str) -> Result<(), String> {
+        self.output
+            .flush()
+            .and_then(|_| {
+                self.error_output
+                    .print(&format!("{} {}\n", "[trace]".dimmed(), traced))
+            })
+            .map_err(|err| format!("Error during trace {}", err))
+    }
+
+    /// Called when a statement executed in the given env raised an error.
+    pub fn statement_failed(&mut self, stmt: &Statement, env: &Environment) {
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.statement_failed(stmt, &self.src, env);
+        }
+    }
+
+    /// Append the next line of the input to buf and return the number of bytes read, 0 at the end
+    /// of the input. The rest of a line whose first words were read comes first.
+    pub fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        match self.unread.is_empty() {
+            true => self.input.read_line(buf),
+            false => {
+                buf.push_str(&self.unread);
+                Ok(mem::take(&mut self.unread).len())
+            }
+        }
+    }
+
+    /// Shared copy of a string literal, allocated only the first time it is seen.
+    ///
+    /// The copies are kept as long as the runtime, the strings read or built by the program are
+    /// not interned.
+    pub fn intern(&mut self, string: &str) -> Arc<str> {
+        match self.strings.get(string) {
+            Some(interned) => Arc::clone(interned),
+            None => {
+                let interned: Arc<str> = Arc::from(string);
+                self.strings.insert(Arc::clone(&interned));
+                interned
+            }
+        }
+    }
+
+    /// Write what the spawned functions printed to the outputs of the runtime.
+    pub fn print_spawned(&mut self) {
+        while let Ok(printed) = self.printed.1.try_recv() {
+            self.print_printed(printed);
+        }
+    }
+
+    fn print_printed(&mut self, (text, error): Printed) {
+        let output = match error {
+            true => &mut self.error_output,
+            false => &mut self.output,
+        };
+        // As in the spawned function, the text is lost if it cannot be written
+        let _ = output.print(&text);
+    }
+
+    /// Wait for the spawned functions to end, failing with the first error they returned.
+    ///
+    /// What they print is written while waiting for them.
+    pub fn join_spawned(&mut self) -> Result<(), String> {
+        let mut result = Ok(());
+        let threads = self.threads.clone();
+        for handle in mem::take(&mut self.spawned) {
+            threads.wait(|| {
+                while !handle.is_finished() {
+                    if let Ok(printed) = self.printed.1.recv_timeout(WAIT) {
+                        self.print_printed(printed);
+                    }
+                }
+            });
+            self.print_spawned();
+            let joined = match handle.join() {
+                Ok(joined) => joined,
+                Err(_) => Err("The thread panicked".to_string()),
+            };
+            if let (Ok(_), Err(err)) = (&result, joined) {
+                result = Err(format!("Error in spawned function\n{}\n", err));
+            }
+        }
+        result
+    }
+
+    /// Called right before the body of a function is executed.
+    pub fn enter_function(&mut self, name: &str) -> Result<(), String> {
+        if self
+            .max_depth
+            .is_some_and(|max_depth| self.calls.len() >= max_depth)
+        {
+            return Err(format!(
+                "Function ({}) exceeds the maximum call depth of {}",
+                name,
+                self.calls.len()
+            ));
+        }
+        self.calls.push((name.to_string(), self.statement));
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.enter_function(name);
+        }
+        Ok(())
+    }
+
+    /// Run a call with the native code of the function, if the JIT compiled it.
+    ///
+    /// The tools observing the statements and the memory limits need the interpreter.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn call_native(
+        &mut self,
+        name: &str,
+        function: &Arc<FunctionDef>,
+        arguments: &[TypeVal],
+    ) -> Option<TypeVal> {
+        if self.debugger.is_some()
+            || self.tracer.is_some()
+            || self.profiler.is_some()
+            || self.coverage.is_some()
+            || !self.observers.is_empty()
+            || !self.watches.is_empty()
+            || self.memory.max_values.is_some()
+            || self.memory.max_bytes.is_some()
+        {
+            return None;
+        }
+        let depth = match self.max_depth {
+            Some(max_depth) => max_depth.saturating_sub(self.calls.len()) as i64,
+            None => MAX_NATIVE_DEPTH,
+        };
+        let cancelled = self.cancelled.as_deref();
+        self.jit
+            .as_mut()?
+            .call(name, function, arguments, depth, cancelled)
+    }
+
+    /// Called when a variable is bound to a value, reports the change if the variable is watched.
+    ///
+    /// old: the value of the variable, None when it is declared.
+    pub fn changed(
+        &mut self,
+        name: &str,
+        old: Option<&TypeVal>,
+        new: &TypeVal,
+    ) -> Result<(), String> {
+        let debugged = self
+            .debugger
+            .as_ref()
+            .is_some_and(|debugger| debugger.watches.contains(name));
+        if !debugged && !self.watches.contains(name) || old == Some(new) {
+            return Ok(());
+        }
+        let line = self.line(self.statement);
+        if debugged {
+            let change = match old {
+                Some(old) => format!(
+                    "`{}` changed from {} to {} at line {}",
+                    name, old, new, line
+                ),
+                None => format!("`{}` declared as {} at line {}", name, new, line),
+            };
+            self.output
+                .flush()
+                .and_then(|_| self.error_output.print(&format!("{}\n", change.cyan())))
+                .map_err(|err| format!("Error during watch {}", err))?;
+        }
+        if self.watches.contains(name) {
+            for observer in self.observers.iter_mut() {
+                observer.on_watch(name, old, new, line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Called right after the body of a function is executed.
+    pub fn exit_function(&mut self) {
+        if let Some((_, caller)) = self.calls.pop() {
+            self.statement = caller;
+        }
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.exit_function();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_line_of_a_span() {
+        let src = "let a = 1;\n\nprintl(a);\n";
+        let runtime = Runtime::new(src);
+        for start in [0, 10, 11, 12, src.len()] {
+            let span = Span { start, end: start };
+            assert_eq!(runtime.line(span), span.line_and_column(src).0);
+        }
+        assert_eq!(Runtime::default().line(Span::default()), 1);
+    }
+}
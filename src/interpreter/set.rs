@@ -0,0 +1,154 @@
+use crate::interpreter::interpreter::TypeVal;
+use crate::parsing::lexer::{quote_char, quote_string};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Value held by a set, only the values compared exactly can be elements.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Element {
+    Bool(bool),
+    Int(i64),
+    Char(char),
+    Str(Arc<str>),
+}
+
+impl Element {
+    fn of(value: &TypeVal) -> Result<Element, String> {
+        match value {
+            TypeVal::Boolean(x) => Ok(Element::Bool(*x)),
+            TypeVal::Int(x) => Ok(Element::Int(*x)),
+            TypeVal::Char(x) => Ok(Element::Char(*x)),
+            TypeVal::Str(x) => Ok(Element::Str(Arc::clone(x))),
+            _ => Err(format!(
+                "A set holds bools, ints, chars and strs, not {}",
+                value.describe()
+            )),
+        }
+    }
+
+    fn value(&self) -> TypeVal {
+        match self {
+            Element::Bool(x) => TypeVal::Boolean(*x),
+            Element::Int(x) => TypeVal::Int(*x),
+            Element::Char(x) => TypeVal::Char(*x),
+            Element::Str(x) => TypeVal::Str(Arc::clone(x)),
+        }
+    }
+}
+
+/// Set of values shared by all its copies, adding to a copy adds to every one of them.
+///
+/// The elements are kept sorted, so a set is printed in the same order on every run.
+#[derive(Debug, Clone, Default)]
+pub struct Set(Arc<Mutex<BTreeSet<Element>>>);
+
+impl PartialEq for Set {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || *self.elements() == *other.elements()
+    }
+}
+
+impl Set {
+    fn new(elements: BTreeSet<Element>) -> Set {
+        Set(Arc::new(Mutex::new(elements)))
+    }
+
+    fn elements(&self) -> MutexGuard<'_, BTreeSet<Element>> {
+        // A thread panicking while holding the lock leaves the elements as they were
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Add a value, false if the set already held it.
+    pub fn add(&self, value: &TypeVal) -> Result<bool, String> {
+        let element = Element::of(value)?;
+        Ok(self.elements().insert(element))
+    }
+
+    pub fn contains(&self, value: &TypeVal) -> Result<bool, String> {
+        let element = Element::of(value)?;
+        Ok(self.elements().contains(&element))
+    }
+
+    /// New set with the same elements, not shared with this one.
+    pub fn copy(&self) -> Set {
+        Set::new(self.elements().clone())
+    }
+
+    /// New set with the elements of both sets.
+    pub fn union(&self, other: &Set) -> Set {
+        let rhs = other.elements().clone();
+        Set::new(
+            self.elements()
+                .union(&rhs)
+                .cloned()
+                .collect::<BTreeSet<_>>(),
+        )
+    }
+
+    /// New set with the elements in both sets.
+    pub fn intersect(&self, other: &Set) -> Set {
+        let rhs = other.elements().clone();
+        Set::new(
+            self.elements()
+                .intersection(&rhs)
+                .cloned()
+                .collect::<BTreeSet<_>>(),
+        )
+    }
+
+    /// New set with the elements of this set that are not in the other one.
+    pub fn difference(&self, other: &Set) -> Set {
+        let rhs = other.elements().clone();
+        Set::new(
+            self.elements()
+                .difference(&rhs)
+                .cloned()
+                .collect::<BTreeSet<_>>(),
+        )
+    }
+}
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elements: Vec<String> = self
+            .elements()
+            .iter()
+            .map(|element| match element.value() {
+                value @ TypeVal::Str(_) => quote_string(&value.to_string()),
+                TypeVal::Char(x) => quote_char(x),
+                value => value.to_string(),
+            })
+            .collect();
+        write!(f, "{{{}}}", elements.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_sets() {
+        let a = Set::default();
+        let b = Set::default();
+        for x in [1, 2, 3] {
+            assert_eq!(a.add(&TypeVal::Int(x)), Ok(true));
+        }
+        assert_eq!(a.add(&TypeVal::Int(1)), Ok(false));
+        b.add(&TypeVal::Int(3)).unwrap();
+        b.add(&TypeVal::from("three")).unwrap();
+        assert_eq!(a.contains(&TypeVal::Int(2)), Ok(true));
+        assert!(a.add(&TypeVal::Float(1.5)).is_err());
+        assert_eq!(a.union(&b).to_string(), "{1, 2, 3, \"three\"}");
+        assert_eq!(a.intersect(&b).to_string(), "{3}");
+        assert_eq!(a.difference(&b).to_string(), "{1, 2}");
+        assert_eq!(a.union(&a), a);
+        let shared = a.clone();
+        shared.add(&TypeVal::Int(4)).unwrap();
+        assert_eq!(a.contains(&TypeVal::Int(4)), Ok(true));
+        let copy = a.copy();
+        copy.add(&TypeVal::Int(5)).unwrap();
+        assert_eq!(a.contains(&TypeVal::Int(5)), Ok(false));
+    }
+}
@@ -0,0 +1,79 @@
+use crate::interpreter::environment::Environment;
+use crate::interpreter::interpreter::TypeVal;
+use crate::parsing::ast::{Statement, StatementKind};
+use std::collections::BTreeMap;
+
+/// Execution trace, it describes every statement executed and the bindings it changed.
+///
+/// snapshots: variables seen before each statement being executed, None for the statements
+/// containing blocks since their changes are traced by the inner statements.
+#[derive(Debug, Default)]
+pub struct Tracer {
+    snapshots: Vec<Option<BTreeMap<String, TypeVal>>>,
+}
+
+impl Tracer {
+    /// Line describing the statement about to be executed.
+    pub fn before_statement(
+        &mut self,
+        stmt: &Statement,
+        line: usize,
+        src: &str,
+        env: &Environment,
+    ) -> String {
+        let has_blocks = matches!(
+            stmt.kind,
+            StatementKind::IfStatement { .. }
+                | StatementKind::IfElseStatement { .. }
+                | StatementKind::WhileStatement { .. }
+                | StatementKind::FunctionDeclaration { .. }
+                | StatementKind::MeasureStatement { .. }
+        );
+        self.snapshots
+            .push((!has_blocks).then(|| env.visible_variables()));
+        let text = src
+            .get(stmt.span.start..stmt.span.end)
+            .and_then(|text| text.lines().next())
+            .unwrap_or_default();
+        format!("line {}: {}", line, text.trim())
+    }
+
+    /// Lines describing the bindings changed by the statement just executed.
+    pub fn after_statement(&mut self, env: &Environment) -> Vec<String> {
+        let Some(Some(before)) = self.snapshots.pop() else {
+            return vec![];
+        };
+        env.visible_variables()
+            .into_iter()
+            .filter(|(name, value)| before.get(name) != Some(value))
+            .map(|(name, value)| format!("    {} = {}", name, value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    #[test]
+    fn traces_changed_bindings() {
+        let src = "let a = 1;\nwhile a < 3 {\n    a = a + 1;\n}";
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        let mut tracer = Tracer::default();
+        let mut env = Environment::default();
+        env.insert_value("a", &TypeVal::Int(1)).unwrap();
+
+        assert_eq!(
+            tracer.before_statement(&ast[1], 2, src, &env),
+            "line 2: while a < 3 {"
+        );
+        env.update_value("a", &TypeVal::Int(3)).unwrap();
+        assert!(tracer.after_statement(&env).is_empty());
+
+        tracer.before_statement(&ast[0], 1, src, &env);
+        env.update_value("a", &TypeVal::Int(4)).unwrap();
+        assert_eq!(tracer.after_statement(&env), vec!["    a = 4"]);
+    }
+}
@@ -1 +1,9 @@
+pub mod compiled;
+pub mod doc_file;
+pub mod format_file;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod repl;
+pub mod run_examples;
 pub mod run_language;
+pub mod run_tests;
+pub mod transpile_file;
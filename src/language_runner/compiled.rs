@@ -0,0 +1,123 @@
+use crate::analysis::optimizer::optimize;
+use crate::language_runner::run_language::{
+    check_program, execute_program, parse_file, RunOptions,
+};
+use crate::parsing::ast::Statement;
+use colored::Colorize;
+use std::fs;
+
+/// First bytes of every compiled program.
+pub const MAGIC: &[u8; 5] = b"GRIMC";
+
+/// Version of the layout of compiled programs, increased whenever the AST changes.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Serialize a checked program: the magic bytes, the format version and the statements.
+pub fn encode_program(tree: &Vec<Statement>) -> Result<Vec<u8>, String> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    let program = bincode::serialize(tree).map_err(|err| err.to_string())?;
+    bytes.extend(program);
+    Ok(bytes)
+}
+
+/// Read back a program written by `encode_program` with the same format version.
+pub fn decode_program(bytes: &[u8]) -> Result<Vec<Statement>, String> {
+    let Some(header) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        return Err("Not a compiled Grim program".to_string());
+    };
+    let (version, program) = header.split_at(header.len().min(4));
+    let version = match <[u8; 4]>::try_from(version) {
+        Ok(version) => u32::from_le_bytes(version),
+        Err(_) => return Err("The compiled program is truncated".to_string()),
+    };
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "The program was compiled with format version {}, this interpreter reads version {}",
+            version, FORMAT_VERSION
+        ));
+    }
+    bincode::deserialize(program)
+        .map_err(|err| format!("The compiled program is corrupted: {}", err))
+}
+
+/// Check the program read from path and write it compiled to output.
+pub fn build_program(path: &str, src: &str, output: &str, options: &RunOptions) -> i32 {
+    let ast = match parse_file(path, src) {
+        Ok(ast) => ast,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("{}", err);
+            return 1;
+        }
+    };
+    if !check_program(&ast, src, options) {
+        return 1;
+    }
+    let ast = match options.optimize {
+        true => optimize(&ast),
+        false => ast,
+    };
+    match encode_program(&ast)
+        .and_then(|bytes| fs::write(output, bytes).map_err(|err| err.to_string()))
+    {
+        Ok(_) => 0,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("Cannot write {}\n{}", output, err);
+            1
+        }
+    }
+}
+
+/// Run a compiled program.
+///
+/// The source code is not shipped, so the tools reporting source lines are not available.
+pub fn run_compiled(path: &str, options: &RunOptions) -> i32 {
+    if options.debug || options.trace || options.coverage {
+        eprintln!(
+            "{}",
+            "ERROR!\n--debug, --trace and --coverage need the source code of the program"
+                .bright_red()
+        );
+        return 1;
+    }
+    let ast = match fs::read(path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| decode_program(&bytes))
+    {
+        Ok(ast) => ast,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("Cannot load {}\n{}", path, err);
+            return 1;
+        }
+    };
+    println!("Hi! \nGrim language interpreter started!\n");
+    execute_program(ast, path, "", options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    #[test]
+    fn round_trips_and_checks_the_version() {
+        let ast = ProgramParser::new()
+            .parse(Lexer::new(
+                "fn f (a: int) -> float { return a * 1.5; } printl(f(2));",
+            ))
+            .unwrap();
+        let mut bytes = encode_program(&ast).unwrap();
+        assert_eq!(decode_program(&bytes), Ok(ast));
+
+        bytes[MAGIC.len()] += 1;
+        assert!(decode_program(&bytes)
+            .unwrap_err()
+            .contains(&format!("format version {}", FORMAT_VERSION + 1)));
+        assert!(decode_program(b"let a = 1;").is_err());
+        assert!(decode_program(b"GRIMC").is_err());
+    }
+}
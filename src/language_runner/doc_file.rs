@@ -0,0 +1,135 @@
+use crate::parsing::ast::{Statement, StatementKind};
+use crate::parsing::parse_program;
+use crate::parsing::printer::print_signature;
+use colored::Colorize;
+use std::fs;
+
+/// Formats in which the documentation can be rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+/// Signature and documentation of a function declared at the top level.
+struct FunctionDoc {
+    name: String,
+    signature: String,
+    doc: String,
+}
+
+fn collect_functions(tree: &[Statement]) -> Vec<FunctionDoc> {
+    tree.iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                return_type,
+                doc,
+                ..
+            } => Some(FunctionDoc {
+                name: name.clone(),
+                signature: print_signature(name, arguments, return_type),
+                doc: doc.clone().unwrap_or_default(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render the documentation of the functions declared at the top level of a program.
+pub fn render_docs(tree: &[Statement], title: &str, format: DocFormat) -> String {
+    let functions = collect_functions(tree);
+    match format {
+        DocFormat::Markdown => {
+            let mut out = format!("# {}\n", title);
+            for function in functions {
+                out.push_str(&format!("\n## `{}`\n", function.signature));
+                if !function.doc.trim().is_empty() {
+                    out.push_str(&format!("\n{}\n", function.doc.trim_end()));
+                }
+            }
+            out
+        }
+        DocFormat::Html => {
+            let mut out = format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                 <title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+                escape_html(title)
+            );
+            for function in functions {
+                out.push_str(&format!(
+                    "<h2 id=\"{}\"><code>{}</code></h2>\n",
+                    function.name,
+                    escape_html(&function.signature)
+                ));
+                // Blank lines separate the paragraphs
+                for paragraph in function.doc.split("\n\n") {
+                    if !paragraph.trim().is_empty() {
+                        out.push_str(&format!("<p>{}</p>\n", escape_html(paragraph.trim())));
+                    }
+                }
+            }
+            out.push_str("</body>\n</html>\n");
+            out
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Print the documentation of the program read from path, or write it to output if given.
+pub fn doc_file(path: &str, src: &str, format: DocFormat, output: Option<&str>) -> i32 {
+    let ast = match parse_program(src) {
+        Ok(ast) => ast,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("{}", err);
+            return 1;
+        }
+    };
+    let title = path.rsplit('/').next().unwrap_or(path);
+    let docs = render_docs(&ast, title, format);
+    let written = match output {
+        Some(output) => fs::write(output, docs).map_err(|err| err.to_string()),
+        None => {
+            print!("{}", docs);
+            Ok(())
+        }
+    };
+    match written {
+        Ok(_) => 0,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("Cannot write the documentation\n{}", err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_documented_functions() {
+        let src = "## Square of a number.\n##\n## Works with ints.\nfn square (x: int) -> int { \
+                   fn inner () -> { } return x * x; }\n## not attached\nlet a = 1;\nfn bare (a, b) -> { }";
+        let ast = parse_program(src).unwrap();
+        assert_eq!(
+            render_docs(&ast, "lib.grim", DocFormat::Markdown),
+            "# lib.grim\n\n## `fn square (x: int) -> int`\n\nSquare of a number.\n\n\
+             Works with ints.\n\n## `fn bare (a, b) ->`\n"
+        );
+        let html = render_docs(&ast, "lib.grim", DocFormat::Html);
+        assert!(html.contains(
+            "<h2 id=\"square\"><code>fn square (x: int) -&gt; int</code></h2>\n\
+             <p>Square of a number.</p>\n<p>Works with ints.</p>\n"
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::parsing::printer::format_source;
+use colored::Colorize;
+use std::fs::{read_to_string, write};
+
+/// Format a source file in place and return the exit code of the process.
+///
+/// check: only report if the file is not formatted, without rewriting it.
+pub fn format_file(path: &str, check: bool) -> i32 {
+    let src = match read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("ERROR!\nCannot read {}: {}", path, err).bright_red()
+            );
+            return 1;
+        }
+    };
+    let formatted = match format_source(&src) {
+        Ok(formatted) => formatted,
+        Err(err) => {
+            eprintln!("{}", format!("ERROR!\n{}", err).bright_red());
+            return 1;
+        }
+    };
+
+    if formatted == src {
+        return 0;
+    }
+    if check {
+        eprintln!("{}", format!("{} is not formatted", path).yellow());
+        return 1;
+    }
+    if let Err(err) = write(path, formatted) {
+        eprintln!(
+            "{}",
+            format!("ERROR!\nCannot write {}: {}", path, err).bright_red()
+        );
+        return 1;
+    }
+    0
+}
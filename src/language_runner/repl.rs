@@ -0,0 +1,384 @@
+use crate::analysis::resolver::resolve_names_after;
+use crate::analysis::type_checker::infer_type;
+use crate::interpreter::interpreter::{Interpreter, TypeVal};
+use crate::language_runner::run_language::parse_file;
+use crate::parsing::ast::Statement;
+use crate::parsing::include::include_files;
+use crate::parsing::lexer::{Lexer, Token};
+use crate::parsing::printer::{format_source, print_signature};
+use crate::parsing::{parse_expression, parse_program};
+use colored::Colorize;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Editor, Helper};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File keeping the inputs of the previous sessions, in the home directory.
+const HISTORY_FILE: &str = ".grim_history";
+
+const HELP: &str = "Commands:\n  \
+    :vars          show the variables of the session\n  \
+    :funcs         show the functions of the session\n  \
+    :ast EXPR      show the syntax tree of an expression\n  \
+    :type EXPR     show the type of an expression, without evaluating it\n  \
+    :load PATH     run a script in the session\n  \
+    :save PATH     write the inputs that ran without errors as a script\n  \
+    :reset         forget every declaration\n  \
+    :quit          end the session";
+
+/// Braces opened by the input and not closed yet, the lexical errors are left to the parser.
+fn open_braces(input: &str) -> i64 {
+    let mut depth = 0;
+    for token in Lexer::new(input) {
+        match token {
+            Ok((_, Token::TokLbrace, _)) => depth += 1,
+            Ok((_, Token::TokRbrace, _)) => depth -= 1,
+            Ok(_) => {}
+            Err(_) => return 0,
+        }
+    }
+    depth
+}
+
+/// Editor helper continuing the input on a new line while a block is open.
+struct GrimHelper;
+
+impl Completer for GrimHelper {
+    type Candidate = String;
+}
+
+impl Hinter for GrimHelper {
+    type Hint = String;
+}
+
+impl Highlighter for GrimHelper {}
+
+impl Validator for GrimHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match open_braces(ctx.input()) > 0 {
+            true => Ok(ValidationResult::Incomplete),
+            false => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
+impl Helper for GrimHelper {}
+
+/// Interactive session, the declarations of an input are seen by the next ones.
+///
+/// inputs: the statements that ran without errors, loaded scripts included.
+pub struct Session {
+    interpreter: Interpreter,
+    inputs: Vec<String>,
+}
+
+impl Session {
+    pub fn new(interpreter: Interpreter) -> Self {
+        Session {
+            interpreter,
+            inputs: vec![],
+        }
+    }
+
+    /// Run the statements of an input, or evaluate it as an expression and return its value.
+    pub fn evaluate(&mut self, input: &str) -> Result<Option<TypeVal>, String> {
+        let ast = match parse_program(input) {
+            Ok(ast) => ast,
+            Err(err) => {
+                // The statement error is clearer when the input is not an expression either
+                let Ok(expr) = parse_expression(input) else {
+                    return Err(err);
+                };
+                let value = self.interpreter.eval_expr(&expr);
+                // The text printed while evaluating it comes before the value
+                if let Err(err) = self.interpreter.runtime.borrow_mut().output.flush() {
+                    return Err(format!("Error while flushing the output {}", err));
+                }
+                return value.map(Some);
+            }
+        };
+        // The files included by an input are relative to the current directory
+        let mut ast = include_files(ast, Path::new(""))?;
+        self.run_statements(&mut ast, input).map(|_| None)
+    }
+
+    /// Resolve and run the statements of an input, keeping it if it ran without errors.
+    fn run_statements(&mut self, ast: &mut Vec<Statement>, input: &str) -> Result<(), String> {
+        let mut functions: Vec<String> = self
+            .interpreter
+            .runtime()
+            .plugins
+            .names()
+            .into_iter()
+            .cloned()
+            .collect();
+        functions.extend(self.interpreter.env.global_functions().into_keys());
+        let resolution_errors = resolve_names_after(
+            ast,
+            input,
+            functions.into_iter().collect(),
+            self.interpreter.env.global_variables(),
+        );
+        if !resolution_errors.is_empty() {
+            return Err(resolution_errors.join("\n"));
+        }
+        self.interpreter.run(ast)?;
+        self.inputs.push(input.trim().to_string());
+        Ok(())
+    }
+
+    /// Run the script at path in the session.
+    fn load(&mut self, path: &str) -> Result<(), String> {
+        let src =
+            fs::read_to_string(path).map_err(|err| format!("Cannot read {} {}", path, err))?;
+        let mut ast = parse_file(path, &src)?;
+        self.run_statements(&mut ast, &src)
+    }
+
+    /// Write the inputs kept by the session to path, formatted when they can be parsed together.
+    fn save(&self, path: &str) -> Result<(), String> {
+        let src = self.inputs.join("\n") + "\n";
+        let src = format_source(&src).unwrap_or(src);
+        fs::write(path, src).map_err(|err| format!("Cannot write {} {}", path, err))
+    }
+
+    /// Answer a command starting with a colon, None ends the session.
+    pub fn run_command(&mut self, command: &str) -> Option<String> {
+        let (name, argument) = command
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((command.trim(), ""));
+        let env = &mut self.interpreter.env;
+        match (name, argument.trim()) {
+            (":quit" | ":q", _) => None,
+            (":vars", "") => Some(
+                env.visible_variables()
+                    .iter()
+                    .map(|(name, value)| format!("{} = {} ({})", name, value, value.get_type()))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            (":funcs", "") => {
+                let mut functions: Vec<String> = env
+                    .global_functions()
+                    .iter()
+                    .map(|(name, function)| {
+                        print_signature(name, &function.arguments, &function.return_type)
+                    })
+                    .collect();
+                functions.sort();
+                Some(functions.join("\n"))
+            }
+            (":ast", expr) if !expr.is_empty() => Some(match parse_expression(expr) {
+                Ok(expr) => serde_json::to_string_pretty(&expr).unwrap(),
+                Err(err) => err,
+            }),
+            (":type", expr) if !expr.is_empty() => {
+                let expr = match parse_expression(expr) {
+                    Ok(expr) => expr,
+                    Err(err) => return Some(err),
+                };
+                let variables = env
+                    .visible_variables()
+                    .into_iter()
+                    .map(|(name, value)| (name, value.get_type()))
+                    .collect();
+                let functions = env
+                    .global_functions()
+                    .into_iter()
+                    .map(|(name, function)| {
+                        (name, (function.arguments.clone(), function.return_type))
+                    })
+                    .collect();
+                Some(match infer_type(&expr, variables, functions) {
+                    Ok(Some(inferred)) => inferred.to_string(),
+                    Ok(None) => "known only at runtime".to_string(),
+                    Err(errors) => errors.join("\n"),
+                })
+            }
+            (":load", path) if !path.is_empty() => Some(self.load(path).err().unwrap_or_default()),
+            (":save", path) if !path.is_empty() => Some(self.save(path).err().unwrap_or_default()),
+            (":reset", "") => {
+                env.reset();
+                self.inputs.clear();
+                Some(String::new())
+            }
+            _ => Some(HELP.to_string()),
+        }
+    }
+}
+
+/// Read the inputs of the user until the end of the input or a call to exit, and return the exit
+/// code of the process.
+///
+/// The history is saved in the home directory, an input goes on while its braces are unbalanced.
+pub fn run_repl() -> i32 {
+    let mut editor: Editor<GrimHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("ERROR!\nCannot start the line editor {}", err).bright_red()
+            );
+            return 1;
+        }
+    };
+    editor.set_helper(Some(GrimHelper));
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(history) = &history {
+        // There is no history before the first session
+        let _ = editor.load_history(history);
+    }
+
+    println!("Hi! \nGrim language interpreter started!\n");
+    let mut session = Session::new(Interpreter::builder().build());
+    let mut exit_code = 0;
+    loop {
+        let mut input = match editor.readline("grim> ") {
+            Ok(input) => input,
+            // Ctrl-C drops the current input, Ctrl-D ends the session
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("{}", format!("ERROR!\n{}", err).bright_red());
+                break;
+            }
+        };
+        // Without a terminal the lines are read one by one, the helper does not join them
+        while open_braces(&input) > 0 {
+            match editor.readline("... ") {
+                Ok(line) => input = format!("{}\n{}", input, line),
+                Err(_) => break,
+            }
+        }
+        if input.trim().is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input.as_str());
+        if input.trim_start().starts_with(':') {
+            match session.run_command(&input) {
+                Some(text) if text.is_empty() => {}
+                Some(text) => println!("{}", text),
+                None => break,
+            }
+            continue;
+        }
+        let evaluated = session.evaluate(&input);
+        if let Some(code) = session.interpreter.exit_code() {
+            exit_code = code;
+            break;
+        }
+        match evaluated {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => {}
+            Err(err) => {
+                println!("{}", "ERROR!".bright_red().bold());
+                println!("{}", err);
+            }
+        }
+    }
+    if let Some(history) = &history {
+        if let Err(err) = editor.save_history(history) {
+            eprintln!(
+                "{}",
+                format!("ERROR!\nCannot save the history {}", err).bright_red()
+            );
+        }
+    }
+    println!("\nGoodbye =)");
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::io::{SharedBuffer, WriteOutput};
+
+    #[test]
+    fn keeps_the_declarations_between_inputs() {
+        let output = SharedBuffer::default();
+        let mut session = Session::new(
+            Interpreter::builder()
+                .output(WriteOutput(output.clone()))
+                .build(),
+        );
+        assert_eq!(session.evaluate("let mut a = 2;"), Ok(None));
+        assert_eq!(
+            session.evaluate("fn twice (x) -> { return x * 2; }"),
+            Ok(None)
+        );
+        assert_eq!(session.evaluate("a = twice(a); printl(a);"), Ok(None));
+        assert_eq!(output.contents(), "4\n");
+        assert_eq!(session.evaluate("a + 1"), Ok(Some(TypeVal::Int(5))));
+        assert!(session.evaluate("printl(b);").is_err());
+        assert!(session.evaluate("let c = 1; c = 2;").is_err());
+        assert_eq!(open_braces("while a < 3 {\n"), 1);
+        assert_eq!(open_braces("printl(\"{\");"), 0);
+    }
+
+    #[test]
+    fn answers_meta_commands() {
+        let mut session = Session::new(Interpreter::builder().build());
+        session
+            .evaluate("let mut a = 2; fn half (x: int) -> float { return x / 2; }")
+            .unwrap();
+        assert_eq!(
+            session.run_command(":vars"),
+            Some("a = 2 (int)".to_string())
+        );
+        assert_eq!(
+            session.run_command(":funcs"),
+            Some("fn half (x: int) -> float".to_string())
+        );
+        assert_eq!(
+            session.run_command(":type half(a) * 2"),
+            Some("float".to_string())
+        );
+        assert!(session
+            .run_command(":type a + true")
+            .unwrap()
+            .contains("incompatible types int and bool"));
+        assert!(session.run_command(":ast -a").unwrap().contains("Minus"));
+        assert_eq!(session.run_command(":reset"), Some(String::new()));
+        assert_eq!(session.run_command(":vars"), Some(String::new()));
+        assert!(session.evaluate("a").is_err());
+        assert_eq!(session.run_command(":help"), Some(HELP.to_string()));
+        assert_eq!(session.run_command(":quit"), None);
+    }
+
+    #[test]
+    fn loads_and_saves_scripts() {
+        let dir = env::temp_dir();
+        let script = dir.join(format!("grim_repl_load_{}.grim", std::process::id()));
+        let saved = dir.join(format!("grim_repl_save_{}.grim", std::process::id()));
+        fs::write(&script, "fn twice(x)->{return x*2;}\n").unwrap();
+        let mut session = Session::new(Interpreter::builder().build());
+        assert_eq!(
+            session.run_command(&format!(":load {}", script.display())),
+            Some(String::new())
+        );
+        session.evaluate("let a = twice(2);").unwrap();
+        assert!(session.evaluate("let b = a + true;").is_err());
+        session.evaluate("a").unwrap();
+        assert_eq!(
+            session.run_command(&format!(":save {}", saved.display())),
+            Some(String::new())
+        );
+        assert_eq!(
+            fs::read_to_string(&saved).unwrap(),
+            "fn twice (x) -> {\n    return x * 2;\n}\n\nlet a = twice(2);\n"
+        );
+        assert!(session
+            .run_command(":load no_such_script.grim")
+            .unwrap()
+            .starts_with("Cannot read no_such_script.grim"));
+        fs::remove_file(script).unwrap();
+        fs::remove_file(saved).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use colored::Colorize;
+use std::env::current_exe;
+use std::fs::{read_dir, read_to_string};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Run every `.grim` file of a directory having a paired `.out` file and compare the output.
+///
+/// The content of the paired `.in` file, if any, is given as input.
+/// Return the exit code of the process.
+pub fn run_examples(dir: &str) -> i32 {
+    let mut scripts: Vec<PathBuf> = match read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "grim"))
+            .collect(),
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("ERROR!\nCannot read {}: {}", dir, err).bright_red()
+            );
+            return 1;
+        }
+    };
+    scripts.sort();
+
+    let mut failed = 0;
+    let mut passed = 0;
+    for script in &scripts {
+        let Ok(expected) = read_to_string(script.with_extension("out")) else {
+            println!(
+                "{} ... {}",
+                script.display(),
+                "skipped, no .out file".yellow()
+            );
+            continue;
+        };
+        let input = read_to_string(script.with_extension("in")).unwrap_or_default();
+        let difference = run_script(script, &input).and_then(|found| {
+            match first_difference(&expected, &found) {
+                Some(difference) => Err(difference),
+                None => Ok(()),
+            }
+        });
+        match difference {
+            Ok(_) => {
+                passed += 1;
+                println!("{} ... {}", script.display(), "ok".green());
+            }
+            Err(difference) => {
+                failed += 1;
+                println!("{} ... {}", script.display(), "FAILED".bright_red());
+                println!("{}", difference);
+            }
+        }
+    }
+
+    println!("\n{} passed; {} failed", passed, failed);
+    match failed {
+        0 => 0,
+        _ => 1,
+    }
+}
+
+/// Run a script with a new interpreter process and return what it printed on stdout.
+fn run_script(script: &Path, input: &str) -> Result<String, String> {
+    let interpreter = current_exe().map_err(|err| err.to_string())?;
+    let mut child = Command::new(interpreter)
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Describe the first line where the output differs from the expected one.
+fn first_difference(expected: &str, found: &str) -> Option<String> {
+    let mut expected_lines = expected.lines();
+    let mut found_lines = found.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), found_lines.next()) {
+            (None, None) => return None,
+            (expected, found) if expected == found => line += 1,
+            (expected, found) => {
+                return Some(format!(
+                    "  line {}\n  expected: {}\n  found:    {}",
+                    line,
+                    expected.unwrap_or("<end of output>"),
+                    found.unwrap_or("<end of output>")
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_first_difference() {
+        assert_eq!(first_difference("a\nb\n", "a\nb"), None);
+        assert_eq!(
+            first_difference("a\nb\nc", "a\nx\nc"),
+            Some("  line 2\n  expected: b\n  found:    x".to_string())
+        );
+        assert_eq!(
+            first_difference("a", "a\nb"),
+            Some("  line 2\n  expected: <end of output>\n  found:    b".to_string())
+        );
+    }
+}
@@ -1,21 +1,284 @@
-use crate::interpreter::interpreter::boot_interpreter;
-use crate::parsing::grammar::ProgramParser;
-use crate::parsing::lexer::Lexer;
+use crate::analysis::optimizer::optimize;
+use crate::analysis::resolver::{resolve_names, resolve_names_with};
+use crate::analysis::type_checker::check_types;
+use crate::analysis::warnings::{collect_warnings, Warning};
+use crate::interpreter::coverage::Coverage;
+use crate::interpreter::debugger::Debugger;
+use crate::interpreter::error_reporting::ColorChoice;
+use crate::interpreter::inspector::Inspector;
+use crate::interpreter::interpreter::{Interpreter, TypeVal};
+use crate::interpreter::profiler::Profiler;
+use crate::interpreter::tracer::Tracer;
+use crate::parsing::ast::Statement;
+use crate::parsing::include::include_files;
+use crate::parsing::parse_program;
+use crate::parsing::printer::print_program;
 use colored::Colorize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Formats in which the AST can be emitted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstFormat {
+    Json,
+}
+
+/// Options given on the command line.
+///
+/// breakpoints: lines where the debugger pauses.
+///
+/// color: when the messages are colored, applied with set_color before running anything.
+///
+/// entry: function called once the top-level statements ran, with the program arguments as strings.
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    pub color: ColorChoice,
+    pub deny_warnings: bool,
+    pub typecheck: bool,
+    pub optimize: bool,
+    pub jit: bool,
+    pub dump_ast: bool,
+    pub emit_ast: Option<AstFormat>,
+    pub debug: bool,
+    pub trace: bool,
+    pub profile: bool,
+    pub coverage: bool,
+    pub inspect: bool,
+    pub breakpoints: HashSet<usize>,
+    pub entry: Option<String>,
+    pub program_arguments: Vec<String>,
+}
+
+/// Check and run a program with the given interpreter, printing only what the program prints.
+pub fn run_source(src: &str, interpreter: &mut Interpreter) -> Result<(), String> {
+    let mut ast = parse_program(src)?;
+    let natives = interpreter
+        .runtime()
+        .plugins
+        .names()
+        .into_iter()
+        .cloned()
+        .collect();
+    let resolution_errors = resolve_names_with(&ast, src, natives);
+    if !resolution_errors.is_empty() {
+        return Err(resolution_errors.join("\n"));
+    }
+    interpreter.run(&mut ast)
+}
+
+/// Parse the program read from path, with the files it includes.
+pub fn parse_file(path: &str, src: &str) -> Result<Vec<Statement>, String> {
+    let ast = parse_program(src)?;
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    include_files(ast, dir)
+}
+
+/// Run the program read from path and return the exit code of the process.
+pub fn run_program(path: &str, src: &str, options: &RunOptions) -> i32 {
+    let ast = match parse_file(path, src) {
+        Ok(ast) => ast,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("{}", err);
+            return 1;
+        }
+    };
+
+    // The program is shown as it would run
+    if options.emit_ast.is_some() || options.dump_ast {
+        let ast = match options.optimize {
+            true => optimize(&ast),
+            false => ast,
+        };
+        match options.emit_ast {
+            Some(AstFormat::Json) => println!("{}", serde_json::to_string_pretty(&ast).unwrap()),
+            None => print!("{}", print_program(&ast)),
+        }
+        return 0;
+    }
 
-pub fn run_program(src: &String) {
     println!("Hi! \nGrim language interpreter started!\n");
+    if !check_program(&ast, src, options) {
+        println!("\nGoodbye =)");
+        return 1;
+    }
+    execute_program(ast, path, src, options)
+}
 
-    let lexer = Lexer::new(src.as_str());
-    let parser = ProgramParser::new();
-    let ast = parser.parse(lexer).unwrap();
-    let _ = match boot_interpreter(&ast) {
-        Ok(_) => (),
+/// Check the program read from path without running it and return the exit code of the process.
+pub fn check_file(path: &str, src: &str, options: &RunOptions) -> i32 {
+    let ast = match parse_file(path, src) {
+        Ok(ast) => ast,
         Err(err) => {
             println!("{}", "ERROR!".bright_red().bold());
             println!("{}", err);
-        },
+            return 1;
+        }
     };
+    match check_program(&ast, src, options) {
+        true => 0,
+        false => 1,
+    }
+}
+
+/// Resolve the names of a program and collect its warnings, and its type errors if requested.
+///
+/// Returns false, after printing what went wrong, if the program must not run.
+pub fn check_program(ast: &Vec<Statement>, src: &str, options: &RunOptions) -> bool {
+    let resolution_errors = resolve_names(ast, src);
+    if !resolution_errors.is_empty() {
+        println!("{}", "ERROR!".bright_red().bold());
+        for err in &resolution_errors {
+            println!("{}", err.red());
+        }
+        return false;
+    }
+
+    // The entry function is called after the program ran
+    let warnings: Vec<Warning> = collect_warnings(ast, src)
+        .into_iter()
+        .filter(|warning| match warning {
+            Warning::UnusedFunction(name) => options.entry.as_ref() != Some(name),
+            _ => true,
+        })
+        .collect();
+    for warning in &warnings {
+        if options.deny_warnings {
+            eprintln!(
+                "{} {}",
+                "ERROR!".bright_red().bold(),
+                warning.to_string().red()
+            );
+        } else {
+            eprintln!(
+                "{} {}",
+                "WARNING!".yellow().bold(),
+                warning.to_string().yellow()
+            );
+        }
+    }
+    if options.deny_warnings && !warnings.is_empty() {
+        return false;
+    }
+
+    if options.typecheck {
+        let type_errors = check_types(ast);
+        if !type_errors.is_empty() {
+            println!("{}", "ERROR!".bright_red().bold());
+            for err in &type_errors {
+                println!("{}", err.red());
+            }
+            return false;
+        }
+    }
+    true
+}
+
+/// Call the entry function of a program, which takes one str for each program argument.
+fn call_entry(
+    interpreter: &mut Interpreter,
+    name: &str,
+    arguments: &[String],
+) -> Result<(), String> {
+    let function = interpreter
+        .env
+        .get_function_info(name)
+        .map_err(|err| format!("Cannot call the entry function\n{}", err))?;
+    if function.arguments.len() != arguments.len() {
+        return Err(format!(
+            "The entry function ({}) takes {} arguments but the program got {}",
+            name,
+            function.arguments.len(),
+            arguments.len()
+        ));
+    }
+    let values = arguments
+        .iter()
+        .map(|argument| TypeVal::Str(format!("\"{}\"", argument).into()))
+        .collect();
+    interpreter.call_function(name, values)?;
+    interpreter.runtime.borrow_mut().join_spawned()
+}
+
+/// Run a checked program, printing the reports of the tools enabled in the options.
+pub fn execute_program(ast: Vec<Statement>, path: &str, src: &str, options: &RunOptions) -> i32 {
+    let mut builder = Interpreter::builder().source(src);
+    if options.debug {
+        builder = builder.debugger(Debugger::new(options.breakpoints.clone()));
+    }
+    if options.trace {
+        builder = builder.tracer(Tracer::default());
+    }
+    if options.profile {
+        builder = builder.profiler(Profiler::default());
+    }
+    if options.coverage {
+        builder = builder.coverage(Coverage::new(&ast));
+    }
+    if options.inspect {
+        builder = builder.inspector(Inspector::default());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.jit {
+        builder = builder.jit();
+    }
+    let mut interpreter = builder.build();
+    let mut ast = match options.optimize {
+        true => optimize(&ast),
+        false => ast,
+    };
+    let result = interpreter
+        .run(&mut ast)
+        .and_then(|_| match &options.entry {
+            Some(entry) if interpreter.exit_code().is_none() => {
+                call_entry(&mut interpreter, entry, &options.program_arguments)
+            }
+            _ => Ok(()),
+        });
+    let exit_code = match result {
+        Ok(_) => interpreter.exit_code().unwrap_or(0),
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("{}", err);
+            1
+        }
+    };
+
+    let runtime = interpreter.runtime();
+    if let Some(inspector) = &runtime.inspector {
+        inspector.inspect();
+    }
+    if let Some(profiler) = &runtime.profiler {
+        eprint!("\n{}", profiler.report());
+    }
+    if let Some(coverage) = &runtime.coverage {
+        eprint!("\n{}", coverage.report(path, src));
+    }
 
     println!("\nGoodbye =)");
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::io::{SharedBuffer, WriteOutput};
+
+    #[test]
+    fn calls_the_entry_function() {
+        let output = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .build();
+        run_source(
+            "fn main (name) -> { greet(name); } fn greet (name) -> { printl(name); } printl(1);",
+            &mut interpreter,
+        )
+        .unwrap();
+        call_entry(&mut interpreter, "main", &["grim".to_string()]).unwrap();
+        assert_eq!(output.contents(), "1\ngrim\n");
+        assert!(call_entry(&mut interpreter, "main", &[])
+            .unwrap_err()
+            .contains("takes 1 arguments but the program got 0"));
+    }
 }
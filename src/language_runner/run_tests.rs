@@ -0,0 +1,145 @@
+use crate::analysis::resolver::resolve_names;
+use crate::interpreter::interpreter::Interpreter;
+use crate::interpreter::io::{ReadInput, SharedBuffer, WriteOutput};
+use crate::language_runner::run_language::parse_file;
+use crate::parsing::ast::{Statement, StatementKind};
+use colored::Colorize;
+use std::io;
+
+/// Run every `test_*` function without arguments declared at the top level of the program.
+///
+/// Each test gets a new scope holding only the top level functions, the other top level
+/// statements are not executed. Tests read an empty input, what a failed test printed is kept
+/// before its error.
+pub fn run_test_functions(tree: &[Statement], src: &str) -> Vec<(String, Result<(), String>)> {
+    let mut declarations: Vec<Statement> = tree
+        .iter()
+        .filter(|stmt| matches!(stmt.kind, StatementKind::FunctionDeclaration { .. }))
+        .cloned()
+        .collect();
+    let tests: Vec<String> = declarations
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StatementKind::FunctionDeclaration {
+                name, arguments, ..
+            } if name.starts_with("test_") && arguments.is_empty() => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+
+    tests
+        .into_iter()
+        .map(|name| {
+            let output = SharedBuffer::default();
+            let mut interpreter = Interpreter::builder()
+                .source(src)
+                .output(WriteOutput(output.clone()))
+                .input(ReadInput(io::empty()))
+                .build();
+            let result = interpreter
+                .run(&mut declarations)
+                .and_then(|_| interpreter.call_function(&name, vec![]))
+                .map(|_| ())
+                .map_err(|err| {
+                    let mut printed = output.contents();
+                    if !printed.is_empty() && !printed.ends_with('\n') {
+                        printed.push('\n');
+                    }
+                    printed + &err
+                });
+            (name, result)
+        })
+        .collect()
+}
+
+/// Run the tests of the program read from path and return the exit code of the process.
+pub fn run_tests(path: &str, src: &str) -> i32 {
+    let ast = match parse_file(path, src) {
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}", format!("ERROR!\n{}", err).bright_red());
+            return 1;
+        }
+    };
+    let resolution_errors = resolve_names(&ast, src);
+    if !resolution_errors.is_empty() {
+        println!("{}", "ERROR!".bright_red().bold());
+        for err in &resolution_errors {
+            println!("{}", err.red());
+        }
+        return 1;
+    }
+
+    let results = run_test_functions(&ast, src);
+    println!("running {} tests", results.len());
+    for (name, result) in &results {
+        match result {
+            Ok(_) => println!("test {} ... {}", name, "ok".green()),
+            Err(_) => println!("test {} ... {}", name, "FAILED".bright_red()),
+        }
+    }
+
+    let failures: Vec<&(String, Result<(), String>)> = results
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .collect();
+    for (name, result) in &failures {
+        if let Err(err) = result {
+            println!("\n---- {} ----\n{}", name, err.trim_end());
+        }
+    }
+    let passed = results.len() - failures.len();
+    if failures.is_empty() {
+        println!(
+            "\ntest result: {}. {} passed; 0 failed",
+            "ok".green(),
+            passed
+        );
+        0
+    } else {
+        println!(
+            "\ntest result: {}. {} passed; {} failed",
+            "FAILED".bright_red(),
+            passed,
+            failures.len()
+        );
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    #[test]
+    fn runs_each_test_in_a_new_scope() {
+        let src = "fn test_double () -> { \
+                       fn double (x) -> { return x * 2; } assert_eq(double(2), 4); return 0; } \
+                   fn test_failing () -> { print(\"two\"); assert(1 + 1 == 3, \"wrong\"); return 0; } \
+                   fn test_declares () -> { let a = 1; return a; } \
+                   fn test_declares_again () -> { let a = 2; return a; } \
+                   fn helper (x) -> { return x; } \
+                   printl(1 / 0);";
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        let results = run_test_functions(&ast, src);
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "test_double",
+                "test_failing",
+                "test_declares",
+                "test_declares_again"
+            ]
+        );
+        assert!(results[0].1.is_ok());
+        assert!(results[1]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .starts_with("two\nError during function call"));
+        assert!(results[2].1.is_ok() && results[3].1.is_ok());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::language_runner::run_language::{check_program, parse_file, RunOptions};
+use crate::transpiler::javascript::transpile_to_javascript;
+use crate::transpiler::rust::transpile_to_rust;
+use crate::transpiler::Target;
+use colored::Colorize;
+use std::fs;
+
+/// Check the program read from path and print it transpiled to target, or write it to output
+/// if given.
+pub fn transpile_file(
+    path: &str,
+    src: &str,
+    target: Target,
+    output: Option<&str>,
+    options: &RunOptions,
+) -> i32 {
+    let ast = match parse_file(path, src) {
+        Ok(ast) => ast,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("{}", err);
+            return 1;
+        }
+    };
+    if !check_program(&ast, src, options) {
+        return 1;
+    }
+    let transpiled = match target {
+        Target::Rust => transpile_to_rust(&ast),
+        Target::JavaScript => transpile_to_javascript(&ast),
+    };
+    let written = transpiled.and_then(|code| match output {
+        Some(output) => fs::write(output, code).map_err(|err| err.to_string()),
+        None => {
+            print!("{}", code);
+            Ok(())
+        }
+    });
+    match written {
+        Ok(_) => 0,
+        Err(err) => {
+            println!("{}", "ERROR!".bright_red().bold());
+            println!("Cannot transpile the program\n{}", err);
+            1
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod analysis;
+pub mod interpreter;
+pub mod language_runner;
+pub mod parsing;
+pub mod transpiler;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
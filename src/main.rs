@@ -1,22 +1,298 @@
-use crate::language_runner::run_language::run_program;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{generate, Shell};
 use colored::Colorize;
-use std::env;
+use grim::interpreter::error_reporting::{set_color, ColorChoice};
+use grim::language_runner::compiled::{build_program, run_compiled};
+use grim::language_runner::doc_file::{doc_file, DocFormat};
+use grim::language_runner::format_file::format_file;
+use grim::language_runner::repl::run_repl;
+use grim::language_runner::run_examples::run_examples;
+use grim::language_runner::run_language::{check_file, run_program, AstFormat, RunOptions};
+use grim::language_runner::run_tests::run_tests;
+use grim::language_runner::transpile_file::transpile_file;
+use grim::transpiler::Target;
 use std::fs::read_to_string;
+use std::io;
 use std::process::exit;
 
-mod interpreter;
-mod language_runner;
-mod parsing;
+/// Interpreter and tools of the Grim language.
+///
+/// Without a subcommand the script is run, and without a script the REPL starts.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// When to color the errors and the warnings: auto, always or never
+    #[arg(long, global = true, default_value = "auto", value_parser = parse_color)]
+    color: ColorChoice,
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!(
-            "{}",
-            "ERROR!\nPlease, insert the path of only one valid .grim file".bright_red()
-        );
-        exit(1);
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Script to run
+    path: Option<String>,
+
+    #[command(flatten)]
+    run: RunFlags,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a script, or a program compiled with build
+    Run {
+        path: String,
+        #[command(flatten)]
+        run: RunFlags,
+    },
+    /// Type statements one input at a time
+    Repl,
+    /// Rewrite a script with the canonical indentation and spacing
+    Fmt {
+        path: String,
+        /// Fail if the script is not formatted, without rewriting it
+        #[arg(long)]
+        check: bool,
+    },
+    /// Resolve the names and report the warnings of a script, without running it
+    Check {
+        path: String,
+        #[command(flatten)]
+        check: CheckFlags,
+    },
+    /// Run the test functions of a script
+    Test { path: String },
+    /// Run the scripts of a directory and compare their output with the .out files
+    RunExamples { dir: String },
+    /// Write a script compiled, to run it without its source
+    Build {
+        path: String,
+        /// Path of the compiled program, the script with the .grimc extension by default
+        #[arg(short)]
+        output: Option<String>,
+        /// Optimize the program before compiling it
+        #[arg(short = 'O')]
+        optimize: bool,
+        #[command(flatten)]
+        check: CheckFlags,
+    },
+    /// Turn a script into a Rust or JavaScript program
+    Transpile {
+        path: String,
+        #[arg(long, value_enum)]
+        target: Language,
+        /// Path of the generated program, printed by default
+        #[arg(short)]
+        output: Option<String>,
+        #[command(flatten)]
+        check: CheckFlags,
+    },
+    /// Render the documentation of the functions declared at the top level
+    Doc {
+        path: String,
+        #[arg(long, value_enum, default_value_t = Format::Markdown)]
+        format: Format,
+        /// Path of the documentation, printed by default
+        #[arg(short)]
+        output: Option<String>,
+    },
+    /// Print the completion script of a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// Flags deciding if a program is allowed to run.
+#[derive(Args, Default)]
+struct CheckFlags {
+    /// Turn the warnings into errors
+    #[arg(long)]
+    deny_warnings: bool,
+    /// Check the types of the program before running it
+    #[arg(long)]
+    typecheck: bool,
+}
+
+#[derive(Args)]
+struct RunFlags {
+    #[command(flatten)]
+    check: CheckFlags,
+    /// Optimize the program before running it
+    #[arg(short = 'O')]
+    optimize: bool,
+    /// Compile the hot functions to native code
+    #[arg(long)]
+    jit: bool,
+    /// Print the program formatted, without running it
+    #[arg(long)]
+    dump_ast: bool,
+    /// Print the AST in the given format, without running the program
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    emit_ast: Option<EmitFormat>,
+    /// Pause at the breakpoint statements
+    #[arg(long)]
+    debug: bool,
+    /// Pause at the given line, implies --debug
+    #[arg(long = "break", value_name = "LINE")]
+    breakpoints: Vec<usize>,
+    /// Print every statement executed on stderr
+    #[arg(long)]
+    trace: bool,
+    /// Print the time spent in each function on stderr at exit
+    #[arg(long)]
+    profile: bool,
+    /// Print the lines never executed on stderr at exit
+    #[arg(long)]
+    coverage: bool,
+    /// Examine the variables that led to a runtime error
+    #[arg(long)]
+    inspect: bool,
+    /// Function called once the top-level statements ran
+    #[arg(long, value_name = "NAME")]
+    entry: Option<String>,
+    /// Arguments passed to the entry function as strings
+    #[arg(last = true, requires = "entry")]
+    program_arguments: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Language {
+    Rust,
+    Js,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EmitFormat {
+    Json,
+}
+
+fn parse_color(value: &str) -> Result<ColorChoice, String> {
+    ColorChoice::parse(value).ok_or_else(|| "use auto, always or never".to_string())
+}
+
+impl CheckFlags {
+    fn options(&self, color: ColorChoice) -> RunOptions {
+        RunOptions {
+            color,
+            deny_warnings: self.deny_warnings,
+            typecheck: self.typecheck,
+            ..Default::default()
+        }
+    }
+}
+
+impl RunFlags {
+    fn options(self, color: ColorChoice) -> RunOptions {
+        RunOptions {
+            optimize: self.optimize,
+            jit: self.jit,
+            dump_ast: self.dump_ast,
+            emit_ast: self.emit_ast.map(|EmitFormat::Json| AstFormat::Json),
+            debug: self.debug || !self.breakpoints.is_empty(),
+            trace: self.trace,
+            profile: self.profile,
+            coverage: self.coverage,
+            inspect: self.inspect,
+            breakpoints: self.breakpoints.into_iter().collect(),
+            entry: self.entry,
+            program_arguments: self.program_arguments,
+            ..self.check.options(color)
+        }
     }
-    let source_code = read_to_string(&args[1]).unwrap();
-    run_program(&source_code);
+}
+
+/// Source code of a script, the process ends if it cannot be read.
+fn read_source(path: &str) -> String {
+    match read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("ERROR!\nCannot read {}: {}", path, err).bright_red()
+            );
+            exit(1);
+        }
+    }
+}
+
+fn run(path: &str, options: &RunOptions) -> i32 {
+    match path.ends_with(".grimc") {
+        true => run_compiled(path, options),
+        false => run_program(path, &read_source(path), options),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    set_color(cli.color);
+    let color = cli.color;
+    let exit_code = match cli.command {
+        None => match cli.path {
+            Some(path) => run(&path, &cli.run.options(color)),
+            None => run_repl(),
+        },
+        Some(Command::Run { path, run: flags }) => run(&path, &flags.options(color)),
+        Some(Command::Repl) => run_repl(),
+        Some(Command::Fmt { path, check }) => format_file(&path, check),
+        Some(Command::Check { path, check }) => {
+            check_file(&path, &read_source(&path), &check.options(color))
+        }
+        Some(Command::Test { path }) => run_tests(&path, &read_source(&path)),
+        Some(Command::RunExamples { dir }) => run_examples(&dir),
+        Some(Command::Build {
+            path,
+            output,
+            optimize,
+            check,
+        }) => {
+            let output =
+                output.unwrap_or_else(|| format!("{}.grimc", path.trim_end_matches(".grim")));
+            let options = RunOptions {
+                optimize,
+                ..check.options(color)
+            };
+            build_program(&path, &read_source(&path), &output, &options)
+        }
+        Some(Command::Transpile {
+            path,
+            target,
+            output,
+            check,
+        }) => {
+            let target = match target {
+                Language::Rust => Target::Rust,
+                Language::Js => Target::JavaScript,
+            };
+            transpile_file(
+                &path,
+                &read_source(&path),
+                target,
+                output.as_deref(),
+                &check.options(color),
+            )
+        }
+        Some(Command::Doc {
+            path,
+            format,
+            output,
+        }) => {
+            let format = match format {
+                Format::Markdown => DocFormat::Markdown,
+                Format::Html => DocFormat::Html,
+            };
+            doc_file(&path, &read_source(&path), format, output.as_deref())
+        }
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            generate(shell, &mut command, name, &mut io::stdout());
+            0
+        }
+    };
+    exit(exit_code);
 }
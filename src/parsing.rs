@@ -1,5 +1,80 @@
 pub mod ast;
+pub mod include;
 pub mod lexer;
+pub mod printer;
 
+use crate::parsing::ast::{Expression, Span, Statement};
+use crate::parsing::grammar::{ExpressionParser, ProgramParser, StatementParser};
+use crate::parsing::lexer::{parse_error_message, Lexer};
 use lalrpop_util::lalrpop_mod;
 lalrpop_mod!(pub grammar, "/parsing/grammar.rs");
+
+/// Parse a whole program, the error tells where the source code is wrong.
+pub fn parse_program(src: &str) -> Result<Vec<Statement>, String> {
+    ProgramParser::new().parse(Lexer::new(src)).map_err(|err| {
+        format!(
+            "Cannot parse the source code\n{}",
+            parse_error_message(&err, src)
+        )
+    })
+}
+
+/// Parse the single statement written at span in the source code of a program, without the
+/// rest of the program, so a tool parses again only the statement that changed.
+///
+/// The spans of the statement and the position of the error are in the whole source code.
+pub fn parse_statement(src: &str, span: Span) -> Result<Statement, String> {
+    let text = src
+        .get(span.start..span.end)
+        .ok_or_else(|| format!("{:?} is not a part of the source code", span))?;
+    StatementParser::new()
+        .parse(Lexer::at(text, span.start))
+        .map_err(|err| {
+            format!(
+                "Cannot parse the statement\n{}",
+                parse_error_message(&err, src)
+            )
+        })
+}
+
+/// Parse a single expression, as typed in the REPL.
+pub fn parse_expression(src: &str) -> Result<Box<Expression>, String> {
+    ExpressionParser::new()
+        .parse(Lexer::new(src))
+        .map_err(|err| {
+            format!(
+                "Cannot parse the expression\n{}",
+                parse_error_message(&err, src)
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_statement_alone() {
+        let src = "let a = 1;\nif a > 0 {\n    printl(a);\n}\nlet b = a $ 2;\n";
+        let start = src.find("if").unwrap();
+        let span = Span {
+            start,
+            end: src.find('}').unwrap() + 1,
+        };
+        let program = parse_program(&src[..span.end]).unwrap();
+        assert_eq!(parse_statement(src, span), Ok(program[1].clone()));
+        assert_eq!(program[1].span, span);
+
+        let start = src.find("let b").unwrap();
+        let err = parse_statement(
+            src,
+            Span {
+                start,
+                end: src.len(),
+            },
+        )
+        .unwrap_err();
+        assert!(err.contains("`$` at line 5, column 11"), "{}", err);
+        assert!(parse_expression("a +").is_err());
+    }
+}
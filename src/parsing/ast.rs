@@ -1,17 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// Byte offsets delimiting a node in the source code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Line and column (both starting from 1) where the span starts.
+    pub fn line_and_column(&self, src: &str) -> (usize, usize) {
+        let before = &src[..self.start.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        (line, column)
+    }
+}
+
+/// A statement with its position in the source code.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+/// Names of the functions declared by a block, without the ones of its nested blocks.
+pub fn declared_functions(tree: &[Statement]) -> impl Iterator<Item = &String> {
+    tree.iter().filter_map(|stmt| match &stmt.kind {
+        StatementKind::FunctionDeclaration { name, .. } => Some(name),
+        _ => None,
+    })
+}
+
 /// Range of possible statements
-#[derive(Clone, Debug, PartialEq)]
-pub enum Statement {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StatementKind {
     ///////////////////////////
     // Assignment statements //
     ///////////////////////////
+    /// let x = 1; binds x for good, let mut x = 1; lets the program assign it later.
     VariableDeclarationStatement {
         name: String,
+        type_annotation: Option<Type>,
         value: Box<Expression>,
+        mutable: bool,
     },
     AssignmentStatement {
         name: String,
         value: Box<Expression>,
     },
+    /// a, b = b, a; or let (x, y) = (1, 2); every value is evaluated before the names are bound.
+    ///
+    /// mutable: the declared names can be assigned later, always false for an assignment.
+    DestructuringStatement {
+        names: Vec<String>,
+        values: Vec<Box<Expression>>,
+        declaration: bool,
+        mutable: bool,
+    },
 
     /////////////////////
     // Flow statements //
@@ -28,11 +76,26 @@ pub enum Statement {
     WhileStatement {
         cond: Box<Expression>,
         body: Vec<Statement>,
+        label: Option<String>,
+    },
+    /// for variable in start..end step step { body }, the end is excluded and the step is 1 if
+    /// not given.
+    ForStatement {
+        variable: String,
+        start: Box<Expression>,
+        end: Box<Expression>,
+        step: Option<Box<Expression>>,
+        body: Vec<Statement>,
+        label: Option<String>,
     },
     FunctionDeclaration {
         name: String,
-        arguments: Vec<String>,
-        body: Vec<Statement>,
+        arguments: Vec<Parameter>,
+        return_type: Option<Type>,
+        /// Shared with the functions declared from it, entering the block does not copy it.
+        body: Arc<Vec<Statement>>,
+        /// Text of the ## comments right before the declaration.
+        doc: Option<String>,
     },
     FunctionCallStatement {
         name: String,
@@ -41,6 +104,19 @@ pub enum Statement {
     ReturnStatement {
         value: Box<Expression>,
     },
+    SpawnStatement {
+        name: String,
+        arguments: Vec<Box<Expression>>,
+    },
+    /// break; leaves the innermost loop, break outer; the loop labeled outer.
+    BreakStatement {
+        label: Option<String>,
+    },
+    /// continue; starts the next iteration of the innermost loop, continue outer; of the loop
+    /// labeled outer.
+    ContinueStatement {
+        label: Option<String>,
+    },
 
     ////////////////////
     // I/O statements //
@@ -54,16 +130,74 @@ pub enum Statement {
     InputStatement {
         name: String,
     },
+    BreakpointStatement,
+    /// include "helpers.grim"; with the path relative to the directory of the including file,
+    /// replaced by the statements of the file before the program is checked.
+    IncludeStatement {
+        path: String,
+    },
+    /// measure "label" { body } runs the body in its own block and prints the time it took.
+    MeasureStatement {
+        label: String,
+        body: Vec<Statement>,
+    },
+}
+
+/// Parameter of a function declaration with its optional type annotation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub type_annotation: Option<Type>,
+}
+
+/// Range of possible type annotations.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Channel,
+    Decimal,
+    Char,
+    Set,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "str"),
+            Type::Channel => write!(f, "channel"),
+            Type::Set => write!(f, "set"),
+            Type::Decimal => write!(f, "decimal"),
+            Type::Char => write!(f, "char"),
+        }
+    }
 }
 
 /// Range of possible expressions.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
     Float(f64),
     Int(i64),
     Identifier(String),
+    /// Identifier bound to a slot of the scope `depth` levels above the current one.
+    ResolvedIdentifier {
+        name: String,
+        depth: usize,
+        slot: usize,
+    },
     Str(String),
+    Char(char),
     Bool(bool),
+    /// target[index], the character of a string at a position.
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+    },
     FunctionCall {
         name: String,
         arguments: Vec<Box<Expression>>,
@@ -80,7 +214,7 @@ pub enum Expression {
 }
 
 /// Range of possible binary operators.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOperator {
     Add,
     Sub,
@@ -97,9 +231,56 @@ pub enum BinaryOperator {
     CompareNeq,
 }
 
+/// Symbol of the operator, as written in the source code.
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessEq => "<=",
+            BinaryOperator::GreaterEq => ">=",
+            BinaryOperator::CompareEq => "==",
+            BinaryOperator::CompareNeq => "!=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
 /// Range of possible unitary operator.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOperator {
     Not,
     Minus,
 }
+
+/// Symbol of the operator, as written in the source code.
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOperator::Not => write!(f, "!"),
+            UnaryOperator::Minus => write!(f, "-"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    #[test]
+    fn json_round_trip() {
+        let src = "fn f (a: int) -> float { return a * 1.5; } let x = f(2) > 1 && !false;";
+        let ast = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        let json = serde_json::to_string(&ast).unwrap();
+        let decoded: Vec<super::Statement> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, ast);
+    }
+}
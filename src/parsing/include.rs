@@ -0,0 +1,143 @@
+use crate::parsing::ast::{Span, Statement, StatementKind};
+use crate::parsing::grammar::ProgramParser;
+use crate::parsing::lexer::{parse_error_message, Lexer};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Error of an include statement left in the program, only the top-level ones are replaced.
+pub fn not_included(path: &str) -> String {
+    format!(
+        "`include \"{}\"` must be at the top level of a program read from a file",
+        path
+    )
+}
+
+/// Replace the top-level include statements of a program read from a file in dir by the
+/// statements of the files they name, which can include other files.
+///
+/// The included statements take the position of their include statement, so their errors are
+/// reported at its line.
+pub fn include_files(tree: Vec<Statement>, dir: &Path) -> Result<Vec<Statement>, String> {
+    expand(tree, dir, &mut vec![])
+}
+
+/// including: files being included, from the outermost, none of them can be included again.
+fn expand(
+    tree: Vec<Statement>,
+    dir: &Path,
+    including: &mut Vec<PathBuf>,
+) -> Result<Vec<Statement>, String> {
+    let mut expanded = vec![];
+    for stmt in tree {
+        let path = match &stmt.kind {
+            StatementKind::IncludeStatement { path } => path,
+            _ => {
+                expanded.push(stmt);
+                continue;
+            }
+        };
+        let file = fs::canonicalize(dir.join(path))
+            .map_err(|err| format!("Cannot include {}: {}", path, err))?;
+        if including.contains(&file) {
+            return Err(format!(
+                "Cannot include {}, it is already being included",
+                path
+            ));
+        }
+        let src =
+            fs::read_to_string(&file).map_err(|err| format!("Cannot include {}: {}", path, err))?;
+        let included = ProgramParser::new()
+            .parse(Lexer::new(&src))
+            .map_err(|err| format!("Cannot parse {}\n{}", path, parse_error_message(&err, &src)))?;
+        including.push(file.clone());
+        let included = expand(included, file.parent().unwrap_or(dir), including)?;
+        including.pop();
+        for mut included_stmt in included {
+            relocate(&mut included_stmt, stmt.span);
+            expanded.push(included_stmt);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Move a statement and the ones in its body to the given position.
+fn relocate(stmt: &mut Statement, span: Span) {
+    stmt.span = span;
+    match &mut stmt.kind {
+        StatementKind::IfStatement {
+            then_part: body, ..
+        }
+        | StatementKind::WhileStatement { body, .. }
+        | StatementKind::ForStatement { body, .. }
+        | StatementKind::MeasureStatement { body, .. } => {
+            for stmt in body {
+                relocate(stmt, span);
+            }
+        }
+        StatementKind::FunctionDeclaration { body, .. } => {
+            for stmt in Arc::make_mut(body) {
+                relocate(stmt, span);
+            }
+        }
+        StatementKind::IfElseStatement {
+            then_part,
+            else_part,
+            ..
+        } => {
+            for stmt in then_part.iter_mut().chain(else_part) {
+                relocate(stmt, span);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::io::{SharedBuffer, WriteOutput};
+    use std::env;
+
+    #[test]
+    fn splices_the_included_files() {
+        let dir = env::temp_dir().join("grim_include_test");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("lib/math.grim"),
+            "include \"square.grim\";\nfn cube (x) -> { return square(x) * x; }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("lib/square.grim"),
+            "fn square (x) -> { return x * x; }\n",
+        )
+        .unwrap();
+        fs::write(dir.join("lib/loop.grim"), "include \"../loop.grim\";\n").unwrap();
+        fs::write(dir.join("loop.grim"), "include \"lib/loop.grim\";\n").unwrap();
+
+        let src = "let x = 2;\ninclude \"lib/math.grim\";\nprintl(cube(x));";
+        let tree = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+        let mut tree = include_files(tree, &dir).unwrap();
+        assert_eq!(tree.len(), 4);
+        assert!(tree[1..3].iter().all(|stmt| stmt.span == tree[1].span));
+
+        let output = SharedBuffer::default();
+        let mut interpreter = Interpreter::builder()
+            .output(WriteOutput(output.clone()))
+            .build();
+        interpreter.run(&mut tree).unwrap();
+        assert_eq!(output.contents(), "8\n");
+
+        let include = |src: &str| {
+            let tree = ProgramParser::new().parse(Lexer::new(src)).unwrap();
+            include_files(tree, &dir).unwrap_err()
+        };
+        assert!(include("include \"missing.grim\";").starts_with("Cannot include missing.grim"));
+        assert_eq!(
+            include("include \"loop.grim\";"),
+            "Cannot include ../loop.grim, it is already being included"
+        );
+    }
+}
@@ -1,24 +1,163 @@
+use crate::parsing::ast::Span;
+use lalrpop_util::ParseError;
 use logos::{Logos, SpannedIter};
+use std::collections::VecDeque;
 use std::fmt;
+use std::ops::Range;
 
+/// Error of the lexer.
+///
+/// Logos produces InvalidToken, the Lexer replaces it with UnexpectedCharacter, holding where
+/// the text that starts no token is. InvalidEscape is an unknown escape sequence in a string or
+/// a char, InvalidChar a char literal not holding exactly one character, IntOutOfRange an int
+/// literal too large for an int.
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum LexicalError {
     #[default]
     InvalidToken,
+    UnexpectedCharacter {
+        span: Span,
+        slice: String,
+    },
+    InvalidEscape {
+        span: Span,
+        sequence: String,
+    },
+    InvalidChar {
+        span: Span,
+        slice: String,
+    },
+    IntOutOfRange {
+        span: Span,
+        slice: String,
+    },
+}
+
+impl LexicalError {
+    /// Describe the error with its line and column in src, and a hint to fix it if any.
+    pub fn describe(&self, src: &str) -> String {
+        let (message, hint) = match self {
+            LexicalError::InvalidToken => return "invalid token".to_string(),
+            LexicalError::UnexpectedCharacter { span, slice } => {
+                let character = slice.chars().next().unwrap_or_default();
+                let (line, column) = span.line_and_column(src);
+                let hint = match character {
+                    '"' => Some("the string is never closed"),
+                    '&' | '|' => Some("the logical operators are && and ||"),
+                    _ => None,
+                };
+                let message = format!(
+                    "unexpected character `{}` at line {}, column {}",
+                    character, line, column
+                );
+                (message, hint)
+            }
+            LexicalError::InvalidEscape { span, sequence } => {
+                let (line, column) = span.line_and_column(src);
+                let message = format!(
+                    "invalid escape sequence `{}` at line {}, column {}",
+                    sequence, line, column
+                );
+                (
+                    message,
+                    Some(r#"the escapes are \n \t \r \0 \\ \" \' and \u{...}"#),
+                )
+            }
+            LexicalError::InvalidChar { span, slice } => {
+                let (line, column) = span.line_and_column(src);
+                let message = format!(
+                    "the char literal {} at line {}, column {} does not hold one character",
+                    slice, line, column
+                );
+                (message, Some("strings are written between double quotes"))
+            }
+            LexicalError::IntOutOfRange { span, slice } => {
+                let (line, column) = span.line_and_column(src);
+                let message = format!(
+                    "the int {} at line {}, column {} is too large",
+                    slice, line, column
+                );
+                (
+                    message,
+                    Some("ints go up to 9223372036854775807, write a float with a point"),
+                )
+            }
+        };
+        match hint {
+            Some(hint) => format!("{}\nhint: {}", message, hint),
+            None => message,
+        }
+    }
+}
+
+/// Describe an error of the parser located in src, with the tokens that could come instead.
+pub fn parse_error_message(err: &ParseError<usize, Token, LexicalError>, src: &str) -> String {
+    let at = |offset: usize| {
+        let (line, column) = Span {
+            start: offset,
+            end: offset,
+        }
+        .line_and_column(src);
+        format!("at line {}, column {}", line, column)
+    };
+    let token = |start: usize, end: usize| src.get(start..end).unwrap_or_default().to_string();
+    match err {
+        ParseError::User { error } => error.describe(src),
+        ParseError::InvalidToken { location } => format!("invalid token {}", at(*location)),
+        ParseError::UnrecognizedEof { location, expected } => format!(
+            "unexpected end of the source {}{}",
+            at(*location),
+            expected_tokens(expected)
+        ),
+        ParseError::UnrecognizedToken {
+            token: (start, _, end),
+            expected,
+        } => format!(
+            "unexpected `{}` {}{}",
+            token(*start, *end),
+            at(*start),
+            expected_tokens(expected)
+        ),
+        ParseError::ExtraToken {
+            token: (start, _, end),
+        } => format!("unexpected `{}` {}", token(*start, *end), at(*start)),
+    }
+}
+
+/// Tokens the parser expected, named as in the grammar, or nothing if it gave none.
+fn expected_tokens(expected: &[String]) -> String {
+    let names: Vec<String> = expected
+        .iter()
+        .map(|name| {
+            let name = name.trim_matches('"');
+            format!("`{}`", name.strip_suffix("_type").unwrap_or(name))
+        })
+        .collect();
+    match names.as_slice() {
+        [] => String::new(),
+        [name] => format!(", expected {}", name),
+        names => format!(", expected one of {}", names.join(", ")),
+    }
 }
 
 /// Possible tokens that can be read.
 #[derive(Logos, Clone, Debug, PartialEq)]
-#[logos(skip r"[ \t\n\f]+", skip r"#.*\n?", error = LexicalError)]
+#[logos(skip r"[ \t\n\f]+", error = LexicalError)]
 pub enum Token {
-    #[regex("[0-9][.][0-9]+", | lex | lex.slice().parse::< f64 > ().unwrap())]
+    #[regex("#[^\n]*", | lex | lex.slice().trim_end().to_owned())]
+    TokComment(String),
+    #[regex("##[^\n]*", | lex | lex.slice().trim_end().to_owned(), priority = 10)]
+    TokDocComment(String),
+    #[regex("[0-9]+[.][0-9]+", | lex | lex.slice().parse::< f64 > ().unwrap())]
     TokFloat(f64),
-    #[regex("[0-9]*", | lex | lex.slice().parse::< i64 > ().unwrap())]
+    #[regex("[0-9]*", int_literal)]
     TokInt(i64),
-    #[regex("[a-z_][a-zA-Z0-9_]*", | lex | lex.slice().to_owned())]
+    #[regex(r"[_\p{XID_Start}]\p{XID_Continue}*", | lex | lex.slice().to_owned())]
     TokIdentifier(String),
-    #[regex(r#"[\"][a-zA-Z0-9_ .:;,><!?=]*[\"]"#, | lex | lex.slice().to_owned())]
+    #[regex(r#""([^"\\]|\\(.|\n))*""#, string_literal)]
     TokString(String),
+    #[regex(r"'([^'\\\n]|\\.)*'", char_literal)]
+    TokChar(char),
     #[regex("true|false", | lex | lex.slice().parse::< bool > ().unwrap())]
     TokBool(bool),
     #[token("(")]
@@ -39,6 +178,10 @@ pub enum Token {
     TokPlus,
     #[token("-")]
     TokMinus,
+    #[token("++")]
+    TokIncrement,
+    #[token("--")]
+    TokDecrement,
     #[token("*")]
     TokTimes,
     #[token("/")]
@@ -69,10 +212,16 @@ pub enum Token {
     TokAnd,
     #[token("||")]
     TokOr,
+    #[token("|>")]
+    TokPipe,
     #[token("->")]
     TokArrow,
+    #[token("..")]
+    TokRange,
     #[token("let")]
     TokLet,
+    #[token("mut")]
+    TokMut,
     #[token("if")]
     TokIf,
     #[token("else")]
@@ -81,14 +230,159 @@ pub enum Token {
     TokFn,
     #[token("while")]
     TokWhile,
+    #[token("for")]
+    TokFor,
+    #[token("in")]
+    TokIn,
+    #[token("step")]
+    TokStep,
+    #[token("to")]
+    TokTo,
+    #[token("break")]
+    TokBreak,
+    #[token("continue")]
+    TokContinue,
     #[token("return")]
     TokReturn,
+    #[token("spawn")]
+    TokSpawn,
+    #[token("breakpoint")]
+    TokBreakpoint,
+    #[token("include")]
+    TokInclude,
+    #[token("measure")]
+    TokMeasure,
     #[token("print")]
     TokPrint,
     #[token("printl")]
     TokPrintL,
     #[token("input")]
     TokInput,
+    #[token("int")]
+    TokTypeInt,
+    #[token("float")]
+    TokTypeFloat,
+    #[token("bool")]
+    TokTypeBool,
+    #[token("str")]
+    TokTypeStr,
+    #[token("char")]
+    TokTypeChar,
+}
+
+/// Value of an int literal, an error if it does not fit in an int.
+fn int_literal(lex: &mut logos::Lexer<Token>) -> Result<i64, LexicalError> {
+    lex.slice()
+        .parse()
+        .map_err(|_| LexicalError::IntOutOfRange {
+            span: Span {
+                start: lex.span().start,
+                end: lex.span().end,
+            },
+            slice: lex.slice().to_string(),
+        })
+}
+
+/// Value of a string literal with its escapes replaced, still between quotes.
+fn string_literal(lex: &mut logos::Lexer<Token>) -> Result<String, LexicalError> {
+    Ok(format!("\"{}\"", unescape(lex)?))
+}
+
+/// Character of a char literal with its escape replaced.
+fn char_literal(lex: &mut logos::Lexer<Token>) -> Result<char, LexicalError> {
+    let value = unescape(lex)?;
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(LexicalError::InvalidChar {
+            span: Span {
+                start: lex.span().start,
+                end: lex.span().end,
+            },
+            slice: lex.slice().to_string(),
+        }),
+    }
+}
+
+/// Content of a string or char literal, without its quotes and with its escapes replaced.
+fn unescape(lex: &mut logos::Lexer<Token>) -> Result<String, LexicalError> {
+    let slice = lex.slice();
+    let mut value = String::new();
+    let mut chars = slice[1..slice.len() - 1].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some((_, 'n')) => Some('\n'),
+            Some((_, 't')) => Some('\t'),
+            Some((_, 'r')) => Some('\r'),
+            Some((_, '0')) => Some('\0'),
+            Some((_, '\\')) => Some('\\'),
+            Some((_, '"')) => Some('"'),
+            Some((_, '\'')) => Some('\''),
+            Some((_, 'u')) if chars.next_if(|(_, c)| *c == '{').is_some() => {
+                let mut code = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => {
+                            break u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)
+                        }
+                        Some((_, c)) if c.is_ascii_hexdigit() && code.len() < 6 => code.push(c),
+                        _ => break None,
+                    }
+                }
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(escaped) => value.push(escaped),
+            None => {
+                // Offset of the sequence in the source, the slice starts with the quote
+                let start = lex.span().start + 1 + i;
+                let end = chars
+                    .peek()
+                    .map_or(lex.span().end - 1, |(j, _)| lex.span().start + 1 + j);
+                return Err(LexicalError::InvalidEscape {
+                    span: Span { start, end },
+                    sequence: lex.source()[start..end].to_string(),
+                });
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// Source form of a string, between quotes and with the characters that need it escaped.
+pub fn quote_string(text: &str) -> String {
+    quote(text.chars(), '"')
+}
+
+/// Source form of a char, between single quotes and escaped if it needs it.
+pub fn quote_char(c: char) -> String {
+    quote(std::iter::once(c), '\'')
+}
+
+fn quote(chars: impl Iterator<Item = char>, delimiter: char) -> String {
+    let mut quoted = String::from(delimiter);
+    for c in chars {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            '\\' => quoted.push_str("\\\\"),
+            c if c == delimiter => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push(delimiter);
+    quoted
 }
 
 impl fmt::Display for Token {
@@ -101,31 +395,174 @@ impl fmt::Display for Token {
 
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
+/// Lexer for the parser.
+///
+/// Doc comments reach the parser only when a function declaration follows them, otherwise they
+/// are plain comments. pending: tokens read ahead to find out.
 pub struct Lexer<'input> {
     token_stream: SpannedIter<'input, Token>,
+    keep_comments: bool,
+    offset: usize,
+    pending: VecDeque<Spanned<Token, usize, LexicalError>>,
 }
 
 impl<'input> Lexer<'input> {
+    /// Lexer for the parser, comments are skipped.
     pub fn new(input: &'input str) -> Self {
+        Self::at(input, 0)
+    }
+
+    /// Lexer for the parser of a part of a source code starting at offset, the tokens and the
+    /// errors are located in the whole source code.
+    pub fn at(input: &'input str, offset: usize) -> Self {
+        Self {
+            token_stream: Token::lexer(input).spanned(),
+            keep_comments: false,
+            offset,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Lexer producing comments as tokens too, for tools that need to preserve them.
+    pub fn with_comments(input: &'input str) -> Self {
         Self {
             token_stream: Token::lexer(input).spanned(),
+            keep_comments: true,
+            offset: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Next token of the source, errors are located.
+    fn next_token(&mut self) -> Option<(Result<Token, LexicalError>, Range<usize>)> {
+        let (token, span) = self.token_stream.next()?;
+        let offset = self.offset;
+        let span = span.start + offset..span.end + offset;
+        let shift = |span: Span| Span {
+            start: span.start + offset,
+            end: span.end + offset,
+        };
+        let token = token.map_err(|err| match err {
+            LexicalError::InvalidToken => LexicalError::UnexpectedCharacter {
+                span: Span {
+                    start: span.start,
+                    end: span.end,
+                },
+                slice: self.token_stream.slice().to_string(),
+            },
+            LexicalError::UnexpectedCharacter { span, slice } => {
+                LexicalError::UnexpectedCharacter {
+                    span: shift(span),
+                    slice,
+                }
+            }
+            LexicalError::InvalidEscape { span, sequence } => LexicalError::InvalidEscape {
+                span: shift(span),
+                sequence,
+            },
+            LexicalError::InvalidChar { span, slice } => LexicalError::InvalidChar {
+                span: shift(span),
+                slice,
+            },
+            LexicalError::IntOutOfRange { span, slice } => LexicalError::IntOutOfRange {
+                span: shift(span),
+                slice,
+            },
+        });
+        Some((token, span))
+    }
+
+    /// Read a run of doc comments and the token after it, queueing them as they must be produced.
+    fn read_docs(&mut self, first: (usize, String, usize)) {
+        let mut docs = vec![first];
+        let next = loop {
+            match self.next_token() {
+                Some((Ok(Token::TokDocComment(text)), span)) => {
+                    docs.push((span.start, text, span.end))
+                }
+                next => break next,
+            }
+        };
+        let documents = matches!(next, Some((Ok(Token::TokFn), _)));
+        for (start, text, end) in docs {
+            match documents {
+                true => self
+                    .pending
+                    .push_back(Ok((start, Token::TokDocComment(text), end))),
+                false if self.keep_comments => {
+                    self.pending
+                        .push_back(Ok((start, Token::TokComment(text), end)))
+                }
+                false => {}
+            }
+        }
+        match next {
+            Some((Ok(Token::TokComment(_)), _)) if !self.keep_comments => {}
+            Some((token, span)) => self
+                .pending
+                .push_back(token.map(|token| (span.start, token, span.end))),
+            None => {}
         }
     }
 }
 
+/// Text of a doc comment without the leading ## and the space after it.
+pub fn doc_line(comment: &str) -> &str {
+    let line = comment.strip_prefix("##").unwrap_or(comment);
+    line.strip_prefix(' ').unwrap_or(line)
+}
+
 impl<'input> Iterator for Lexer<'input> {
     type Item = Spanned<Token, usize, LexicalError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.token_stream
-            .next()
-            .map(|(token, span)| Ok((span.start, token?, span.end)))
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(token);
+            }
+            let (token, span) = self.next_token()?;
+            match token {
+                Ok(Token::TokComment(_)) if !self.keep_comments => continue,
+                Ok(Token::TokDocComment(text)) => self.read_docs((span.start, text, span.end)),
+                token => return Some(token.map(|token| (span.start, token, span.end))),
+            }
+        }
     }
 }
 
+/// A comment kept as trivia of the source code.
+///
+/// trailing: the comment follows some code on the same line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub start: usize,
+    pub text: String,
+    pub trailing: bool,
+}
+
+/// Collect the comments of the source code in order of appearance.
+pub fn collect_comments(src: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut code_end: Option<usize> = None;
+    for (start, token, end) in Lexer::with_comments(src).flatten() {
+        match token {
+            // Doc comments belong to the function they document
+            Token::TokDocComment(_) => {}
+            Token::TokComment(text) => comments.push(Comment {
+                start,
+                text,
+                trailing: code_end.is_some_and(|code_end| !src[code_end..start].contains('\n')),
+            }),
+            _ => code_end = Some(end),
+        }
+    }
+    comments
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parsing::grammar::ProgramParser;
     use crate::parsing::lexer::Token;
 
     #[test]
@@ -224,4 +661,149 @@ mod tests {
         assert_eq!(lex.next(), Some(Ok(Token::TokBool(false))));
         assert_eq!(lex.next(), Some(Ok(Token::TokSemi)))
     }
+
+    #[test]
+    fn tokenizer_test_6() {
+        let src: &str = "let x: float = 1; fn f (a: int) -> str {}";
+        let mut lex = Token::lexer(src);
+
+        assert_eq!(lex.next(), Some(Ok(Token::TokLet)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokIdentifier("x".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::TokColon)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokTypeFloat)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokEquals)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokInt(1))));
+        assert_eq!(lex.next(), Some(Ok(Token::TokSemi)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokFn)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokIdentifier("f".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::TokLpar)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokIdentifier("a".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::TokColon)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokTypeInt)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokRpar)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokArrow)));
+        assert_eq!(lex.next(), Some(Ok(Token::TokTypeStr)))
+    }
+
+    #[test]
+    fn comments_as_trivia() {
+        let src: &str = "# first\nlet a = 1; # second\n";
+        let mut lex = Lexer::new(src);
+        assert_eq!(lex.next(), Some(Ok((8, Token::TokLet, 11))));
+        assert_eq!(lex.nth(3), Some(Ok((17, Token::TokSemi, 18))));
+        assert_eq!(lex.next(), None);
+
+        let mut lex = Lexer::with_comments(src);
+        assert_eq!(
+            lex.next(),
+            Some(Ok((0, Token::TokComment("# first".to_string()), 7)))
+        );
+        assert_eq!(
+            collect_comments(src),
+            vec![
+                Comment {
+                    start: 0,
+                    text: "# first".to_string(),
+                    trailing: false
+                },
+                Comment {
+                    start: 19,
+                    text: "# second".to_string(),
+                    trailing: true
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn locates_unexpected_characters() {
+        let src = "let a = 1;\nlet b = a & 2;";
+        let err = Lexer::new(src).find_map(Result::err).unwrap();
+        assert_eq!(
+            err,
+            LexicalError::UnexpectedCharacter {
+                span: Span { start: 21, end: 22 },
+                slice: "&".to_string()
+            }
+        );
+        assert_eq!(
+            err.describe(src),
+            "unexpected character `&` at line 2, column 11\nhint: the logical operators are && and ||"
+        );
+    }
+
+    #[test]
+    fn locates_out_of_range_ints_and_parse_errors() {
+        let src = "let a = 1;\nlet b = 99999999999999999999;";
+        let err = Lexer::new(src).find_map(Result::err).unwrap();
+        assert!(err
+            .describe(src)
+            .starts_with("the int 99999999999999999999 at line 2, column 9 is too large"));
+        let err = crate::parsing::parse_program("let a = 1").unwrap_err();
+        assert!(
+            err.contains("unexpected end of the source at line 1, column 10, expected one of"),
+            "{}",
+            err
+        );
+        assert!(err.contains("`;`"), "{}", err);
+        let err = crate::parsing::parse_program("printl(1));").unwrap_err();
+        assert!(
+            err.contains("unexpected `)` at line 1, column 10"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn reads_uppercase_identifiers() {
+        let src = "let MAX = 10; fn Counter () -> { } let True = Counter();";
+        let identifiers: Vec<String> = Lexer::new(src)
+            .filter_map(|token| match token.unwrap().1 {
+                Token::TokIdentifier(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(identifiers, ["MAX", "Counter", "True", "Counter"]);
+        assert!(ProgramParser::new().parse(Lexer::new(src)).is_ok());
+    }
+
+    #[test]
+    fn reads_unicode_strings_and_identifiers() {
+        let src = r#"let café = "日本 \"ok\"\n\u{e9}";"#;
+        let tokens: Vec<Token> = Lexer::new(src).map(|token| token.unwrap().1).collect();
+        assert_eq!(tokens[1], Token::TokIdentifier("café".to_string()));
+        assert_eq!(
+            tokens[3],
+            Token::TokString("\"日本 \"ok\"\né\"".to_string())
+        );
+        assert_eq!(quote_string("日本 \"ok\"\né"), r#""日本 \"ok\"\né""#);
+        let err = Lexer::new(r#"let s = "a\qb";"#)
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(
+            err,
+            LexicalError::InvalidEscape {
+                span: Span { start: 10, end: 12 },
+                sequence: r"\q".to_string()
+            }
+        );
+    }
+    #[test]
+    fn reads_char_literals() {
+        let src = r"let c: char = 'é'; '\n' '\'' 'ab'";
+        let tokens: Vec<Result<Token, LexicalError>> = Lexer::new(src)
+            .map(|token| token.map(|(_, token, _)| token))
+            .collect();
+        assert_eq!(tokens[3], Ok(Token::TokTypeChar));
+        assert_eq!(tokens[5], Ok(Token::TokChar('é')));
+        assert_eq!(tokens[7], Ok(Token::TokChar('\n')));
+        assert_eq!(tokens[8], Ok(Token::TokChar('\'')));
+        let err = tokens[9].clone().unwrap_err();
+        assert_eq!(
+            err.describe(src),
+            "the char literal 'ab' at line 1, column 30 does not hold one character\n\
+             hint: strings are written between double quotes"
+        );
+        assert_eq!(quote_char('\''), r"'\''");
+    }
 }
@@ -0,0 +1,518 @@
+use crate::parsing::ast::{BinaryOperator, Expression, Parameter, Statement, StatementKind, Type};
+use crate::parsing::lexer::{collect_comments, quote_char, quote_string, Comment, Lexer, Token};
+use crate::parsing::parse_program;
+use std::collections::VecDeque;
+
+const INDENT: &str = "    ";
+
+/// Render a program back into formatted Grim source code.
+pub fn print_program(tree: &[Statement]) -> String {
+    let mut printer = Printer {
+        src: None,
+        comments: VecDeque::new(),
+        last_end: 0,
+        out: String::new(),
+    };
+    printer.print_block(tree, 0, 0);
+    printer.out
+}
+
+/// Render an expression with the minimum amount of parentheses.
+pub fn print_expression(expr: &Expression) -> String {
+    match expr {
+        Expression::Float(x) => print_float(*x),
+        Expression::Int(x) => x.to_string(),
+        Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => name.clone(),
+        Expression::Str(x) => quote_string(&x[1..x.len() - 1]),
+        Expression::Char(x) => quote_char(*x),
+        Expression::Bool(x) => x.to_string(),
+        Expression::FunctionCall { name, arguments } => {
+            format!("{}({})", name, print_arguments(arguments))
+        }
+        Expression::BinaryOperation { lhs, operator, rhs } => {
+            let level = precedence(operator);
+            // Operators are left associative, so the right operand needs parentheses on ties
+            let lhs = match lhs.as_ref() {
+                Expression::BinaryOperation { operator, .. } if precedence(operator) > level => {
+                    format!("({})", print_expression(lhs))
+                }
+                _ => print_expression(lhs),
+            };
+            let rhs = match rhs.as_ref() {
+                Expression::BinaryOperation { operator, .. } if precedence(operator) >= level => {
+                    format!("({})", print_expression(rhs))
+                }
+                _ => print_expression(rhs),
+            };
+            format!("{} {} {}", lhs, operator, rhs)
+        }
+        Expression::UnaryOperation { operator, rhs } => {
+            let operand = print_expression(rhs);
+            // A minus before a negative operand would be read as the decrement `--`
+            match rhs.as_ref() {
+                Expression::BinaryOperation { .. } => format!("{}({})", operator, operand),
+                _ if operand.starts_with('-') => format!("{}({})", operator, operand),
+                _ => format!("{}{}", operator, operand),
+            }
+        }
+        Expression::Index { target, index } => match target.as_ref() {
+            Expression::BinaryOperation { .. } | Expression::UnaryOperation { .. } => {
+                format!(
+                    "({})[{}]",
+                    print_expression(target),
+                    print_expression(index)
+                )
+            }
+            _ => format!("{}[{}]", print_expression(target), print_expression(index)),
+        },
+    }
+}
+
+/// Render a float as the lexer reads it, with digits on both sides of the point and no exponent.
+fn print_float(x: f64) -> String {
+    let printed = x.to_string();
+    match x.is_finite() && !printed.contains('.') {
+        true => format!("{}.0", printed),
+        false => printed,
+    }
+}
+
+/// Keyword marking a declaration as mutable, followed by its space.
+fn mut_keyword(mutable: bool) -> &'static str {
+    if mutable {
+        "mut "
+    } else {
+        ""
+    }
+}
+
+/// Format a whole source file, keeping its comments and single blank lines between statements.
+pub fn format_source(src: &str) -> Result<String, String> {
+    let tree = parse_program(src)?;
+    let mut printer = Printer {
+        src: Some(src),
+        comments: collect_comments(src).into(),
+        last_end: 0,
+        out: String::new(),
+    };
+    printer.print_block(&tree, 0, src.len());
+    Ok(printer.out)
+}
+
+struct Printer<'src> {
+    src: Option<&'src str>,
+    comments: VecDeque<Comment>,
+    last_end: usize,
+    out: String,
+}
+
+impl<'src> Printer<'src> {
+    /// Print the statements of a block, `end` is the offset where the block closes in the source.
+    fn print_block(&mut self, tree: &[Statement], depth: usize, end: usize) {
+        for (i, stmt) in tree.iter().enumerate() {
+            // A comment closing the line of the previous statement goes before any blank line
+            if self
+                .comments
+                .front()
+                .is_some_and(|c| c.trailing && c.start < stmt.span.start)
+            {
+                self.print_comments_before(self.comments[0].start + 1, depth);
+            }
+            let is_function = matches!(stmt.kind, StatementKind::FunctionDeclaration { .. });
+            // Function declarations are kept apart from the surrounding code
+            if i > 0 && (is_function || self.blank_line_before(stmt.span.start)) {
+                self.blank_line();
+            }
+            self.print_comments_before(stmt.span.start, depth);
+            self.last_end = stmt.span.start;
+            self.print_statement(stmt, depth);
+            self.last_end = stmt.span.end;
+            // A comment closing the line of the statement stays on it, before any blank line
+            let next = tree.get(i + 1).map_or(end, |next| next.span.start);
+            if self
+                .comments
+                .front()
+                .is_some_and(|c| c.trailing && c.start < next)
+            {
+                self.print_comments_before(self.comments[0].start + 1, depth);
+            }
+            if is_function && i + 1 < tree.len() {
+                self.blank_line();
+            }
+        }
+        self.print_comments_before(end, depth);
+    }
+
+    fn print_comments_before(&mut self, offset: usize, depth: usize) {
+        while self.comments.front().is_some_and(|c| c.start < offset) {
+            let comment = self.comments.pop_front().unwrap();
+            if comment.trailing && self.out.ends_with('\n') {
+                self.out.pop();
+                self.out.push_str(&format!(" {}\n", comment.text));
+            } else {
+                if !self.out.is_empty() && self.blank_line_before(comment.start) {
+                    self.blank_line();
+                }
+                self.out
+                    .push_str(&format!("{}{}\n", INDENT.repeat(depth), comment.text));
+            }
+            self.last_end = comment.start + comment.text.len();
+        }
+    }
+
+    /// Check if the source has an empty line between the last printed item and the offset.
+    fn blank_line_before(&self, offset: usize) -> bool {
+        let Some(src) = self.src else {
+            return false;
+        };
+        let lines: Vec<&str> = src[self.last_end.min(offset)..offset].split('\n').collect();
+        lines.len() > 2
+            && lines[1..lines.len() - 1]
+                .iter()
+                .any(|line| line.trim().is_empty())
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn print_statement(&mut self, stmt: &Statement, depth: usize) {
+        self.out.push_str(&INDENT.repeat(depth));
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                type_annotation,
+                value,
+                mutable,
+            } => {
+                let annotation = type_annotation
+                    .map(|t| format!(": {}", t))
+                    .unwrap_or_default();
+                self.out.push_str(&format!(
+                    "let {}{}{} = {};\n",
+                    mut_keyword(*mutable),
+                    name,
+                    annotation,
+                    print_expression(value)
+                ));
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                self.out
+                    .push_str(&format!("{} = {};\n", name, print_expression(value)));
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                mutable,
+            } => {
+                let names = names.join(", ");
+                let values = values
+                    .iter()
+                    .map(|value| print_expression(value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                self.out.push_str(&match declaration {
+                    true => format!("let {}({}) = ({});\n", mut_keyword(*mutable), names, values),
+                    false => format!("{} = {};\n", names, values),
+                });
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                self.out
+                    .push_str(&format!("if {} ", print_expression(cond)));
+                self.print_body(then_part, depth, stmt.span.end.saturating_sub(1));
+                self.out.push('\n');
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                self.out
+                    .push_str(&format!("if {} ", print_expression(cond)));
+                let then_end = self.then_part_end(stmt, then_part);
+                self.print_body(then_part, depth, then_end);
+                self.out.push_str(" else ");
+                self.print_body(else_part, depth, stmt.span.end.saturating_sub(1));
+                self.out.push('\n');
+            }
+            StatementKind::WhileStatement { cond, body, label } => {
+                self.out.push_str(&format!(
+                    "{}while {} ",
+                    print_label(label),
+                    print_expression(cond)
+                ));
+                self.print_body(body, depth, stmt.span.end.saturating_sub(1));
+                self.out.push('\n');
+            }
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                label,
+            } => {
+                let step = step
+                    .as_ref()
+                    .map(|step| format!(" step {}", print_expression(step)))
+                    .unwrap_or_default();
+                self.out.push_str(&format!(
+                    "{}for {} in {}..{}{} ",
+                    print_label(label),
+                    variable,
+                    print_expression(start),
+                    print_expression(end),
+                    step
+                ));
+                self.print_body(body, depth, stmt.span.end.saturating_sub(1));
+                self.out.push('\n');
+            }
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                return_type,
+                body,
+                doc,
+            } => {
+                if let Some(doc) = doc {
+                    for line in doc.split('\n') {
+                        let line = format!("## {}", line);
+                        self.out.push_str(line.trim_end());
+                        self.out.push('\n');
+                        self.out.push_str(&INDENT.repeat(depth));
+                    }
+                }
+                self.out.push_str(&format!(
+                    "{} ",
+                    print_signature(name, arguments, return_type)
+                ));
+                self.print_body(body, depth, stmt.span.end.saturating_sub(1));
+                self.out.push('\n');
+            }
+            StatementKind::FunctionCallStatement { name, arguments } => {
+                self.out
+                    .push_str(&format!("{}({});\n", name, print_arguments(arguments)));
+            }
+            StatementKind::SpawnStatement { name, arguments } => {
+                self.out.push_str(&format!(
+                    "spawn {}({});\n",
+                    name,
+                    print_arguments(arguments)
+                ));
+            }
+            StatementKind::ReturnStatement { value } => {
+                self.out
+                    .push_str(&format!("return {};\n", print_expression(value)));
+            }
+            StatementKind::PrintStatement { content } => {
+                self.out
+                    .push_str(&format!("print({});\n", print_expression(content)));
+            }
+            StatementKind::PrintLineStatement { content } => {
+                self.out
+                    .push_str(&format!("printl({});\n", print_expression(content)));
+            }
+            StatementKind::InputStatement { name } => {
+                self.out.push_str(&format!("input({});\n", name));
+            }
+            StatementKind::BreakStatement { label } => {
+                self.out
+                    .push_str(&format!("break{};\n", print_target(label)));
+            }
+            StatementKind::ContinueStatement { label } => {
+                self.out
+                    .push_str(&format!("continue{};\n", print_target(label)));
+            }
+            StatementKind::BreakpointStatement => self.out.push_str("breakpoint;\n"),
+            StatementKind::IncludeStatement { path } => {
+                self.out.push_str(&format!("include \"{}\";\n", path))
+            }
+            StatementKind::MeasureStatement { label, body } => {
+                self.out.push_str(&format!("measure \"{}\" ", label));
+                self.print_body(body, depth, stmt.span.end.saturating_sub(1));
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Offset of the brace closing the then part of an if-else statement, found before the `else`.
+    fn then_part_end(&self, stmt: &Statement, then_part: &[Statement]) -> usize {
+        let Some(src) = self.src else {
+            return stmt.span.end;
+        };
+        let from = then_part.last().map_or(stmt.span.start, |s| s.span.end);
+        let mut brace = from;
+        for (start, token, _) in Lexer::new(&src[from..stmt.span.end]).flatten() {
+            match token {
+                Token::TokRbrace => brace = from + start,
+                Token::TokElse => break,
+                _ => (),
+            }
+        }
+        brace
+    }
+
+    fn print_body(&mut self, body: &[Statement], depth: usize, end: usize) {
+        let has_comments = self.comments.front().is_some_and(|c| c.start < end);
+        if body.is_empty() && !has_comments {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        self.print_block(body, depth + 1, end);
+        self.out.push_str(&INDENT.repeat(depth));
+        self.out.push('}');
+    }
+}
+
+/// Label before a loop, followed by its colon.
+fn print_label(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map(|label| format!("{}: ", label))
+        .unwrap_or_default()
+}
+
+/// Label after a break or a continue, preceded by its space.
+fn print_target(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map(|label| format!(" {}", label))
+        .unwrap_or_default()
+}
+
+fn print_arguments(arguments: &[Box<Expression>]) -> String {
+    arguments
+        .iter()
+        .map(|arg| print_expression(arg))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Render the head of a function declaration, up to its return type.
+pub fn print_signature(name: &str, arguments: &[Parameter], return_type: &Option<Type>) -> String {
+    format!(
+        "fn {} ({}) ->{}",
+        name,
+        print_parameters(arguments),
+        return_type.map(|t| format!(" {}", t)).unwrap_or_default()
+    )
+}
+
+fn print_parameters(parameters: &[Parameter]) -> String {
+    parameters
+        .iter()
+        .map(|param| match param.type_annotation {
+            Some(t) => format!("{}: {}", param.name, t),
+            None => param.name.clone(),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Precedence level of the operator in the grammar, higher binds less.
+fn precedence(operator: &BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod | BinaryOperator::And => 3,
+        BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Or => 4,
+        BinaryOperator::Less
+        | BinaryOperator::Greater
+        | BinaryOperator::LessEq
+        | BinaryOperator::GreaterEq
+        | BinaryOperator::CompareEq
+        | BinaryOperator::CompareNeq => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reprint(src: &str) -> String {
+        print_program(&parse_program(src).unwrap())
+    }
+
+    #[test]
+    fn prints_indented_source() {
+        let printed = reprint(
+            "fn f(a:int,b)->float{if a>0{return (a+b)*2.0;}else{return -(a-b);}} let x=f(1,2);",
+        );
+        assert_eq!(
+            printed,
+            "fn f (a: int, b) -> float {\n    \
+                 if a > 0 {\n        return (a + b) * 2.0;\n    \
+                 } else {\n        return -(a - b);\n    }\n}\n\n\
+             let x = f(1, 2);\n"
+        );
+    }
+
+    #[test]
+    fn printing_is_stable() {
+        let src =
+            "let a = 1 - (2 - 3); let mut b = true == (1 < 2); while !b { b = a % 2 == 0 || b; }";
+        let printed = reprint(src);
+        assert_eq!(reprint(&printed), printed);
+        assert!(printed.contains("1 - (2 - 3)"));
+        assert!(printed.contains("true == (1 < 2)"));
+    }
+
+    #[test]
+    fn formatting_keeps_comments() {
+        let src = "# header\nlet mut a=1; # trailing\n\n\nwhile a<3{\n# inside\na=a+1;}\n\
+                   if a>0{printl(a);} # after then\nelse{}\n# final\n";
+        let formatted = format_source(src).unwrap();
+        assert_eq!(
+            formatted,
+            "# header\nlet mut a = 1; # trailing\n\n\
+             while a < 3 {\n    # inside\n    a = a + 1;\n}\n\
+             if a > 0 {\n    printl(a);\n} else { # after then\n}\n# final\n"
+        );
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn prints_negations_that_parse_back() {
+        let printed = reprint("let c = -(-a); let d = -(-1); let e = !!true; let f = - -2.5;");
+        assert!(printed.contains("let c = -(-a);"), "{}", printed);
+        assert!(printed.contains("let f = -(-2.5);"), "{}", printed);
+        assert_eq!(reprint(&printed), printed);
+    }
+
+    #[test]
+    fn prints_floats_that_parse_back() {
+        for x in [1e-7, 1e20, 1.5e300, 5e-324, 0.1, 12.5] {
+            let printed = print_expression(&Expression::Float(x));
+            assert!(!printed.contains('e'), "{}", printed);
+            assert_eq!(
+                crate::parsing::parse_expression(&printed),
+                Ok(Box::new(Expression::Float(x)))
+            );
+        }
+    }
+
+    #[test]
+    fn formatting_is_idempotent_with_comments_after_functions() {
+        let src =
+            "fn f () -> {} # trailing\nfn g () -> {\n    return 1;\n} # after g\nlet a = 1;\n";
+        let formatted = format_source(src).unwrap();
+        assert_eq!(
+            formatted,
+            "fn f () -> {} # trailing\n\nfn g () -> {\n    return 1;\n} # after g\n\nlet a = 1;\n"
+        );
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn formatting_keeps_doc_comments() {
+        let src = "## Sum.\n##\nfn f(a)->{## Inner.\nfn g()->{}}\n## stray\nlet a=1;\n";
+        let formatted = format_source(src).unwrap();
+        assert_eq!(
+            formatted,
+            "## Sum.\n##\nfn f (a) -> {\n    ## Inner.\n    fn g () -> {}\n}\n\n## stray\nlet a = 1;\n"
+        );
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+}
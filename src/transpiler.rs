@@ -0,0 +1,9 @@
+pub mod javascript;
+pub mod rust;
+
+/// Languages a Grim program can be transpiled to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Rust,
+    JavaScript,
+}
@@ -0,0 +1,489 @@
+use crate::parsing::ast::{
+    declared_functions, BinaryOperator, Expression, Statement, StatementKind, UnaryOperator,
+};
+use crate::parsing::include::not_included;
+use std::collections::HashSet;
+
+const INDENT: &str = "    ";
+
+/// Runtime put before every generated program.
+const PRELUDE: &str = include_str!("javascript_prelude.js");
+
+/// JavaScript reserved words that are valid Grim identifiers, and the function wrapping the program.
+const KEYWORDS: [&str; 43] = [
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "function",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "with",
+    "yield",
+    "main",
+];
+
+/// Builtins available in the generated programs, with their name in the runtime.
+const BUILTINS: [(&str, &str); 8] = [
+    ("assert", "assert"),
+    ("assert_eq", "assertEq"),
+    ("flush", "flush"),
+    ("eprint", "eprint"),
+    ("eprintl", "eprintLine"),
+    ("exit", "exit"),
+    ("panic", "panic"),
+    ("concat", "concat"),
+];
+
+/// Constants of the interpreter, with the JavaScript expression of their value.
+const CONSTANTS: [(&str, &str); 4] = [
+    ("PI", "Math.PI"),
+    ("E", "Math.E"),
+    ("INF", "Infinity"),
+    ("NAN", "NaN"),
+];
+
+/// globals: functions declared at the top level, every function body can call them.
+struct JavaScriptGenerator {
+    functions: Vec<HashSet<String>>,
+    globals: HashSet<String>,
+    out: String,
+}
+
+/// Generate a readable JavaScript program equivalent to a checked Grim program.
+///
+/// The operators are the ones of JavaScript, so type errors are not detected at runtime.
+/// Threads and channels have no equivalent and are rejected.
+pub fn transpile_to_javascript(tree: &[Statement]) -> Result<String, String> {
+    let mut generator = JavaScriptGenerator {
+        functions: vec![],
+        globals: declared_functions(tree).cloned().collect(),
+        out: String::new(),
+    };
+    generator
+        .out
+        .push_str("// Generated from a Grim program.\n\"use strict\";\n\n");
+    generator.out.push_str(PRELUDE);
+    generator.out.push_str("\nfunction main() {\n");
+    generator.block(tree, 1, HashSet::new(), false)?;
+    generator.out.push_str("}\n\n$grim.run(main);\n");
+    Ok(generator.out)
+}
+
+/// Name of a Grim variable or function in the generated code.
+fn identifier(name: &str) -> String {
+    match KEYWORDS.contains(&name) {
+        true => format!("{}_", name),
+        false => name.to_string(),
+    }
+}
+
+/// Label before a loop, followed by its colon.
+fn loop_label(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map(|label| format!("{}: ", identifier(label)))
+        .unwrap_or_default()
+}
+
+/// Label after a break or a continue, preceded by its space.
+fn loop_target(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map(|label| format!(" {}", identifier(label)))
+        .unwrap_or_default()
+}
+
+/// Precedence of the operators in JavaScript, higher binds tighter.
+fn precedence(operator: &BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 12,
+        BinaryOperator::Add | BinaryOperator::Sub => 11,
+        BinaryOperator::Less
+        | BinaryOperator::Greater
+        | BinaryOperator::LessEq
+        | BinaryOperator::GreaterEq => 9,
+        BinaryOperator::CompareEq | BinaryOperator::CompareNeq => 8,
+        BinaryOperator::And => 4,
+        BinaryOperator::Or => 3,
+    }
+}
+
+fn binary_symbol(operator: &BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Sub => "-",
+        BinaryOperator::Mul => "*",
+        BinaryOperator::Div => "/",
+        BinaryOperator::Mod => "%",
+        BinaryOperator::And => "&&",
+        BinaryOperator::Or => "||",
+        BinaryOperator::Less => "<",
+        BinaryOperator::Greater => ">",
+        BinaryOperator::LessEq => "<=",
+        BinaryOperator::GreaterEq => ">=",
+        BinaryOperator::CompareEq => "===",
+        BinaryOperator::CompareNeq => "!==",
+    }
+}
+
+impl JavaScriptGenerator {
+    fn line(&mut self, depth: usize, line: &str) {
+        self.out.push_str(&INDENT.repeat(depth));
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// Generate the statements of a block, in_function tells if it belongs to a function body.
+    fn block(
+        &mut self,
+        tree: &[Statement],
+        depth: usize,
+        mut functions: HashSet<String>,
+        in_function: bool,
+    ) -> Result<(), String> {
+        // Function declarations are hoisted in JavaScript too
+        functions.extend(declared_functions(tree).cloned());
+        self.functions.push(functions);
+        for stmt in tree {
+            self.statement(stmt, depth, in_function)?;
+        }
+        self.functions.pop();
+        Ok(())
+    }
+
+    fn statement(
+        &mut self,
+        stmt: &Statement,
+        depth: usize,
+        in_function: bool,
+    ) -> Result<(), String> {
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                value,
+                mutable,
+                ..
+            } => {
+                let value = self.expression(value)?;
+                let keyword = if *mutable { "let" } else { "const" };
+                self.line(
+                    depth,
+                    &format!("{} {} = {};", keyword, identifier(name), value),
+                );
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                let value = self.expression(value)?;
+                self.line(depth, &format!("{} = {};", identifier(name), value));
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                mutable,
+            } => {
+                let names: Vec<String> = names.iter().map(|name| identifier(name)).collect();
+                let values = values
+                    .iter()
+                    .map(|value| self.expression(value))
+                    .collect::<Result<Vec<String>, String>>()?;
+                let keyword = match (declaration, mutable) {
+                    (true, true) => "let ",
+                    (true, false) => "const ",
+                    (false, _) => "",
+                };
+                self.line(
+                    depth,
+                    &format!(
+                        "{}[{}] = [{}];",
+                        keyword,
+                        names.join(", "),
+                        values.join(", ")
+                    ),
+                );
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                let cond = self.expression(cond)?;
+                self.line(depth, &format!("if ({}) {{", cond));
+                self.block(then_part, depth + 1, HashSet::new(), in_function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                let cond = self.expression(cond)?;
+                self.line(depth, &format!("if ({}) {{", cond));
+                self.block(then_part, depth + 1, HashSet::new(), in_function)?;
+                self.line(depth, "} else {");
+                self.block(else_part, depth + 1, HashSet::new(), in_function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::WhileStatement { cond, body, label } => {
+                let cond = self.expression(cond)?;
+                self.line(depth, &format!("{}while ({}) {{", loop_label(label), cond));
+                self.block(body, depth + 1, HashSet::new(), in_function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                label,
+            } => {
+                let step = match step {
+                    Some(step) => self.expression(step)?,
+                    None => "1".to_string(),
+                };
+                self.line(
+                    depth,
+                    &format!(
+                        "{}for (let {} of $grim.range({}, {}, {})) {{",
+                        loop_label(label),
+                        identifier(variable),
+                        self.expression(start)?,
+                        self.expression(end)?,
+                        step
+                    ),
+                );
+                self.block(body, depth + 1, HashSet::new(), in_function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                body,
+                ..
+            } => {
+                let parameters: Vec<String> =
+                    arguments.iter().map(|arg| identifier(&arg.name)).collect();
+                self.line(
+                    depth,
+                    &format!(
+                        "function {}({}) {{",
+                        identifier(name),
+                        parameters.join(", ")
+                    ),
+                );
+                // The function body only sees its arguments, itself and the global functions
+                let outer = std::mem::replace(&mut self.functions, vec![self.globals.clone()]);
+                let result = self.block(body, depth + 1, HashSet::from([name.clone()]), true);
+                self.functions = outer;
+                result?;
+                // Functions without a return statement return 0
+                if !matches!(
+                    body.last().map(|stmt| &stmt.kind),
+                    Some(StatementKind::ReturnStatement { .. })
+                ) {
+                    self.line(depth + 1, "return 0;");
+                }
+                self.line(depth, "}");
+            }
+            StatementKind::FunctionCallStatement { name, arguments } => {
+                let call = self.call(name, arguments)?;
+                self.line(depth, &format!("{};", call));
+            }
+            StatementKind::SpawnStatement { .. } => {
+                return Err("spawn is not supported by the javascript target".to_string())
+            }
+            StatementKind::ReturnStatement { value } => {
+                let value = self.expression(value)?;
+                match in_function {
+                    true => self.line(depth, &format!("return {};", value)),
+                    // A return at the top level only ends the program
+                    false => {
+                        self.line(depth, &format!("{};", value));
+                        self.line(depth, "return;");
+                    }
+                }
+            }
+            StatementKind::PrintStatement { content } => {
+                let content = self.expression(content)?;
+                self.line(depth, &format!("$grim.print({});", content));
+            }
+            StatementKind::PrintLineStatement { content } => {
+                let content = self.expression(content)?;
+                self.line(depth, &format!("$grim.printLine({});", content));
+            }
+            StatementKind::InputStatement { name } => {
+                let variable = identifier(name);
+                self.line(
+                    depth,
+                    &format!("{} = $grim.input({:?}, {});", variable, name, variable),
+                );
+            }
+            StatementKind::BreakStatement { label } => {
+                self.line(depth, &format!("break{};", loop_target(label)))
+            }
+            StatementKind::ContinueStatement { label } => {
+                self.line(depth, &format!("continue{};", loop_target(label)))
+            }
+            StatementKind::BreakpointStatement => self.line(depth, "debugger;"),
+            StatementKind::IncludeStatement { path } => return Err(not_included(path)),
+            StatementKind::MeasureStatement { label, body } => {
+                // The time is printed when the body is left by a return or a break, not by an error
+                self.line(depth, "{");
+                self.line(
+                    depth + 1,
+                    &format!("const $measure = $grim.measure({:?});", label),
+                );
+                self.line(depth + 1, "try {");
+                self.block(body, depth + 2, HashSet::new(), in_function)?;
+                self.line(depth + 1, "} catch (err) {");
+                self.line(depth + 2, "$measure.failed = !(err instanceof $grim.Exit);");
+                self.line(depth + 2, "throw err;");
+                self.line(depth + 1, "} finally {");
+                self.line(depth + 2, "$grim.measured($measure);");
+                self.line(depth + 1, "}");
+                self.line(depth, "}");
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&self, name: &str, arguments: &[Box<Expression>]) -> Result<String, String> {
+        let arguments: Result<Vec<String>, String> =
+            arguments.iter().map(|arg| self.expression(arg)).collect();
+        let arguments = arguments?.join(", ");
+        if self
+            .functions
+            .iter()
+            .any(|functions| functions.contains(name))
+        {
+            return Ok(format!("{}({})", identifier(name), arguments));
+        }
+        match BUILTINS.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, runtime_name)) => Ok(format!("$grim.{}({})", runtime_name, arguments)),
+            None => Err(format!(
+                "Function `{}` does not exist in the javascript target",
+                name
+            )),
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> Result<String, String> {
+        Ok(match expr {
+            Expression::Int(x) => x.to_string(),
+            Expression::Float(x) => format!("{:?}", x),
+            Expression::Bool(x) => x.to_string(),
+            // JSON strings are valid JavaScript strings
+            Expression::Str(x) => serde_json::to_string(&x[1..x.len() - 1]).unwrap(),
+            Expression::Char(x) => serde_json::to_string(x).unwrap(),
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                match CONSTANTS.iter().find(|(constant, _)| constant == name) {
+                    Some((_, value)) => value.to_string(),
+                    None => identifier(name),
+                }
+            }
+            Expression::FunctionCall { name, arguments } => self.call(name, arguments)?,
+            Expression::BinaryOperation { lhs, operator, rhs } => {
+                let level = precedence(operator);
+                // Operators are left associative, so the right operand needs parentheses on ties
+                let lhs = match lhs.as_ref() {
+                    Expression::BinaryOperation { operator, .. }
+                        if precedence(operator) < level =>
+                    {
+                        format!("({})", self.expression(lhs)?)
+                    }
+                    _ => self.expression(lhs)?,
+                };
+                let rhs = match rhs.as_ref() {
+                    Expression::BinaryOperation { operator, .. }
+                        if precedence(operator) <= level =>
+                    {
+                        format!("({})", self.expression(rhs)?)
+                    }
+                    _ => self.expression(rhs)?,
+                };
+                format!("{} {} {}", lhs, binary_symbol(operator), rhs)
+            }
+            Expression::UnaryOperation { operator, rhs } => {
+                let symbol = match operator {
+                    UnaryOperator::Not => "!",
+                    UnaryOperator::Minus => "-",
+                };
+                match rhs.as_ref() {
+                    // Two minus signs in a row would be a decrement
+                    Expression::BinaryOperation { .. } | Expression::UnaryOperation { .. } => {
+                        format!("{}({})", symbol, self.expression(rhs)?)
+                    }
+                    _ => format!("{}{}", symbol, self.expression(rhs)?),
+                }
+            }
+            Expression::Index { target, index } => format!(
+                "$grim.index({}, {})",
+                self.expression(target)?,
+                self.expression(index)?
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    fn transpiled(src: &str) -> Result<String, String> {
+        transpile_to_javascript(&ProgramParser::new().parse(Lexer::new(src)).unwrap())
+    }
+
+    #[test]
+    fn generates_readable_code() {
+        let program = transpiled(
+            "fn new (n: int) -> int { if n < 2 { return 1; } return n * new(n - 1); } \
+             let x = 1 < 2 && true; let mut y = (1 + 2) * -(3 - 4); input(y); print(new(y) == 6);",
+        )
+        .unwrap();
+        let main = &program[program.find("function main").unwrap()..];
+        assert_eq!(
+            main,
+            "function main() {\n    \
+             function new_(n) {\n        \
+             if (n < 2) {\n            return 1;\n        }\n        \
+             return n * new_(n - 1);\n    }\n    \
+             const x = 1 < (2 && true);\n    \
+             let y = (1 + 2) * -(3 - 4);\n    \
+             y = $grim.input(\"y\", y);\n    \
+             $grim.print(new_(y) === 6);\n}\n\n$grim.run(main);\n"
+        );
+        assert!(transpiled("fn f () -> { } spawn f();").is_err());
+    }
+}
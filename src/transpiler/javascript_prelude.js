@@ -0,0 +1,180 @@
+// Runtime of the JavaScript programs generated from Grim scripts.
+// Set globalThis.grimIO to { print(text), readLine() } before loading the program to redirect its IO,
+// by default it uses the standard streams under Node.js and the console and prompt() in a browser.
+// readLine() returns null at the end of the input, as prompt() does when it is cancelled.
+// An optional printError(text) receives eprint and eprintl, the standard error by default.
+const $grim = (() => {
+    const node = typeof process !== "undefined" && typeof require === "function";
+    let pending = "";
+
+    function nodeReadLine() {
+        const fs = require("fs");
+        const byte = Buffer.alloc(1);
+        const bytes = [];
+        let read = 0;
+        while ((read = fs.readSync(0, byte, 0, 1, null)) === 1 && byte[0] !== 10) {
+            bytes.push(byte[0]);
+        }
+        return read === 0 && bytes.length === 0 ? null : Buffer.from(bytes).toString("utf8");
+    }
+
+    const io = globalThis.grimIO ?? {
+        print(text) {
+            if (node) {
+                process.stdout.write(text);
+                return;
+            }
+            // The console only prints whole lines
+            const lines = (pending + text).split("\n");
+            pending = lines.pop();
+            lines.forEach((line) => console.log(line));
+        },
+        readLine() {
+            return node ? nodeReadLine() : prompt("");
+        },
+    };
+
+    function print(value) {
+        io.print(String(value));
+    }
+
+    function printLine(value) {
+        io.print(String(value) + "\n");
+    }
+
+    // A browser console shows each diagnostic on its own line
+    function printError(text) {
+        if (io.printError) {
+            io.printError(text);
+        } else if (node) {
+            process.stderr.write(text);
+        } else {
+            console.error(text);
+        }
+        return true;
+    }
+
+    function eprint(value) {
+        return printError(String(value));
+    }
+
+    function eprintLine(value) {
+        return printError(String(value) + "\n");
+    }
+
+    function concat(...parts) {
+        return parts.join("");
+    }
+
+    // Start of a measure block, its time is printed when it ends unless it failed
+    function measure(label) {
+        return { label, start: performance.now(), failed: false };
+    }
+
+    function measured(measure) {
+        if (!measure.failed) {
+            printError(`${measure.label} took ${(performance.now() - measure.start).toFixed(3)} ms\n`);
+        }
+    }
+
+    // Read a line and parse it with the type of the variable it is assigned to
+    function input(name, current) {
+        const read = io.readLine();
+        if (read === null || read === undefined) {
+            throw new Error(`End of the input, no line left to read \`${name}\``);
+        }
+        const line = read.trim();
+        if (typeof current === "number" && line !== "" && !Number.isNaN(Number(line))) {
+            return Number(line);
+        }
+        if (typeof current === "boolean" && (line === "true" || line === "false")) {
+            return line === "true";
+        }
+        if (typeof current === "string") {
+            return line;
+        }
+        throw new Error(`Error of type incoherence, "${name}" is a ${typeof current}`);
+    }
+
+    function assert(cond, message) {
+        if (!cond) {
+            throw new Error(message === undefined ? "Assertion failed" : `Assertion failed: ${message}`);
+        }
+        return true;
+    }
+
+    function assertEq(lhs, rhs) {
+        if (lhs !== rhs) {
+            throw new Error(`Assertion failed: ${lhs} is not equal to ${rhs}`);
+        }
+        return true;
+    }
+
+    // Values taken by the variable of a for loop, the end is excluded
+    function* range(start, end, step) {
+        if (step === 0) {
+            throw new Error("The step of a for loop cannot be 0");
+        }
+        for (let i = start; step > 0 ? i < end : i > end; i += step) {
+            yield i;
+        }
+    }
+
+    // The text is written as soon as it is printed, only a browser console waits for the end of the line
+    function flush() {
+        return true;
+    }
+
+    // Thrown by exit to leave every function, run ends the program with its code
+    class Exit extends Error {
+        constructor(code) {
+            super(`exit(${code})`);
+            this.code = code;
+        }
+    }
+
+    function exit(code) {
+        if (!Number.isInteger(code)) {
+            throw new Error("exit expects an int");
+        }
+        throw new Exit(code);
+    }
+
+    function panic(message) {
+        throw new Error(`Panic: ${message}`);
+    }
+
+    // Character of a string by code point, chars are strings of one character
+    function index(text, position) {
+        if (typeof text !== "string") {
+            throw new Error(`Values of type ${typeof text} cannot be indexed`);
+        }
+        const chars = Array.from(text);
+        if (!Number.isInteger(position) || position < 0 || position >= chars.length) {
+            throw new Error(`Index ${position} is out of a string of ${chars.length} characters`);
+        }
+        return chars[position];
+    }
+
+    function run(main) {
+        try {
+            main();
+        } catch (err) {
+            const exited = err instanceof Exit;
+            if (!exited) {
+                printLine(`ERROR!\n${err.message}`);
+            }
+            if (node) {
+                process.exitCode = exited ? err.code : 1;
+            }
+        }
+        if (pending !== "") {
+            console.log(pending);
+        }
+    }
+
+    return {
+        print, printLine, eprint, eprintLine, concat, input, assert, assertEq, flush, exit, panic, range, index,
+        measure, measured, Exit, run,
+    };
+})();
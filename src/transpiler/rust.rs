@@ -0,0 +1,555 @@
+use crate::interpreter::builtins::is_builtin;
+use crate::parsing::ast::{
+    declared_functions, BinaryOperator, Expression, Parameter, Statement, StatementKind, Type,
+    UnaryOperator,
+};
+use crate::parsing::include::not_included;
+use std::collections::{HashMap, HashSet};
+
+const INDENT: &str = "    ";
+
+/// Entry point of every generated program, the statements of the script go in `program`.
+const HEADER: &str = "// Generated from a Grim program.
+#![allow(
+    unused_mut,
+    unused_variables,
+    unused_assignments,
+    unreachable_code,
+    dead_code,
+    non_snake_case,
+    uncommon_codepoints,
+    unused_labels
+)]
+
+fn main() {
+    program();
+    grim::join_spawned();
+}
+
+fn program() {
+";
+
+/// Runtime appended to every generated program.
+const PRELUDE: &str = include_str!("rust_prelude.rs");
+
+/// Builtins implemented by the runtime of the generated programs.
+const BUILTINS: [&str; 11] = [
+    "assert",
+    "assert_eq",
+    "channel",
+    "send",
+    "recv",
+    "flush",
+    "eprint",
+    "eprintl",
+    "exit",
+    "panic",
+    "concat",
+];
+
+/// Constants of the interpreter, with the Rust expression of their value.
+const CONSTANTS: [(&str, &str); 4] = [
+    ("PI", "std::f64::consts::PI"),
+    ("E", "std::f64::consts::E"),
+    ("INF", "f64::INFINITY"),
+    ("NAN", "f64::NAN"),
+];
+
+/// Rust keywords that are valid Grim identifiers.
+const KEYWORDS: [&str; 40] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "enum", "extern", "for", "impl",
+    "in", "loop", "match", "mod", "move", "mut", "pub", "ref", "static", "struct", "trait", "type",
+    "unsafe", "use", "where", "abstract", "become", "box", "do", "final", "macro", "override",
+    "priv", "typeof", "unsized", "virtual", "yield", "try", "gen",
+];
+
+/// Names declared by a block that change how the generated code uses them.
+///
+/// functions: the calls to these names do not go to the builtins.
+///
+/// annotations: declared type of the variables, checked again on every assignment.
+#[derive(Default)]
+struct Block {
+    functions: HashSet<String>,
+    annotations: HashMap<String, Type>,
+}
+
+/// globals: functions declared at the top level, every function body can call them.
+struct RustGenerator {
+    blocks: Vec<Block>,
+    globals: HashSet<String>,
+    out: String,
+}
+
+/// Generate a standalone Rust program equivalent to a checked Grim program.
+///
+/// Every value keeps its dynamic type: the operators and the errors follow the interpreter.
+pub fn transpile_to_rust(tree: &[Statement]) -> Result<String, String> {
+    let mut generator = RustGenerator {
+        blocks: vec![],
+        globals: declared_functions(tree).cloned().collect(),
+        out: String::new(),
+    };
+    generator.out.push_str(HEADER);
+    generator.block(tree, 1, Block::default(), None)?;
+    generator.out.push_str("}\n\n");
+    generator.out.push_str(PRELUDE);
+    Ok(generator.out)
+}
+
+/// Name of a Grim variable or function in the generated code.
+fn identifier(name: &str) -> String {
+    match name {
+        // The variants of the prelude would be patterns in a let
+        "self" | "Self" | "super" | "crate" | "_" | "Some" | "None" | "Ok" | "Err" => {
+            format!("{}_", name)
+        }
+        name if KEYWORDS.contains(&name) => format!("r#{}", name),
+        name => name.to_string(),
+    }
+}
+
+/// Rust label of a loop, the keywords cannot be labels so they are suffixed with _.
+fn label_name(label: &str) -> String {
+    match identifier(label) == label {
+        true => format!("'{}", label),
+        false => format!("'{}_", label),
+    }
+}
+
+/// Label before a loop, followed by its colon.
+fn loop_label(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map(|label| format!("{}: ", label_name(label)))
+        .unwrap_or_default()
+}
+
+/// Label after a break or a continue, preceded by its space.
+fn loop_target(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map(|label| format!(" {}", label_name(label)))
+        .unwrap_or_default()
+}
+
+fn type_path(annotation: &Type) -> &'static str {
+    match annotation {
+        Type::Int => "grim::Type::Int",
+        Type::Float => "grim::Type::Float",
+        Type::Bool => "grim::Type::Bool",
+        Type::Str => "grim::Type::Str",
+        Type::Channel => "grim::Type::Channel",
+        Type::Decimal => "grim::Type::Decimal",
+        Type::Char => "grim::Type::Char",
+        Type::Set => "grim::Type::Set",
+    }
+}
+
+impl RustGenerator {
+    fn line(&mut self, depth: usize, line: &str) {
+        self.out.push_str(&INDENT.repeat(depth));
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// Generate the statements of a block.
+    ///
+    /// function: name and return type of the function the block belongs to, if any.
+    fn block(
+        &mut self,
+        tree: &[Statement],
+        depth: usize,
+        mut block: Block,
+        function: Option<(&str, &Option<Type>)>,
+    ) -> Result<(), String> {
+        // Rust items are visible in their whole block, as the Grim functions
+        block.functions.extend(declared_functions(tree).cloned());
+        self.blocks.push(block);
+        for stmt in tree {
+            self.statement(stmt, depth, function)?;
+        }
+        self.blocks.pop();
+        Ok(())
+    }
+
+    fn annotation(&self, name: &str) -> Option<Type> {
+        self.blocks
+            .iter()
+            .rev()
+            .find_map(|block| block.annotations.get(name).copied())
+    }
+
+    fn statement(
+        &mut self,
+        stmt: &Statement,
+        depth: usize,
+        function: Option<(&str, &Option<Type>)>,
+    ) -> Result<(), String> {
+        match &stmt.kind {
+            StatementKind::VariableDeclarationStatement {
+                name,
+                type_annotation,
+                value,
+                mutable,
+            } => {
+                let value = self.annotated(name, self.expression(value)?, type_annotation);
+                if let (Some(block), Some(annotation)) = (self.blocks.last_mut(), type_annotation) {
+                    block.annotations.insert(name.clone(), *annotation);
+                }
+                let keyword = if *mutable { "let mut" } else { "let" };
+                self.line(
+                    depth,
+                    &format!("{} {} = {};", keyword, identifier(name), value),
+                );
+            }
+            StatementKind::AssignmentStatement { name, value } => {
+                let value = self.annotated(name, self.expression(value)?, &self.annotation(name));
+                self.line(depth, &format!("{} = {};", identifier(name), value));
+            }
+            StatementKind::DestructuringStatement {
+                names,
+                values,
+                declaration,
+                mutable,
+            } => {
+                let mut bound = vec![];
+                for (name, value) in names.iter().zip(values) {
+                    let annotation = match declaration {
+                        true => None,
+                        false => self.annotation(name),
+                    };
+                    bound.push(self.annotated(name, self.expression(value)?, &annotation));
+                }
+                let targets: Vec<String> = names
+                    .iter()
+                    .map(|name| match *declaration && *mutable {
+                        true => format!("mut {}", identifier(name)),
+                        false => identifier(name),
+                    })
+                    .collect();
+                let keyword = if *declaration { "let " } else { "" };
+                self.line(
+                    depth,
+                    &format!(
+                        "{}({}) = ({});",
+                        keyword,
+                        targets.join(", "),
+                        bound.join(", ")
+                    ),
+                );
+            }
+            StatementKind::IfStatement { cond, then_part } => {
+                let cond = self.expression(cond)?;
+                self.line(depth, &format!("if grim::condition({}) {{", cond));
+                self.block(then_part, depth + 1, Block::default(), function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::IfElseStatement {
+                cond,
+                then_part,
+                else_part,
+            } => {
+                let cond = self.expression(cond)?;
+                self.line(depth, &format!("if grim::condition({}) {{", cond));
+                self.block(then_part, depth + 1, Block::default(), function)?;
+                self.line(depth, "} else {");
+                self.block(else_part, depth + 1, Block::default(), function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::WhileStatement { cond, body, label } => {
+                let cond = self.expression(cond)?;
+                self.line(
+                    depth,
+                    &format!("{}while grim::condition({}) {{", loop_label(label), cond),
+                );
+                self.block(body, depth + 1, Block::default(), function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::ForStatement {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                label,
+            } => {
+                let step = match step {
+                    Some(step) => self.expression(step)?,
+                    None => "grim::Value::Int(1)".to_string(),
+                };
+                self.line(
+                    depth,
+                    &format!(
+                        "{}for mut {} in grim::range({}, {}, {}) {{",
+                        loop_label(label),
+                        identifier(variable),
+                        self.expression(start)?,
+                        self.expression(end)?,
+                        step
+                    ),
+                );
+                self.block(body, depth + 1, Block::default(), function)?;
+                self.line(depth, "}");
+            }
+            StatementKind::FunctionDeclaration {
+                name,
+                arguments,
+                return_type,
+                body,
+                ..
+            } => {
+                self.function(name, arguments, return_type, body, depth)?;
+            }
+            StatementKind::FunctionCallStatement { name, arguments } => {
+                let call = self.call(name, arguments)?;
+                self.line(depth, &format!("{};", call));
+            }
+            StatementKind::SpawnStatement { name, arguments } => {
+                if !self.is_function(name) {
+                    return Err(format!("Function ({}) cannot be spawned", name));
+                }
+                let arguments = self.arguments(arguments)?;
+                self.line(
+                    depth,
+                    &format!("grim::spawn({}, vec![{}]);", identifier(name), arguments),
+                );
+            }
+            StatementKind::ReturnStatement { value } => {
+                let value = self.expression(value)?;
+                match function {
+                    Some((name, Some(return_type))) => self.line(
+                        depth,
+                        &format!(
+                            "return grim::returned({:?}, {}, {});",
+                            name,
+                            value,
+                            type_path(return_type)
+                        ),
+                    ),
+                    Some((_, None)) => self.line(depth, &format!("return {};", value)),
+                    // A return at the top level only ends the program
+                    None => {
+                        self.line(depth, &format!("{};", value));
+                        self.line(depth, "return;");
+                    }
+                }
+            }
+            StatementKind::PrintStatement { content } => {
+                let content = self.expression(content)?;
+                self.line(depth, &format!("grim::print({});", content));
+            }
+            StatementKind::PrintLineStatement { content } => {
+                let content = self.expression(content)?;
+                self.line(depth, &format!("grim::print_line({});", content));
+            }
+            StatementKind::InputStatement { name } => {
+                let variable = identifier(name);
+                self.line(
+                    depth,
+                    &format!("{} = grim::input({:?}, &{});", variable, name, variable),
+                );
+            }
+            StatementKind::BreakStatement { label } => {
+                self.line(depth, &format!("break{};", loop_target(label)))
+            }
+            StatementKind::ContinueStatement { label } => {
+                self.line(depth, &format!("continue{};", loop_target(label)))
+            }
+            StatementKind::BreakpointStatement => self.line(depth, "// breakpoint"),
+            StatementKind::IncludeStatement { path } => return Err(not_included(path)),
+            StatementKind::MeasureStatement { label, body } => {
+                self.line(depth, "{");
+                self.line(
+                    depth + 1,
+                    &format!("let _measure = grim::measure({:?});", label),
+                );
+                self.block(body, depth + 1, Block::default(), function)?;
+                self.line(depth, "}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate a function taking its arguments as a vector, as the builtins do.
+    fn function(
+        &mut self,
+        name: &str,
+        arguments: &[Parameter],
+        return_type: &Option<Type>,
+        body: &[Statement],
+        depth: usize,
+    ) -> Result<(), String> {
+        self.line(
+            depth,
+            &format!(
+                "fn {}(arguments_: Vec<grim::Value>) -> grim::Value {{",
+                identifier(name)
+            ),
+        );
+        self.line(depth + 1, "let mut arguments_ = arguments_.into_iter();");
+        // The function body only sees its arguments, itself and the global functions
+        let mut block = Block::default();
+        block.functions.insert(name.to_string());
+        for arg in arguments {
+            let value = format!("grim::argument(&mut arguments_, {:?})", arg.name);
+            let value = self.annotated(&arg.name, value, &arg.type_annotation);
+            self.line(
+                depth + 1,
+                &format!("let mut {} = {};", identifier(&arg.name), value),
+            );
+            if let Some(annotation) = arg.type_annotation {
+                block.annotations.insert(arg.name.clone(), annotation);
+            }
+        }
+        let globals = Block {
+            functions: self.globals.clone(),
+            ..Default::default()
+        };
+        let outer = std::mem::replace(&mut self.blocks, vec![globals]);
+        let result = self.block(body, depth + 1, block, Some((name, return_type)));
+        self.blocks = outer;
+        result?;
+        match return_type {
+            Some(return_type) => self.line(
+                depth + 1,
+                &format!(
+                    "grim::fail({:?})",
+                    format!(
+                        "Function ({}) ended without returning a {}",
+                        name, return_type
+                    )
+                ),
+            ),
+            // Functions without a return statement return 0
+            None => self.line(depth + 1, "grim::Value::Int(0)"),
+        }
+        self.line(depth, "}");
+        Ok(())
+    }
+
+    fn annotated(&self, name: &str, value: String, annotation: &Option<Type>) -> String {
+        match annotation {
+            Some(annotation) => format!(
+                "grim::annotate({:?}, {}, {})",
+                name,
+                value,
+                type_path(annotation)
+            ),
+            None => value,
+        }
+    }
+
+    fn is_function(&self, name: &str) -> bool {
+        self.blocks
+            .iter()
+            .any(|block| block.functions.contains(name))
+    }
+
+    fn arguments(&self, arguments: &[Box<Expression>]) -> Result<String, String> {
+        let arguments: Result<Vec<String>, String> =
+            arguments.iter().map(|arg| self.expression(arg)).collect();
+        Ok(arguments?.join(", "))
+    }
+
+    fn call(&self, name: &str, arguments: &[Box<Expression>]) -> Result<String, String> {
+        let arguments = self.arguments(arguments)?;
+        if self.is_function(name) {
+            Ok(format!("{}(vec![{}])", identifier(name), arguments))
+        } else if BUILTINS.contains(&name) {
+            Ok(format!("grim::{}(vec![{}])", name, arguments))
+        } else if is_builtin(name) {
+            Err(format!("{} is not supported by the rust target", name))
+        } else {
+            Err(format!("Function `{}` does not exist", name))
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> Result<String, String> {
+        Ok(match expr {
+            Expression::Int(x) => format!("grim::Value::Int({})", x),
+            Expression::Float(x) => format!("grim::Value::Float({:?})", x),
+            Expression::Bool(x) => format!("grim::Value::Bool({})", x),
+            Expression::Str(x) => format!("grim::string({:?})", &x[1..x.len() - 1]),
+            Expression::Char(x) => format!("grim::Value::Char({:?})", x),
+            Expression::Identifier(name) | Expression::ResolvedIdentifier { name, .. } => {
+                match CONSTANTS.iter().find(|(constant, _)| constant == name) {
+                    Some((_, value)) => format!("grim::Value::Float({})", value),
+                    None => format!("{}.clone()", identifier(name)),
+                }
+            }
+            Expression::FunctionCall { name, arguments } => self.call(name, arguments)?,
+            Expression::BinaryOperation { lhs, operator, rhs } => {
+                let function = match operator {
+                    BinaryOperator::Add => "add",
+                    BinaryOperator::Sub => "sub",
+                    BinaryOperator::Mul => "mul",
+                    BinaryOperator::Div => "div",
+                    BinaryOperator::Mod => "rem",
+                    BinaryOperator::And => "and",
+                    BinaryOperator::Or => "or",
+                    BinaryOperator::Less => "less",
+                    BinaryOperator::Greater => "greater",
+                    BinaryOperator::LessEq => "less_eq",
+                    BinaryOperator::GreaterEq => "greater_eq",
+                    BinaryOperator::CompareEq => "equal",
+                    BinaryOperator::CompareNeq => "not_equal",
+                };
+                format!(
+                    "grim::{}({}, {})",
+                    function,
+                    self.expression(lhs)?,
+                    self.expression(rhs)?
+                )
+            }
+            Expression::UnaryOperation { operator, rhs } => {
+                let function = match operator {
+                    UnaryOperator::Not => "not",
+                    UnaryOperator::Minus => "neg",
+                };
+                format!("grim::{}({})", function, self.expression(rhs)?)
+            }
+            Expression::Index { target, index } => format!(
+                "grim::index({}, {})",
+                self.expression(target)?,
+                self.expression(index)?
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::grammar::ProgramParser;
+    use crate::parsing::lexer::Lexer;
+
+    fn transpiled(src: &str) -> Result<String, String> {
+        transpile_to_rust(&ProgramParser::new().parse(Lexer::new(src)).unwrap())
+    }
+
+    #[test]
+    fn generates_functions_and_statements() {
+        let program = transpiled(
+            "fn type (n: int) -> int { if n < 2 { return 1; } return n * type(n - 1); } \
+             let mut x: float = 2; x = type(3); printl(\"x is\"); assert(x == 6.0);",
+        )
+        .unwrap();
+        assert!(program.contains(
+            "    fn r#type(arguments_: Vec<grim::Value>) -> grim::Value {\n        \
+             let mut arguments_ = arguments_.into_iter();\n        \
+             let mut n = grim::annotate(\"n\", grim::argument(&mut arguments_, \"n\"), grim::Type::Int);\n"
+        ));
+        assert!(program.contains(
+            "            return grim::returned(\"type\", grim::Value::Int(1), grim::Type::Int);\n"
+        ));
+        assert!(program.contains(
+            "    x = grim::annotate(\"x\", r#type(vec![grim::Value::Int(3)]), grim::Type::Float);\n"
+        ));
+        assert!(program.contains("    grim::print_line(grim::string(\"x is\"));\n"));
+        assert!(program.contains(
+            "    grim::assert(vec![grim::equal(x.clone(), grim::Value::Float(6.0))]);\n"
+        ));
+        assert!(transpiled("fn f () -> { return g(); }").is_err());
+    }
+}
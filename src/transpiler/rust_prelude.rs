@@ -0,0 +1,528 @@
+// Runtime of the Rust programs generated from Grim scripts, appended as is to every program.
+// Values keep the dynamic types of Grim and the operators follow the rules of the interpreter.
+mod grim {
+    use std::fmt;
+    use std::io::{self, BufRead, Write};
+    use std::process;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, JoinHandle};
+    use std::time::Instant;
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Type {
+        Int,
+        Float,
+        Bool,
+        Str,
+        Channel,
+        Decimal,
+        Char,
+        Set,
+    }
+
+    impl fmt::Display for Type {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Type::Int => write!(f, "int"),
+                Type::Float => write!(f, "float"),
+                Type::Bool => write!(f, "bool"),
+                Type::Str => write!(f, "str"),
+                Type::Channel => write!(f, "channel"),
+                Type::Decimal => write!(f, "decimal"),
+                Type::Char => write!(f, "char"),
+                Type::Set => write!(f, "set"),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Channel {
+        sender: Sender<Value>,
+        receiver: Arc<Mutex<Receiver<Value>>>,
+    }
+
+    #[derive(Clone)]
+    pub enum Value {
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+        Str(Arc<str>),
+        Channel(Channel),
+        Char(char),
+    }
+
+    impl Value {
+        fn get_type(&self) -> Type {
+            match self {
+                Value::Int(_) => Type::Int,
+                Value::Float(_) => Type::Float,
+                Value::Bool(_) => Type::Bool,
+                Value::Str(_) => Type::Str,
+                Value::Channel(_) => Type::Channel,
+                Value::Char(_) => Type::Char,
+            }
+        }
+    }
+
+    impl PartialEq for Value {
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (Value::Int(x), Value::Int(y)) => x == y,
+                (Value::Float(x), Value::Float(y)) => x == y,
+                (Value::Bool(x), Value::Bool(y)) => x == y,
+                (Value::Str(x), Value::Str(y)) => x == y,
+                (Value::Channel(x), Value::Channel(y)) => Arc::ptr_eq(&x.receiver, &y.receiver),
+                (Value::Char(x), Value::Char(y)) => x == y,
+                _ => false,
+            }
+        }
+    }
+
+    impl fmt::Display for Value {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Value::Int(x) => write!(f, "{}", x),
+                Value::Float(x) => write!(f, "{}", x),
+                Value::Bool(x) => write!(f, "{}", x),
+                Value::Str(x) => write!(f, "{}", x),
+                Value::Channel(_) => write!(f, "channel"),
+                Value::Char(x) => write!(f, "{}", x),
+            }
+        }
+    }
+
+    /// Stop the program as the interpreter does on a runtime error.
+    pub fn fail(message: &str) -> ! {
+        let _ = io::stdout().flush();
+        println!("ERROR!\n{}", message);
+        process::exit(1)
+    }
+
+    fn incompatible(operation: &str, lhs: &Value, rhs: &Value) -> ! {
+        match (lhs, rhs) {
+            (Value::Channel(_), _) | (_, Value::Channel(_)) => {
+                fail("Channels are not supported by operators")
+            }
+            _ => fail(&format!(
+                "{} between incompatible types: {} and {}",
+                operation,
+                lhs.get_type(),
+                rhs.get_type()
+            )),
+        }
+    }
+
+    pub fn string(x: &str) -> Value {
+        Value::Str(Arc::from(x))
+    }
+
+    macro_rules! arithmetic {
+        ($name:ident, $op:tt, $operation:literal) => {
+            pub fn $name(lhs: Value, rhs: Value) -> Value {
+                match (&lhs, &rhs) {
+                    (Value::Int(x), Value::Int(y)) => Value::Int(x $op y),
+                    (Value::Int(x), Value::Float(y)) => Value::Float(*x as f64 $op y),
+                    (Value::Float(x), Value::Int(y)) => Value::Float(x $op *y as f64),
+                    (Value::Float(x), Value::Float(y)) => Value::Float(x $op y),
+                    _ => incompatible($operation, &lhs, &rhs),
+                }
+            }
+        };
+    }
+
+    arithmetic!(add_numbers, +, "Sum");
+
+    pub fn add(lhs: Value, rhs: Value) -> Value {
+        match (lhs, rhs) {
+            (Value::Str(x), Value::Str(y)) => string(&format!("{}{}", x, y)),
+            (lhs, rhs) => add_numbers(lhs, rhs),
+        }
+    }
+
+    /// Join strs with a single allocation.
+    pub fn concat(arguments: Vec<Value>) -> Value {
+        let mut joined = String::new();
+        for argument in &arguments {
+            match argument {
+                Value::Str(part) => joined.push_str(part),
+                _ => fail(&format!("concat expects strs, not {}", argument.get_type())),
+            }
+        }
+        string(&joined)
+    }
+    arithmetic!(sub, -, "Difference");
+    arithmetic!(mul, *, "Product");
+
+    pub fn div(lhs: Value, rhs: Value) -> Value {
+        match (&lhs, &rhs) {
+            (Value::Int(x), Value::Int(y)) if x % y == 0 => Value::Int(x / y),
+            (Value::Int(x), Value::Int(y)) => Value::Float(*x as f64 / *y as f64),
+            (Value::Int(x), Value::Float(y)) => Value::Float(*x as f64 / y),
+            (Value::Float(x), Value::Int(y)) => Value::Float(x / *y as f64),
+            (Value::Float(x), Value::Float(y)) => Value::Float(x / y),
+            _ => incompatible("Division", &lhs, &rhs),
+        }
+    }
+
+    pub fn rem(lhs: Value, rhs: Value) -> Value {
+        match (&lhs, &rhs) {
+            (Value::Int(x), Value::Int(y)) => Value::Int(x % y),
+            _ => incompatible("Modulo", &lhs, &rhs),
+        }
+    }
+
+    pub fn and(lhs: Value, rhs: Value) -> Value {
+        match (&lhs, &rhs) {
+            (Value::Bool(x), Value::Bool(y)) => Value::Bool(*x && *y),
+            _ => incompatible("Logical AND", &lhs, &rhs),
+        }
+    }
+
+    pub fn or(lhs: Value, rhs: Value) -> Value {
+        match (&lhs, &rhs) {
+            (Value::Bool(x), Value::Bool(y)) => Value::Bool(*x || *y),
+            _ => incompatible("Logical OR", &lhs, &rhs),
+        }
+    }
+
+    macro_rules! comparison {
+        ($name:ident, $op:tt, $operation:literal) => {
+            pub fn $name(lhs: Value, rhs: Value) -> Value {
+                match (&lhs, &rhs) {
+                    (Value::Int(x), Value::Int(y)) => Value::Bool(x $op y),
+                    (Value::Int(x), Value::Float(y)) => Value::Bool((*x as f64) $op *y),
+                    (Value::Float(x), Value::Int(y)) => Value::Bool(*x $op *y as f64),
+                    (Value::Float(x), Value::Float(y)) => Value::Bool(x $op y),
+                    (Value::Str(x), Value::Str(y)) => Value::Bool(x $op y),
+                    (Value::Char(x), Value::Char(y)) => Value::Bool(x $op y),
+                    _ => incompatible($operation, &lhs, &rhs),
+                }
+            }
+        };
+    }
+
+    comparison!(less, <, "Logical LESS");
+    comparison!(greater, >, "Logical GREATER");
+    comparison!(less_eq, <=, "Logical LEQ");
+    comparison!(greater_eq, >=, "Logical GEQ");
+
+    pub fn equal(lhs: Value, rhs: Value) -> Value {
+        match (&lhs, &rhs) {
+            (Value::Channel(_), _) | (_, Value::Channel(_)) => incompatible("Logical EQ", &lhs, &rhs),
+            (Value::Int(x), Value::Float(y)) => Value::Bool(*x as f64 == *y),
+            (Value::Float(x), Value::Int(y)) => Value::Bool(*x == *y as f64),
+            _ if lhs.get_type() == rhs.get_type() => Value::Bool(lhs == rhs),
+            _ => incompatible("Logical EQ", &lhs, &rhs),
+        }
+    }
+
+    pub fn not_equal(lhs: Value, rhs: Value) -> Value {
+        match (&lhs, &rhs) {
+            (Value::Channel(_), _) | (_, Value::Channel(_)) => incompatible("Logical NEQ", &lhs, &rhs),
+            (Value::Int(x), Value::Float(y)) => Value::Bool(*x as f64 != *y),
+            (Value::Float(x), Value::Int(y)) => Value::Bool(*x != *y as f64),
+            _ if lhs.get_type() == rhs.get_type() => Value::Bool(lhs != rhs),
+            _ => incompatible("Logical NEQ", &lhs, &rhs),
+        }
+    }
+
+    pub fn neg(rhs: Value) -> Value {
+        match rhs {
+            Value::Int(x) => Value::Int(-x),
+            Value::Float(x) => Value::Float(-x),
+            Value::Channel(_) => fail("Channels are not supported by operators"),
+            _ => fail(&format!("Minus {} is not supported", rhs.get_type())),
+        }
+    }
+
+    pub fn not(rhs: Value) -> Value {
+        match rhs {
+            Value::Bool(x) => Value::Bool(!x),
+            Value::Channel(_) => fail("Channels are not supported by operators"),
+            _ => fail(&format!("Not {} is not supported", rhs.get_type())),
+        }
+    }
+
+    /// Character of a string by code point.
+    pub fn index(target: Value, index: Value) -> Value {
+        match (&target, &index) {
+            (Value::Str(text), Value::Int(position)) => {
+                let length = text.chars().count();
+                let found = match *position < 0 {
+                    true => None,
+                    false => text.chars().nth(*position as usize),
+                };
+                match found {
+                    Some(c) => Value::Char(c),
+                    None => fail(&format!(
+                        "Index {} is out of a string of {} characters",
+                        position, length
+                    )),
+                }
+            }
+            (Value::Str(_), _) => fail(&format!(
+                "A string is indexed by an int, not by a {}",
+                index.get_type()
+            )),
+            _ => fail(&format!("Values of type {} cannot be indexed", target.get_type())),
+        }
+    }
+
+    pub fn condition(value: Value) -> bool {
+        match value {
+            Value::Bool(x) => x,
+            _ => fail(&format!("{} cannot be used as condition", value.get_type())),
+        }
+    }
+
+    /// Values taken by the variable of a for loop, the end is excluded.
+    pub fn range(start: Value, end: Value, step: Value) -> impl Iterator<Item = Value> {
+        let (start, end, step) = match (start, end, step) {
+            (Value::Int(_), Value::Int(_), Value::Int(0)) => {
+                fail("The step of a for loop cannot be 0")
+            }
+            (Value::Int(start), Value::Int(end), Value::Int(step)) => (start, end, step),
+            _ => fail("The bounds and the step of a for loop must be ints"),
+        };
+        std::iter::successors(Some(start), move |i| i.checked_add(step))
+            .take_while(move |i| if step > 0 { *i < end } else { *i > end })
+            .map(Value::Int)
+    }
+
+    /// Check a value against the type annotation of a variable, promoting ints to floats.
+    pub fn annotate(name: &str, value: Value, expected: Type) -> Value {
+        match (expected, value) {
+            (Type::Float, Value::Int(x)) => Value::Float(x as f64),
+            (expected, value) if expected == value.get_type() => value,
+            (expected, value) => fail(&format!(
+                "({}) is declared as {} but a {} is given",
+                name,
+                expected,
+                value.get_type()
+            )),
+        }
+    }
+
+    /// Check the value returned by a function against its return type.
+    pub fn returned(name: &str, value: Value, expected: Type) -> Value {
+        match (expected, value) {
+            (Type::Float, Value::Int(x)) => Value::Float(x as f64),
+            (expected, value) if expected == value.get_type() => value,
+            (expected, value) => fail(&format!(
+                "Function ({}) should return a {} but returned a {}",
+                name,
+                expected,
+                value.get_type()
+            )),
+        }
+    }
+
+    pub fn argument(arguments: &mut impl Iterator<Item = Value>, name: &str) -> Value {
+        match arguments.next() {
+            Some(value) => value,
+            None => fail(&format!("Variable `{}` does not exist", name)),
+        }
+    }
+
+    pub fn print(value: Value) {
+        print!("{}", value);
+        let _ = io::stdout().flush();
+    }
+
+    pub fn print_line(value: Value) {
+        println!("{}", value);
+    }
+
+    /// Read a line and parse it with the type of the variable it is assigned to.
+    pub fn input(name: &str, current: &Value) -> Value {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) => fail(&format!("End of the input, no line left to read `{}`", name)),
+            Ok(_) => {}
+            Err(err) => fail(&format!("Error during input statement {}", err)),
+        }
+        let line = line.trim();
+        let value = if let Ok(x) = line.parse::<i64>() {
+            Value::Int(x)
+        } else if let Ok(x) = line.parse::<f64>() {
+            Value::Float(x)
+        } else if let Ok(x) = line.parse::<bool>() {
+            Value::Bool(x)
+        } else {
+            string(line)
+        };
+        // An int is converted to a float, as the interpreter does
+        match (value, current.get_type()) {
+            (Value::Int(x), Type::Float) => Value::Float(x as f64),
+            (value, expected) if value.get_type() == expected => value,
+            (_, expected) => fail(&format!(
+                "Error of type incoherence, \"{}\" is of type {}",
+                name, expected
+            )),
+        }
+    }
+
+    pub fn assert(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [Value::Bool(true)] | [Value::Bool(true), Value::Str(_)] => Value::Bool(true),
+            [Value::Bool(false)] => fail("Assertion failed"),
+            [Value::Bool(false), Value::Str(message)] => {
+                fail(&format!("Assertion failed: {}", message))
+            }
+            _ => fail("assert expects a bool and optionally a str message"),
+        }
+    }
+
+    pub fn assert_eq(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [lhs, rhs] if lhs == rhs => Value::Bool(true),
+            [Value::Int(x), Value::Float(y)] | [Value::Float(y), Value::Int(x)] if *x as f64 == *y => {
+                Value::Bool(true)
+            }
+            [lhs, rhs] => fail(&format!(
+                "Assertion failed: {} ({}) is not equal to {} ({})",
+                lhs,
+                lhs.get_type(),
+                rhs,
+                rhs.get_type()
+            )),
+            _ => fail("assert_eq expects two arguments"),
+        }
+    }
+
+    pub fn channel(arguments: Vec<Value>) -> Value {
+        if !arguments.is_empty() {
+            fail("channel expects no arguments");
+        }
+        let (sender, receiver) = mpsc::channel();
+        Value::Channel(Channel {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        })
+    }
+
+    pub fn send(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [Value::Channel(channel), value] => match channel.sender.send(value.clone()) {
+                Ok(_) => Value::Bool(true),
+                Err(_) => fail("The channel is closed"),
+            },
+            _ => fail("send expects a channel and a value"),
+        }
+    }
+
+    pub fn recv(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [Value::Channel(channel)] => {
+                let receiver = match channel.receiver.lock() {
+                    Ok(receiver) => receiver,
+                    Err(_) => fail("The channel is closed"),
+                };
+                match receiver.recv() {
+                    Ok(value) => value,
+                    Err(_) => fail("The channel is closed"),
+                }
+            }
+            _ => fail("recv expects a channel"),
+        }
+    }
+
+    pub fn flush(arguments: Vec<Value>) -> Value {
+        if !arguments.is_empty() {
+            fail("flush expects no arguments");
+        }
+        let _ = io::stdout().flush();
+        Value::Bool(true)
+    }
+
+    /// Print to the standard error, after the pending output so both appear in order.
+    pub fn eprint(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [value] => {
+                let _ = io::stdout().flush();
+                eprint!("{}", value);
+                Value::Bool(true)
+            }
+            _ => fail("eprint expects one argument"),
+        }
+    }
+
+    pub fn eprintl(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [value] => {
+                let _ = io::stdout().flush();
+                eprintln!("{}", value);
+                Value::Bool(true)
+            }
+            _ => fail("eprintl expects one argument"),
+        }
+    }
+
+    /// End the program with an exit code, the main thread waits for the spawned functions first.
+    pub fn exit(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [Value::Int(code)] if *code >= i32::MIN as i64 && *code <= i32::MAX as i64 => {
+                if thread::current().name() == Some("main") {
+                    join_spawned();
+                }
+                let _ = io::stdout().flush();
+                process::exit(*code as i32)
+            }
+            [Value::Int(code)] => fail(&format!("The exit code {} does not fit in 32 bits", code)),
+            _ => fail("exit expects an int"),
+        }
+    }
+
+    /// End the program with an error, the generated programs have no call stack to show.
+    pub fn panic(arguments: Vec<Value>) -> Value {
+        match arguments.as_slice() {
+            [message] => fail(&format!("Panic: {}", message)),
+            _ => fail("panic expects one argument"),
+        }
+    }
+
+    /// Time of a measure block, printed when the block is left, by a return or a break as well.
+    pub struct Measure(&'static str, Instant);
+
+    pub fn measure(label: &'static str) -> Measure {
+        Measure(label, Instant::now())
+    }
+
+    impl Drop for Measure {
+        fn drop(&mut self) {
+            let elapsed = self.1.elapsed().as_secs_f64() * 1000.0;
+            let _ = io::stdout().flush();
+            eprintln!("{} took {:.3} ms", self.0, elapsed);
+        }
+    }
+
+    static SPAWNED: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+    /// Call a function on a new thread, the program waits for it before exiting.
+    pub fn spawn(function: fn(Vec<Value>) -> Value, arguments: Vec<Value>) {
+        let handle = thread::spawn(move || {
+            function(arguments);
+        });
+        if let Ok(mut spawned) = SPAWNED.lock() {
+            spawned.push(handle);
+        }
+    }
+
+    pub fn join_spawned() {
+        loop {
+            let handle = match SPAWNED.lock() {
+                Ok(mut spawned) => spawned.pop(),
+                Err(_) => None,
+            };
+            match handle {
+                Some(handle) => {
+                    if handle.join().is_err() {
+                        process::exit(1);
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::interpreter::interpreter::Interpreter;
+use crate::interpreter::io::{CallbackInput, CallbackOutput};
+use crate::language_runner::run_language::run_source;
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+/// Entry point of the web playground.
+///
+/// print is called with every piece of text printed by the program and with the error, if any,
+/// read_line is called for every input and returns a string, or undefined when the input is over.
+/// Return true if the program ended without errors.
+#[wasm_bindgen]
+pub fn run(src: &str, print: Function, read_line: Function) -> bool {
+    let print_output = print.clone();
+    let output = CallbackOutput(Box::new(move |text| {
+        let _ = print_output.call1(&JsValue::NULL, &JsValue::from_str(text));
+    }));
+    // The diagnostics are shown with the output, a page has no standard error
+    let print_error = print.clone();
+    let error_output = CallbackOutput(Box::new(move |text| {
+        let _ = print_error.call1(&JsValue::NULL, &JsValue::from_str(text));
+    }));
+    let input = CallbackInput(Box::new(move || {
+        read_line
+            .call0(&JsValue::NULL)
+            .ok()
+            .and_then(|line| line.as_string())
+    }));
+    let mut interpreter = Interpreter::builder()
+        .source(src)
+        .output(output)
+        .error_output(error_output)
+        .input(input)
+        .build();
+    match run_source(src, &mut interpreter) {
+        Ok(_) => true,
+        Err(err) => {
+            let _ = print.call1(&JsValue::NULL, &JsValue::from_str(&err));
+            false
+        }
+    }
+}